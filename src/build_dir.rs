@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use ignore::WalkBuilder;
+
+use crate::presets::{ConfigurePreset, load_configure_presets};
+
+/// The build directory used when it is neither configured nor detected
+pub const DEFAULT_BUILD_DIR: &str = "build";
+
+/// How deep the build directories are searched, enough for `out/build/<preset>`
+const SEARCH_DEPTH: usize = 4;
+
+const CMAKE_CACHE: &str = "CMakeCache.txt";
+
+/// The directories of the vendored sources and the dependencies, which are not built by the
+/// project itself
+const SKIPPED_DIRS: &[&str] = &[
+    "node_modules",
+    "vcpkg",
+    "vcpkg_installed",
+    "third_party",
+    "3rdparty",
+    "external",
+    "vendor",
+];
+
+/// Read the source directory which a CMakeCache.txt is generated from
+fn cache_home_directory(cache_file: &Path) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(cache_file).ok()?;
+    content.lines().find_map(|line| {
+        let (_, home) = line
+            .strip_prefix("CMAKE_HOME_DIRECTORY:")?
+            .split_once('=')?;
+        Some(PathBuf::from(home.trim()))
+    })
+}
+
+fn same_path(left: &Path, right: &Path) -> bool {
+    match (left.canonicalize(), right.canonicalize()) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+/// Find the directories under the root which contain a CMakeCache.txt of the project
+pub fn detect_build_dirs(root: &Path) -> Vec<PathBuf> {
+    // NOTE: the build directories are usually ignored by git, so the ignore files are not used
+    let mut dirs: Vec<PathBuf> = WalkBuilder::new(root)
+        .standard_filters(false)
+        .max_depth(Some(SEARCH_DEPTH))
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || SKIPPED_DIRS.contains(&&*name))
+        })
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_name() == CMAKE_CACHE)
+        .filter(|entry| {
+            cache_home_directory(entry.path()).is_some_and(|home| same_path(&home, root))
+        })
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// The build directories found in the project, or `<root>/build` when there is none
+fn build_dir_candidates(root: &Path) -> Vec<PathBuf> {
+    let detected = detect_build_dirs(root);
+    if detected.is_empty() {
+        return vec![root.join(DEFAULT_BUILD_DIR)];
+    }
+    detected
+}
+
+/// A build directory which the project can use, with the configure preset which generates it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildChoice {
+    /// The name shown to the user
+    pub title: String,
+    pub build_dir: PathBuf,
+    pub preset: Option<ConfigurePreset>,
+}
+
+impl BuildChoice {
+    fn from_dir(root: &Path, build_dir: PathBuf) -> Self {
+        Self {
+            title: build_dir
+                .strip_prefix(root)
+                .unwrap_or(&build_dir)
+                .display()
+                .to_string(),
+            build_dir,
+            preset: None,
+        }
+    }
+
+    fn from_preset(root: &Path, preset: ConfigurePreset) -> Self {
+        Self {
+            title: preset.name.clone(),
+            build_dir: preset
+                .binary_dir
+                .clone()
+                .unwrap_or_else(|| root.join(DEFAULT_BUILD_DIR)),
+            preset: Some(preset),
+        }
+    }
+
+    /// The cmake program, which can be set by the configure preset
+    pub fn cmake_executable(&self) -> &Path {
+        self.preset
+            .as_ref()
+            .and_then(|preset| preset.cmake_executable.as_deref())
+            .unwrap_or(Path::new("cmake"))
+    }
+
+    /// The ctest program, next to the cmake program
+    pub fn ctest_executable(&self) -> PathBuf {
+        let cmake = self.cmake_executable();
+        let mut ctest = cmake.with_file_name("ctest");
+        if let Some(extension) = cmake.extension() {
+            ctest.set_extension(extension);
+        }
+        ctest
+    }
+
+    /// The cache variables set by the configure preset
+    pub fn cache_variables(&self) -> HashMap<String, String> {
+        self.preset
+            .as_ref()
+            .map(|preset| preset.cache_variables.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the environment of the configure preset to the command
+    pub fn apply_environment(&self, command: &mut Command) {
+        let Some(preset) = &self.preset else {
+            return;
+        };
+        for (key, value) in &preset.environment {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+    }
+}
+
+/// The build directories which the project can use: the configured preset or directory, which is
+/// relative to the root. Without them, the presets of the project, or the detected directories
+pub fn build_choices(
+    root: &Path,
+    configured_dir: Option<&Path>,
+    configured_preset: Option<&str>,
+) -> Vec<BuildChoice> {
+    let presets = load_configure_presets(root);
+    if let Some(name) = configured_preset
+        && let Some(preset) = presets.iter().find(|preset| preset.name == name)
+    {
+        return vec![BuildChoice::from_preset(root, preset.clone())];
+    }
+    if let Some(configured_dir) = configured_dir {
+        return vec![BuildChoice::from_dir(root, root.join(configured_dir))];
+    }
+    if !presets.is_empty() {
+        return presets
+            .into_iter()
+            .map(|preset| BuildChoice::from_preset(root, preset))
+            .collect();
+    }
+    build_dir_candidates(root)
+        .into_iter()
+        .map(|build_dir| BuildChoice::from_dir(root, build_dir))
+        .collect()
+}
+
+/// The build directory configured most recently, before the user chooses one
+pub fn latest_choice(choices: &[BuildChoice]) -> Option<&BuildChoice> {
+    choices.iter().max_by_key(|choice| {
+        std::fs::metadata(choice.build_dir.join(CMAKE_CACHE))
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_cache(dir: &Path, home: &Path) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join(CMAKE_CACHE),
+            format!(
+                "CMAKE_BUILD_TYPE:STRING=Debug\nCMAKE_HOME_DIRECTORY:INTERNAL={}\n",
+                home.display()
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_detect_build_dirs() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write_cache(&root.join("build-debug"), root);
+        write_cache(&root.join("out").join("build").join("release"), root);
+        write_cache(
+            &root.join("third_party").join("build"),
+            &root.join("third_party"),
+        );
+        write_cache(&root.join("node_modules").join("build"), root);
+        write_cache(&root.join(".cache").join("build"), root);
+
+        assert_eq!(
+            detect_build_dirs(root),
+            vec![
+                root.join("build-debug"),
+                root.join("out").join("build").join("release"),
+            ]
+        );
+        let choices = build_choices(root, Some(Path::new("out/build/release")), None);
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].build_dir, root.join("out/build/release"));
+        assert_eq!(choices[0].title, "out/build/release");
+    }
+
+    #[test]
+    fn test_default_build_dir() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let choices = build_choices(root, None, None);
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].build_dir, root.join(DEFAULT_BUILD_DIR));
+        assert_eq!(choices[0].cmake_executable(), Path::new("cmake"));
+        assert_eq!(latest_choice(&[]), None);
+    }
+
+    #[test]
+    fn test_preset_choices() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write_cache(&root.join("build-debug"), root);
+        fs::write(
+            root.join(crate::presets::PRESETS_FILE),
+            r#"{
+  "version": 6,
+  "configurePresets": [
+    { "name": "debug", "binaryDir": "${sourceDir}/out/debug" },
+    { "name": "release", "binaryDir": "${sourceDir}/out/release" }
+  ]
+}"#,
+        )
+        .unwrap();
+
+        let titles = |choices: Vec<BuildChoice>| -> Vec<String> {
+            choices.into_iter().map(|choice| choice.title).collect()
+        };
+        assert_eq!(
+            titles(build_choices(root, None, None)),
+            vec!["debug", "release"]
+        );
+
+        let choices = build_choices(root, Some(Path::new("build-debug")), Some("release"));
+        assert_eq!(titles(choices.clone()), vec!["release"]);
+        assert_eq!(choices[0].build_dir, root.join("out").join("release"));
+
+        let choices = build_choices(root, Some(Path::new("build-debug")), Some("unknown"));
+        assert_eq!(choices[0].build_dir, root.join("build-debug"));
+        assert_eq!(choices[0].preset, None);
+    }
+}
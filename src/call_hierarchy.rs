@@ -0,0 +1,296 @@
+use std::path::Path;
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{
+    CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall, Location, Position,
+    Range as LspRange, SymbolKind, Uri,
+};
+use tree_sitter::Point;
+
+use crate::document::{Analysis, DefinitionIndex, Document};
+use crate::jump::{ReferenceInfo, get_cached_def, reference_all};
+use crate::languageserver::get_or_update_document;
+use crate::utils::query::ToLspRange;
+use crate::utils::treehelper::{ToPoint, location_in_range};
+
+/// A `function()` or `macro()` of the analysis, with the name of its kind
+type Definition<'a> = (&'a DefinitionIndex, &'static str);
+
+fn definitions(analysis: &Analysis) -> impl Iterator<Item = Definition<'_>> {
+    analysis
+        .functions
+        .iter()
+        .map(|fun| (fun, "function"))
+        .chain(analysis.macros.iter().map(|mac| (mac, "macro")))
+}
+
+fn definition_item((def, kind): Definition, uri: &Uri) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: def.name.clone(),
+        kind: SymbolKind::Function,
+        tags: None,
+        detail: Some(kind.to_string()),
+        uri: uri.clone(),
+        range: def.range.lsp_range(),
+        selection_range: def.name_range.lsp_range(),
+        data: None,
+    }
+}
+
+/// The calls at the top level of a file are made by the file itself
+fn file_item(document: &Document, path: &Path) -> CallHierarchyItem {
+    let range = document.tree().root_node().range().lsp_range();
+    CallHierarchyItem {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        kind: SymbolKind::File,
+        tags: None,
+        detail: Some(path.display().to_string()),
+        uri: Uri::from_file_path(path).unwrap(),
+        range,
+        selection_range: range,
+        data: None,
+    }
+}
+
+/// Find the innermost definition which contains the point
+fn enclosing_definition(analysis: &Analysis, point: Point) -> Option<Definition<'_>> {
+    definitions(analysis)
+        .filter(|(def, _)| location_in_range(point, def.range))
+        .max_by_key(|(def, _)| def.range.start_byte)
+}
+
+/// Get the item of the definition at a location, which is the name of a definition
+async fn location_item(
+    location: &Location,
+    documents: &DashMap<Uri, Document>,
+) -> Option<CallHierarchyItem> {
+    let path = location.uri.to_file_path().ok()?;
+    let document = get_or_update_document(&path, documents).await.ok()?;
+    definitions(document.analysis())
+        .find(|(def, _)| def.name_range.lsp_range() == location.range)
+        .map(|definition| definition_item(definition, &location.uri))
+}
+
+pub async fn prepare_call_hierarchy<P: AsRef<Path>>(
+    document: &Document,
+    position: Position,
+    path: P,
+    documents: &DashMap<Uri, Document>,
+) -> Option<Vec<CallHierarchyItem>> {
+    let point = position.to_point();
+    let analysis = document.analysis();
+    if let Some(definition) =
+        definitions(analysis).find(|(def, _)| location_in_range(point, def.name_range))
+    {
+        let uri = Uri::from_file_path(path.as_ref()).unwrap();
+        return Some(vec![definition_item(definition, &uri)]);
+    }
+    let command = analysis
+        .commands
+        .iter()
+        .find(|command| location_in_range(point, command.identifier_range))?;
+    let ReferenceInfo {
+        is_function: true,
+        loc,
+    } = get_cached_def(path, &command.identifier, documents).await?
+    else {
+        return None;
+    };
+    Some(vec![location_item(&loc, documents).await?])
+}
+
+pub async fn incoming_calls(
+    item: &CallHierarchyItem,
+    documents: &DashMap<Uri, Document>,
+) -> Option<Vec<CallHierarchyIncomingCall>> {
+    let path = item.uri.to_file_path().ok()?;
+    let references = reference_all(&path, &item.name, true, documents).await;
+    let calls = incoming_calls_from(&references, documents).await;
+    if calls.is_empty() {
+        return None;
+    }
+    Some(calls)
+}
+
+/// Group the calls among the references by the definition or the file making them
+async fn incoming_calls_from(
+    references: &[Location],
+    documents: &DashMap<Uri, Document>,
+) -> Vec<CallHierarchyIncomingCall> {
+    let mut calls: Vec<CallHierarchyIncomingCall> = vec![];
+    for reference in references {
+        let Ok(path) = reference.uri.to_file_path() else {
+            continue;
+        };
+        let Ok(document) = get_or_update_document(&path, documents).await else {
+            continue;
+        };
+        let analysis = document.analysis();
+        // NOTE: the references have the definitions too, which are not calls
+        let Some(command) = analysis
+            .commands
+            .iter()
+            .find(|command| command.identifier_range.lsp_range() == reference.range)
+        else {
+            continue;
+        };
+        let from = match enclosing_definition(analysis, command.identifier_range.start_point) {
+            Some(definition) => definition_item(definition, &reference.uri),
+            None => file_item(&document, &path),
+        };
+        match calls.iter_mut().find(|call| call.from == from) {
+            Some(call) => call.from_ranges.push(reference.range),
+            None => calls.push(CallHierarchyIncomingCall {
+                from,
+                from_ranges: vec![reference.range],
+            }),
+        }
+    }
+    calls
+}
+
+pub async fn outgoing_calls(
+    item: &CallHierarchyItem,
+    documents: &DashMap<Uri, Document>,
+) -> Option<Vec<CallHierarchyOutgoingCall>> {
+    let path = item.uri.to_file_path().ok()?;
+    let document = get_or_update_document(&path, documents).await.ok()?;
+    let analysis = document.analysis();
+    let (def, _) = find_definition(analysis, item.selection_range)?;
+
+    let mut calls: Vec<CallHierarchyOutgoingCall> = vec![];
+    for command in analysis
+        .commands
+        .iter()
+        .filter(|command| location_in_range(command.range.start_point, def.range))
+    {
+        let Some(ReferenceInfo {
+            is_function: true,
+            loc,
+        }) = get_cached_def(&path, &command.identifier, documents).await
+        else {
+            continue;
+        };
+        let Some(to) = location_item(&loc, documents).await else {
+            continue;
+        };
+        let range = command.identifier_range.lsp_range();
+        match calls.iter_mut().find(|call| call.to == to) {
+            Some(call) => call.from_ranges.push(range),
+            None => calls.push(CallHierarchyOutgoingCall {
+                to,
+                from_ranges: vec![range],
+            }),
+        }
+    }
+    if calls.is_empty() {
+        return None;
+    }
+    Some(calls)
+}
+
+fn find_definition(analysis: &Analysis, selection_range: LspRange) -> Option<Definition<'_>> {
+    definitions(analysis).find(|(def, _)| def.name_range.lsp_range() == selection_range)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::scansubs::TREE_CMAKE_MAP;
+
+    const HELPER_SRC: &str = r"function(call_hierarchy_leaf)
+endfunction()
+macro(call_hierarchy_middle)
+  call_hierarchy_leaf()
+  message(STATUS middle)
+  CALL_HIERARCHY_LEAF()
+endmacro()
+";
+
+    const TOP_SRC: &str = r"include(helper.cmake)
+call_hierarchy_middle()
+function(call_hierarchy_top)
+  call_hierarchy_middle()
+endfunction()
+";
+
+    #[tokio::test]
+    async fn test_call_hierarchy() {
+        let dir = tempdir().unwrap();
+        let helper_path = dir.path().join("helper.cmake");
+        let top_path = dir.path().join("CMakeLists.txt");
+        let documents = DashMap::new();
+        documents.insert(
+            Uri::from_file_path(&helper_path).unwrap(),
+            Document::new(HELPER_SRC).with_version(1),
+        );
+        documents.insert(
+            Uri::from_file_path(&top_path).unwrap(),
+            Document::new(TOP_SRC).with_version(1),
+        );
+
+        let helper = documents
+            .get(&Uri::from_file_path(&helper_path).unwrap())
+            .unwrap()
+            .clone();
+        let items = prepare_call_hierarchy(
+            &helper,
+            Position {
+                line: 2,
+                character: 8,
+            },
+            &helper_path,
+            &documents,
+        )
+        .await
+        .unwrap();
+        assert_eq!(items.len(), 1);
+        let middle = &items[0];
+        assert_eq!(middle.name, "call_hierarchy_middle");
+        assert_eq!(middle.detail.as_deref(), Some("macro"));
+
+        TREE_CMAKE_MAP
+            .lock()
+            .await
+            .insert(helper_path.clone(), vec![top_path.clone()]);
+        let incoming = incoming_calls(middle, &documents).await.unwrap();
+        let callers: Vec<(&str, usize)> = incoming
+            .iter()
+            .map(|call| (call.from.name.as_str(), call.from_ranges.len()))
+            .collect();
+        assert_eq!(
+            callers,
+            vec![("CMakeLists.txt", 1), ("call_hierarchy_top", 1)]
+        );
+
+        let outgoing = outgoing_calls(middle, &documents).await.unwrap();
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].to.name, "call_hierarchy_leaf");
+        assert_eq!(outgoing[0].from_ranges.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_on_builtin() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("CMakeLists.txt");
+        let document = Document::new("message(STATUS hello)\n");
+        let documents = DashMap::new();
+        documents.insert(Uri::from_file_path(&path).unwrap(), document.clone());
+        let items = prepare_call_hierarchy(
+            &document,
+            Position {
+                line: 0,
+                character: 2,
+            },
+            &path,
+            &documents,
+        )
+        .await;
+        assert_eq!(items, None);
+    }
+}
@@ -72,6 +72,14 @@ pub enum Command {
         #[arg(default_value = ".", value_hint = ValueHint::AnyPath)]
         path: PathBuf,
 
+        /// The build directory, detected when it is not given.
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        build_dir: Option<PathBuf>,
+
+        /// The configure preset of CMakePresets.json, which sets the build directory.
+        #[arg(long)]
+        preset: Option<String>,
+
         #[arg(long)]
         args: Vec<String>,
     },
@@ -81,6 +89,14 @@ pub enum Command {
         /// File to start with.
         #[arg(default_value = ".", value_hint = ValueHint::AnyPath)]
         path: PathBuf,
+
+        /// The build directory, detected when it is not given.
+        #[arg(long, value_hint = ValueHint::DirPath)]
+        build_dir: Option<PathBuf>,
+
+        /// The configure preset of CMakePresets.json, which sets the build directory.
+        #[arg(long)]
+        preset: Option<String>,
     },
 }
 
@@ -1,5 +1,8 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
+use crate::build_dir::{BuildChoice, build_choices};
+use crate::config;
 use crate::fileapi::{
     self,
     target::{TARGET_REGEX, Target, TargetType},
@@ -12,11 +15,11 @@ pub enum HelperKind {
     Build,
 }
 
-fn get_target_info(target: Option<String>, kind: HelperKind) -> Option<Target> {
+fn get_target_info(target: Option<String>, dir: &Path, kind: HelperKind) -> Option<Target> {
     match target {
-        Some(name) => fileapi::get_target_data(&name),
+        Some(name) => fileapi::get_target_data(dir, &name),
         None => {
-            let data = fileapi::get_all_targets()?;
+            let data = fileapi::get_targets(dir)?;
             let data_vec: Vec<&Target> = data
                 .values()
                 .filter(|target| match kind {
@@ -34,8 +37,12 @@ fn get_target_info(target: Option<String>, kind: HelperKind) -> Option<Target> {
         }
     }
 }
-pub fn prepare_helper(target: Option<String>, dir: PathBuf, kind: HelperKind) -> Option<Target> {
-    let build_dir = dir.join("build");
+pub fn prepare_helper(
+    target: Option<String>,
+    dir: &Path,
+    build_dir: &Path,
+    kind: HelperKind,
+) -> Option<Target> {
     let cache_path = build_dir
         .join(".cmake")
         .join("api")
@@ -53,43 +60,129 @@ pub fn prepare_helper(target: Option<String>, dir: PathBuf, kind: HelperKind) ->
                     let file_name = file_name.to_string_lossy().to_string();
 
                     if TARGET_REGEX.is_match(&file_name) {
-                        fileapi::update_target_data(file_path);
+                        fileapi::update_target_data(dir, file_path);
                     }
                 }
             }
         }
     }
-    get_target_info(target, kind)
+    get_target_info(target, dir, kind)
 }
-pub fn help_build(target: Option<String>, dir: PathBuf) -> anyhow::Result<()> {
-    let Some(target_info) = prepare_helper(target, dir, HelperKind::Build) else {
-        eprintln!("cannot find target");
-        return Ok(());
+
+/// Find the build directory of the cli: the given preset or directory, the ones of
+/// `.neocmake.toml`, or the presets and the detected directories, the user chooses when there
+/// are several
+fn choose_build(
+    dir: &Path,
+    build_dir: Option<PathBuf>,
+    preset: Option<String>,
+) -> Option<BuildChoice> {
+    config::load_project_config(dir);
+    let config = config::get_config(dir);
+    let build_dir = build_dir.or_else(|| config.build_directory.clone());
+    let preset = preset.or_else(|| config.configure_preset.clone());
+    let choices = build_choices(dir, build_dir.as_deref(), preset.as_deref());
+    if choices.len() < 2 {
+        return choices.into_iter().next();
+    }
+    let titles: Vec<&str> = choices.iter().map(|choice| choice.title.as_str()).collect();
+    let index = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Choose build directory")
+        .default(0)
+        .items(&titles)
+        .interact()
+        .ok()?;
+    choices.into_iter().nth(index)
+}
+
+/// The `cmake --build` command of a target, shared by the cli and the code lenses
+pub fn build_command(
+    target: Option<String>,
+    dir: &Path,
+    build: &BuildChoice,
+) -> anyhow::Result<Command> {
+    let Some(target_info) = prepare_helper(target, dir, &build.build_dir, HelperKind::Build) else {
+        anyhow::bail!("cannot find target");
     };
 
-    let mut command = std::process::Command::new("cmake")
+    let mut command = Command::new(build.cmake_executable());
+    build.apply_environment(&mut command);
+    command
         .arg("--build")
-        .arg("build")
+        .arg(&build.build_dir)
         .arg("--target")
-        .arg(target_info.name)
-        .spawn()?;
-    command.wait()?;
-    Ok(())
+        .arg(target_info.name);
+    Ok(command)
 }
 
-pub fn help_run(target: Option<String>, dir: PathBuf, args: Vec<String>) -> anyhow::Result<()> {
-    let build_dir = dir.join("build");
-    let Some(target_info) = prepare_helper(target, dir, HelperKind::Run) else {
-        eprintln!("cannot find target");
-        return Ok(());
+/// The command to run the artifact of a target, shared by the cli and the code lenses
+pub fn run_command(
+    target: Option<String>,
+    dir: &Path,
+    build: &BuildChoice,
+    args: Vec<String>,
+) -> anyhow::Result<Command> {
+    let Some(target_info) = prepare_helper(target, dir, &build.build_dir, HelperKind::Run) else {
+        anyhow::bail!("cannot find target");
     };
 
     let Some(artifact) = target_info.info.artifacts().first() else {
-        eprintln!("target does not contain a runnable path");
+        anyhow::bail!("target does not contain a runnable path");
+    };
+    let mut command = Command::new(build.build_dir.join(&artifact.path));
+    build.apply_environment(&mut command);
+    command.args(args);
+    Ok(command)
+}
+
+/// The `ctest` command running one test, with its arguments and working directory
+pub fn test_command(name: &str, build: &BuildChoice) -> Command {
+    let mut command = Command::new(build.ctest_executable());
+    build.apply_environment(&mut command);
+    command
+        .current_dir(&build.build_dir)
+        .arg("-R")
+        .arg(format!("^{}$", regex::escape(name)));
+    command
+}
+
+pub fn help_build(
+    target: Option<String>,
+    dir: PathBuf,
+    build_dir: Option<PathBuf>,
+    preset: Option<String>,
+) -> anyhow::Result<()> {
+    let Some(build) = choose_build(&dir, build_dir, preset) else {
+        return Ok(());
+    };
+    let mut command = match build_command(target, &dir, &build) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{e}");
+            return Ok(());
+        }
+    };
+    command.spawn()?.wait()?;
+    Ok(())
+}
+
+pub fn help_run(
+    target: Option<String>,
+    dir: PathBuf,
+    build_dir: Option<PathBuf>,
+    preset: Option<String>,
+    args: Vec<String>,
+) -> anyhow::Result<()> {
+    let Some(build) = choose_build(&dir, build_dir, preset) else {
         return Ok(());
     };
-    let target_path = build_dir.join(&artifact.path);
-    let mut command = std::process::Command::new(target_path).args(args).spawn()?;
-    command.wait()?;
+    let mut command = match run_command(target, &dir, &build, args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{e}");
+            return Ok(());
+        }
+    };
+    command.spawn()?.wait()?;
     Ok(())
 }
@@ -0,0 +1,371 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use dashmap::DashMap;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tower_lsp::jsonrpc::{Error as LspError, Result};
+use tower_lsp::lsp_types::{CodeLens, Command, Location, LspAny, MessageType, Uri};
+
+use crate::build_dir::BuildChoice;
+use crate::cmake_tool;
+use crate::document::{Analysis, CommandIndex, Document};
+use crate::languageserver::read_document;
+use crate::scansubs::scanned_files;
+use crate::utils::query::ToLspRange;
+
+pub const BUILD_COMMAND: &str = "neocmake.build";
+pub const RUN_COMMAND: &str = "neocmake.run";
+pub const TEST_COMMAND: &str = "neocmake.test";
+/// The command of the reference counts, its arguments are the uri, the position and the locations
+/// of the references, like the show references action of the clients
+pub const REFERENCES_COMMAND: &str = "neocmake.references";
+
+/// These libraries are not built by the project
+const NOT_BUILT_LIBRARIES: &[&str] = &["ALIAS", "IMPORTED", "INTERFACE"];
+
+pub async fn get_code_lenses<P: Into<PathBuf>>(
+    document: &Document,
+    path: P,
+    documents: &DashMap<Uri, Document>,
+) -> Option<Vec<CodeLens>> {
+    let analysis = document.analysis();
+    let mut paths = scanned_files().await;
+    let path = path.into();
+    let uri = Uri::from_file_path(&path).ok()?;
+    if !paths.contains(&path) {
+        paths.push(path.clone());
+    }
+    let mut files = vec![];
+    for path in paths {
+        if let (Ok(document), Ok(uri)) = (
+            read_document(&path, documents).await,
+            Uri::from_file_path(&path),
+        ) {
+            files.push((uri, document));
+        }
+    }
+
+    let mut lenses = reference_lenses(analysis, &files, &uri);
+    lenses.append(&mut target_lenses(analysis, &path));
+    if lenses.is_empty() {
+        return None;
+    }
+    Some(lenses)
+}
+
+/// Count the calls of every function and macro defined in the analysis
+fn reference_lenses(analysis: &Analysis, files: &[(Uri, Document)], uri: &Uri) -> Vec<CodeLens> {
+    analysis
+        .functions
+        .iter()
+        .chain(analysis.macros.iter())
+        .map(|def| {
+            // NOTE: the commands are case insensitive
+            let locations: Vec<Location> = files
+                .iter()
+                .flat_map(|(uri, file)| {
+                    file.analysis()
+                        .commands
+                        .iter()
+                        .filter(|command| command.identifier.eq_ignore_ascii_case(&def.name))
+                        .map(|command| Location {
+                            uri: uri.clone(),
+                            range: command.identifier_range.lsp_range(),
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            let count = locations.len();
+            let title = if count == 1 {
+                "1 reference".to_string()
+            } else {
+                format!("{count} references")
+            };
+            let range = def.name_range.lsp_range();
+            CodeLens {
+                range,
+                command: Some(Command {
+                    title,
+                    tooltip: None,
+                    command: REFERENCES_COMMAND.to_string(),
+                    arguments: Some(vec![
+                        Value::String(uri.to_string()),
+                        serde_json::to_value(range.start).unwrap(),
+                        serde_json::to_value(locations).unwrap(),
+                    ]),
+                }),
+                data: None,
+            }
+        })
+        .collect()
+}
+
+/// What a code lens of a command does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LensKind {
+    /// Build the target, and run it when it is an executable
+    Target { runnable: bool },
+    /// Run the test with ctest
+    Test,
+}
+
+/// Get the target or the test of a command
+fn command_target(command: &CommandIndex) -> Option<(&str, LensKind)> {
+    let identifier = command.identifier.to_lowercase();
+    match identifier.as_str() {
+        "add_executable" | "add_library" => {
+            let name = command.first_arg()?;
+            let built = !command
+                .args
+                .iter()
+                .any(|arg| NOT_BUILT_LIBRARIES.contains(&arg.content.as_str()));
+            built.then_some((
+                name,
+                LensKind::Target {
+                    runnable: identifier == "add_executable",
+                },
+            ))
+        }
+        "add_test" => {
+            // NOTE: add_test(NAME <name> COMMAND <command>) or add_test(<name> <command>)
+            let name = if command.first_arg()? == "NAME" {
+                command.args.get(1)?
+            } else {
+                command.args.first()?
+            };
+            Some((name.content.as_str(), LensKind::Test))
+        }
+        _ => None,
+    }
+}
+
+/// The build, run and test actions, the file tells which project builds them
+fn target_lenses(analysis: &Analysis, path: &Path) -> Vec<CodeLens> {
+    let mut lenses = vec![];
+    for command in &analysis.commands {
+        let Some((target, kind)) = command_target(command) else {
+            continue;
+        };
+        let range = command.identifier_range.lsp_range();
+        let lens = |title: &str, command: &str| CodeLens {
+            range,
+            command: Some(Command {
+                title: title.to_string(),
+                tooltip: Some(format!("{title} {target}")),
+                command: command.to_string(),
+                arguments: Some(vec![
+                    Value::String(target.to_string()),
+                    Value::String(path.to_string_lossy().to_string()),
+                ]),
+            }),
+            data: None,
+        };
+        match kind {
+            LensKind::Target { runnable } => {
+                lenses.push(lens("Build", BUILD_COMMAND));
+                if runnable {
+                    lenses.push(lens("Run", RUN_COMMAND));
+                }
+            }
+            LensKind::Test => lenses.push(lens("Test", TEST_COMMAND)),
+        }
+    }
+    lenses
+}
+
+/// Send the lines of the output to the log of the client as they arrive
+async fn log_lines<R: AsyncRead + Unpin>(client: &tower_lsp::Client, output: Option<R>) {
+    let Some(output) = output else {
+        return;
+    };
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        client.log_message(MessageType::Info, line).await;
+    }
+}
+
+/// Build, run or test the target of a code lens, the output is sent to the log of the client
+///
+/// NOTE: the build or the program can run for long, so the request returns once it is started
+pub async fn execute_command(
+    client: &tower_lsp::Client,
+    command: &str,
+    arguments: &[LspAny],
+    project: Option<(&Path, &BuildChoice)>,
+) -> Result<Option<LspAny>> {
+    // NOTE: the references are in the arguments, they are sent back for the clients which show
+    // the result of the command
+    if command == REFERENCES_COMMAND {
+        return Ok(arguments.get(2).cloned());
+    }
+    let Some((root_path, build)) = project else {
+        client
+            .show_message(MessageType::Warning, "No project root to build the target")
+            .await;
+        return Ok(None);
+    };
+    let Some(target) = arguments.first().and_then(Value::as_str) else {
+        return Err(LspError::invalid_params("the target name is required"));
+    };
+    let process = match command {
+        BUILD_COMMAND => cmake_tool::build_command(Some(target.to_string()), root_path, build),
+        RUN_COMMAND => {
+            cmake_tool::run_command(Some(target.to_string()), root_path, build, Vec::new())
+        }
+        TEST_COMMAND => Ok(cmake_tool::test_command(target, build)),
+        _ => return Err(LspError::method_not_found()),
+    };
+    let process = match process {
+        Ok(process) => process,
+        Err(e) => {
+            client
+                .show_message(MessageType::Error, format!("{target}: {e}"))
+                .await;
+            return Ok(None);
+        }
+    };
+    let mut child = match tokio::process::Command::from(process)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            client
+                .show_message(MessageType::Error, format!("{target}: {e}"))
+                .await;
+            return Ok(None);
+        }
+    };
+    let client = client.clone();
+    let target = target.to_string();
+    tokio::spawn(async move {
+        tokio::join!(
+            log_lines(&client, child.stdout.take()),
+            log_lines(&client, child.stderr.take())
+        );
+        match child.wait().await {
+            Ok(status) if status.success() => {
+                client
+                    .show_message(MessageType::Info, format!("{target}: finished"))
+                    .await;
+            }
+            Ok(status) => {
+                client
+                    .show_message(
+                        MessageType::Error,
+                        format!("{target}: failed with {status}"),
+                    )
+                    .await;
+            }
+            Err(e) => {
+                client
+                    .show_message(MessageType::Error, format!("{target}: {e}"))
+                    .await;
+            }
+        }
+    });
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Position;
+
+    use super::*;
+
+    fn titles(lenses: &[CodeLens]) -> Vec<(u32, String, Option<Value>)> {
+        lenses
+            .iter()
+            .map(|lens| {
+                let command = lens.command.as_ref().unwrap();
+                (
+                    lens.range.start.line,
+                    command.title.clone(),
+                    command
+                        .arguments
+                        .as_ref()
+                        .and_then(|arguments| arguments.first().cloned()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reference_lenses() {
+        let document = Document::new(
+            r"function(hello)
+endfunction()
+macro(world)
+endmacro()
+hello()
+HELLO()
+",
+        );
+        let other = Document::new("hello()\nworld()\n");
+        let uri = Uri::from_file_path("/project/CMakeLists.txt").unwrap();
+        let other_uri = Uri::from_file_path("/project/other.cmake").unwrap();
+        let lenses = reference_lenses(
+            document.analysis(),
+            &[(uri.clone(), document.clone()), (other_uri.clone(), other)],
+            &uri,
+        );
+        let uri = Some(Value::String(uri.to_string()));
+        assert_eq!(
+            titles(&lenses),
+            vec![
+                (0, "3 references".to_string(), uri.clone()),
+                (2, "1 reference".to_string(), uri),
+            ]
+        );
+        assert!(
+            lenses
+                .iter()
+                .all(|lens| lens.command.as_ref().unwrap().command == REFERENCES_COMMAND)
+        );
+        let arguments = lenses[1]
+            .command
+            .as_ref()
+            .unwrap()
+            .arguments
+            .clone()
+            .unwrap();
+        let locations: Vec<Location> = serde_json::from_value(arguments[2].clone()).unwrap();
+        assert_eq!(
+            locations,
+            vec![Location {
+                uri: other_uri,
+                range: tower_lsp::lsp_types::Range::new(Position::new(1, 0), Position::new(1, 5)),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_target_lenses() {
+        let document = Document::new(
+            r"add_executable(app main.cpp)
+add_library(lib STATIC lib.cpp)
+add_library(alias ALIAS lib)
+add_library(iface INTERFACE)
+add_test(NAME app_test COMMAND app --check)
+add_test(old_test app)
+",
+        );
+        let target = |name: &str| Some(Value::String(name.to_string()));
+        assert_eq!(
+            titles(&target_lenses(
+                document.analysis(),
+                Path::new("CMakeLists.txt")
+            )),
+            vec![
+                (0, "Build".to_string(), target("app")),
+                (0, "Run".to_string(), target("app")),
+                (1, "Build".to_string(), target("lib")),
+                (4, "Test".to_string(), target("app_test")),
+                (5, "Test".to_string(), target("old_test")),
+            ]
+        );
+    }
+}
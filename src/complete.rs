@@ -6,7 +6,7 @@ use std::fs::read_dir;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
 
-use builtin::{BUILTIN_COMMAND, BUILTIN_MODULE, BUILTIN_VARIABLE};
+use builtin::{BUILTIN_MODULE, BUILTIN_VARIABLE, builtin_commands};
 use dashmap::DashMap;
 use glob::glob;
 use tokio::sync::Mutex;
@@ -16,18 +16,12 @@ use tower_lsp::lsp_types::{
 };
 
 use crate::config::CommandCase;
-use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::document::{Analysis, ArgumentIndex, Document, StartBefore};
 use crate::fileapi;
-use crate::languageserver::get_or_update_buffer_contents;
+use crate::languageserver::get_or_update_document;
 use crate::scansubs::TREE_MAP;
-use crate::utils::query::{
-    FunMarcoArg, get_bracket_comments, get_functions, get_line_comments, get_macros,
-    get_normal_commands,
-};
-use crate::utils::treehelper::{CurrentNodeInfo, PositionType, ToPoint, location_range_contain};
-use crate::utils::{
-    CACHE_CMAKE_PACKAGES_WITHKEYS, NeoStrExt, gen_module_pattern, include_is_module,
-};
+use crate::utils::treehelper::{CurrentNodeInfo, PositionType, ToPoint, location_in_range};
+use crate::utils::{NeoStrExt, find_cmake_package, gen_module_pattern, include_is_module};
 
 pub type CompleteKV = HashMap<PathBuf, Vec<CompletionItem>>;
 
@@ -43,7 +37,7 @@ static LABEL_FILITER: LazyLock<regex::Regex> =
 const PKG_IMPORT_TARGET: &str = "IMPORTED_TARGET";
 
 pub fn init_builtin_command() {
-    let _ = &*BUILTIN_COMMAND;
+    let _ = builtin_commands();
 }
 pub fn init_builtin_module() {
     let _ = &*BUILTIN_MODULE;
@@ -132,14 +126,9 @@ pub fn rst_doc_read(doc: &str, filename: &str) -> Vec<CompletionItem> {
         .collect()
 }
 
-pub async fn update_cache<P: AsRef<Path>>(path: P, context: &str) -> Vec<CompletionItem> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(context, None);
-    let tree = thetree.unwrap();
+pub async fn update_cache<P: AsRef<Path>>(path: P, document: &Document) -> Vec<CompletionItem> {
     let Some(result_data) = getsubcomplete(
-        tree.root_node(),
-        context,
+        document.analysis(),
         path.as_ref(),
         PositionType::VarOrFun,
         None,
@@ -157,7 +146,7 @@ pub async fn update_cache<P: AsRef<Path>>(path: P, context: &str) -> Vec<Complet
 
 pub async fn get_cached_completion<P: AsRef<Path>>(
     path: P,
-    documents: &DashMap<Uri, String>,
+    documents: &DashMap<Uri, Document>,
 ) -> Vec<CompletionItem> {
     let mut path = path.as_ref().to_path_buf();
     let mut completions = Vec::new();
@@ -168,9 +157,9 @@ pub async fn get_cached_completion<P: AsRef<Path>>(
         let complete_cache = COMPLETE_CACHE.lock().await;
         if let Some(data) = complete_cache.get(parent) {
             completions.extend(data.clone());
-        } else if let Ok(context) = get_or_update_buffer_contents(parent, documents).await {
+        } else if let Ok(document) = get_or_update_document(parent, documents).await {
             drop(complete_cache);
-            completions.extend(update_cache(parent, context.as_str()).await);
+            completions.extend(update_cache(parent, &document).await);
             path.clone_from(parent);
             continue;
         }
@@ -198,48 +187,45 @@ impl From<Option<String>> for TriggerType {
 
 /// get the complete messages
 pub async fn getcomplete<P: AsRef<Path>>(
-    source: &str,
+    document: &Document,
     triggered: impl Into<TriggerType>,
     location: Position,
     client: &tower_lsp::Client,
     local_path: P,
     find_cmake_in_package: bool,
-    documents: &DashMap<Uri, String>,
+    documents: &DashMap<Uri, Document>,
 ) -> Option<CompletionResponse> {
     let local_path = local_path.as_ref();
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(source, None);
-    let tree = thetree.unwrap();
+    let source = document.text();
+    let root = document.tree().root_node();
     let mut complete: Vec<CompletionItem> = vec![];
 
     let current_point = location.to_point();
-    let node_info = CurrentNodeInfo::get(source, tree.root_node(), current_point);
+    let node_info = CurrentNodeInfo::get(source, root, current_point);
     let postype = node_info.pos_type();
     if matches!(triggered.into(), TriggerType::Path)
         // NOTE: skip SubDir
         && !matches!(postype, PositionType::SubDir)
         && let Some(promopt) = node_info.content()
-        && let Some(prompt) = promopt.try_replace_placeholders()
+        && let Some(prompt) = promopt.try_replace_placeholders(local_path)
         && let Some(list) = get_path_completions(&prompt, local_path)
         && !list.is_empty()
     {
         complete.extend(list);
         return Some(CompletionResponse::CompletionItemList(complete));
     }
-    let command_case = crate::config::CONFIG.command_case;
+    let command_case = crate::config::get_config(local_path).command_case;
     match postype {
         PositionType::VarOrFun | PositionType::TargetLink | PositionType::TargetInclude => {
             let cached_completion = get_cached_completion(local_path, documents).await;
             if !cached_completion.is_empty() {
                 complete.extend(cached_completion.filter_cache(command_case));
             }
-            if let Some(cmake_cache) = fileapi::get_complete_data() {
+            if let Some(cmake_cache) = fileapi::get_complete_data(local_path) {
                 complete.extend(cmake_cache);
             }
             if let Some(message) = getsubcomplete(
-                tree.root_node(),
-                source,
+                document.analysis(),
                 Path::new(local_path),
                 postype,
                 Some(location),
@@ -252,7 +238,7 @@ pub async fn getcomplete<P: AsRef<Path>>(
             }
 
             if !node_info.in_argument_list() {
-                let messages = (*BUILTIN_COMMAND).clone().filter_builtin(command_case);
+                let messages = builtin_commands().to_vec().filter_builtin(command_case);
                 complete.extend(messages);
             }
             if let Ok(messages) = &*BUILTIN_VARIABLE {
@@ -263,7 +249,7 @@ pub async fn getcomplete<P: AsRef<Path>>(
             complete.extend(findpackage::completion_items_with_prefix(space));
         }
         PositionType::FindPackage => {
-            complete.extend(findpackage::CMAKE_SOURCE.clone());
+            complete.extend(findpackage::completion_items());
         }
         #[cfg(unix)]
         PositionType::FindPkgConfig => {
@@ -274,7 +260,7 @@ pub async fn getcomplete<P: AsRef<Path>>(
             if !cached_completion.is_empty() {
                 complete.extend(cached_completion);
             }
-            if let Some(cmake_cache) = fileapi::get_complete_data() {
+            if let Some(cmake_cache) = fileapi::get_complete_data(local_path) {
                 complete.extend(cmake_cache);
             }
             if let Ok(messages) = &*BUILTIN_MODULE {
@@ -282,7 +268,7 @@ pub async fn getcomplete<P: AsRef<Path>>(
             }
             if let Some(prompt) = node_info.content()
                 && node_info.is_first_argument()
-                && let Some(prompt) = prompt.try_replace_placeholders()
+                && let Some(prompt) = prompt.try_replace_placeholders(local_path)
                 && let Some(list) = get_include_completions(&prompt, local_path)
             {
                 complete.extend(list);
@@ -291,7 +277,7 @@ pub async fn getcomplete<P: AsRef<Path>>(
         PositionType::SubDir => {
             if let Some(promopt) = node_info.content()
                 && node_info.is_first_argument()
-                && let Some(prompt) = promopt.try_replace_placeholders()
+                && let Some(prompt) = promopt.try_replace_placeholders(local_path)
                 && let Some(list) = get_subdir_completions(&prompt, local_path)
             {
                 complete.extend(list);
@@ -423,8 +409,7 @@ fn get_path_completions<SubP: AsRef<Path>, P: AsRef<Path>>(
 /// use position to make only can complete which has show before
 #[allow(clippy::too_many_arguments)]
 fn getsubcomplete<P: AsRef<Path>>(
-    input: tree_sitter::Node,
-    source: &str,
+    analysis: &Analysis,
     local_path: P,
     postype: PositionType,
     location: Option<Position>,
@@ -438,22 +423,18 @@ fn getsubcomplete<P: AsRef<Path>>(
         PositionType::VarOrFun | PositionType::TargetLink | PositionType::TargetInclude
     ));
     let local_path = local_path.as_ref();
-    let source_bytes = source.as_bytes();
     let mut complete: HashMap<String, CompletionItem> = HashMap::new();
 
     let end_location = location.map(|p| p.to_point());
 
-    // NOTE: prepare
-    let bracket_comments = get_bracket_comments(source_bytes, input, end_location);
-    let comments = get_line_comments(source_bytes, input, end_location);
-
-    let macros = get_macros(source_bytes, input, end_location);
-    let functions = get_functions(source_bytes, input, end_location);
-    let normal_commands = get_normal_commands(source_bytes, input, end_location);
     // NOTE: check bracket_comments
-    for bracket_comment in bracket_comments {
+    for bracket_comment in analysis
+        .bracket_comments
+        .iter()
+        .filter(|c| c.start_before(end_location))
+    {
         let comments = rst_doc_read(
-            bracket_comment.content,
+            &bracket_comment.content,
             local_path.file_name().unwrap().to_str().unwrap(),
         );
         for comment in comments {
@@ -461,106 +442,61 @@ fn getsubcomplete<P: AsRef<Path>>(
         }
     }
 
-    // NOTE: check functions
-    for fun in functions {
-        let name = fun.name;
-        let row = fun.node.start_position().row;
-        let column = fun.node.start_position().column;
-
-        let mut document_info = format!("defined function\nfrom: {}", local_path.display());
-        let mut variable_info = format!("defined variable\nfrom: {}", local_path.display());
-        if let Some(line_comment) = comments
-            .iter()
-            .find(|c| {
-                c.node.start_position().row + 1 == row && c.node.start_position().column == column
-            })
-            .map(|c| c.content)
-        {
-            document_info = format!("{}\n\n{}", document_info, line_comment);
-            variable_info = format!("{}\n\n{}", variable_info, line_comment);
-        }
-        complete.insert(
-            name.to_owned(),
-            CompletionItem {
-                label: name.to_string(),
-                kind: Some(CompletionItemKind::Function),
-                detail: Some("Function".to_string()),
-                documentation: Some(Documentation::String(document_info)),
-                ..Default::default()
-            },
-        );
-        if let Some(location) = location
-            && location_range_contain(location.to_point(), fun.node)
-        {
-            for FunMarcoArg { content, .. } in fun.args(source_bytes) {
-                complete.insert(
-                    content.to_owned(),
-                    CompletionItem {
-                        label: content.to_string(),
-                        kind: Some(CompletionItemKind::Variable),
-                        detail: Some("VARIABLE".to_string()),
-                        documentation: Some(Documentation::String(variable_info.clone())),
-                        ..Default::default()
-                    },
-                );
+    // NOTE: check functions and macros
+    for (defs, kind) in [
+        (&analysis.functions, "function"),
+        (&analysis.macros, "macro"),
+    ] {
+        for fun in defs.iter().filter(|f| f.start_before(end_location)) {
+            let name = &fun.name;
+
+            let mut document_info = format!("defined {kind}\nfrom: {}", local_path.display());
+            let mut variable_info = format!("defined variable\nfrom: {}", local_path.display());
+            if let Some(line_comment) = analysis.comment_above(fun.range.start_point) {
+                document_info = format!("{}\n\n{}", document_info, line_comment);
+                variable_info = format!("{}\n\n{}", variable_info, line_comment);
             }
-        }
-    }
-
-    // NOTE: check macros
-    for macro_node in macros {
-        let name = macro_node.name;
-        let row = macro_node.node.start_position().row;
-        let column = macro_node.node.start_position().column;
-
-        let mut document_info = format!("defined macro\nfrom: {}", local_path.display());
-        let mut variable_info = format!("defined variable\nfrom: {}", local_path.display());
-        if let Some(line_comment) = comments
-            .iter()
-            .find(|c| {
-                c.node.start_position().row + 1 == row && c.node.start_position().column == column
-            })
-            .map(|c| c.content)
-        {
-            document_info = format!("{}\n\n{}", document_info, line_comment);
-            variable_info = format!("{}\n\n{}", variable_info, line_comment);
-        }
-        complete.insert(
-            name.to_owned(),
-            CompletionItem {
-                label: name.to_string(),
-                kind: Some(CompletionItemKind::Function),
-                detail: Some("Function".to_string()),
-                documentation: Some(Documentation::String(document_info)),
-                ..Default::default()
-            },
-        );
-        if let Some(location) = location
-            && location_range_contain(location.to_point(), macro_node.node)
-        {
-            for FunMarcoArg { content, .. } in macro_node.args(source_bytes) {
-                complete.insert(
-                    content.to_owned(),
-                    CompletionItem {
-                        label: content.to_string(),
-                        kind: Some(CompletionItemKind::Variable),
-                        detail: Some("VARIABLE".to_string()),
-                        documentation: Some(Documentation::String(variable_info.clone())),
-                        ..Default::default()
-                    },
-                );
+            complete.insert(
+                name.to_owned(),
+                CompletionItem {
+                    label: name.to_string(),
+                    kind: Some(CompletionItemKind::Function),
+                    detail: Some("Function".to_string()),
+                    documentation: Some(Documentation::String(document_info)),
+                    ..Default::default()
+                },
+            );
+            if let Some(location) = location
+                && location_in_range(location.to_point(), fun.range)
+            {
+                for ArgumentIndex { content, .. } in &fun.args {
+                    complete.insert(
+                        content.to_owned(),
+                        CompletionItem {
+                            label: content.to_string(),
+                            kind: Some(CompletionItemKind::Variable),
+                            detail: Some("VARIABLE".to_string()),
+                            documentation: Some(Documentation::String(variable_info.clone())),
+                            ..Default::default()
+                        },
+                    );
+                }
             }
         }
     }
 
     // NOTE: check normal_commands
-    for command in normal_commands {
+    for command in analysis
+        .commands
+        .iter()
+        .filter(|c| c.start_before(end_location))
+    {
         let name = command.identifier.to_lowercase();
         if name == "include" {
-            let Some(first_arg) = command.first_arg else {
+            let Some(first_arg) = command.first_arg() else {
                 continue;
             };
-            let Some(file_name) = first_arg.try_replace_placeholders() else {
+            let Some(file_name) = first_arg.try_replace_placeholders(local_path) else {
                 continue;
             };
             let (is_builtin, subpath) = {
@@ -600,8 +536,8 @@ fn getsubcomplete<P: AsRef<Path>>(
                 include_files.push(subpath);
             }
         } else if name == "mark_as_advanced" {
-            for arg in command.args {
-                let variable = arg.utf8_text(source_bytes).unwrap();
+            for arg in &command.args {
+                let variable = arg.content.as_str();
                 complete.insert(
                     variable.to_owned(),
                     CompletionItem {
@@ -618,7 +554,7 @@ fn getsubcomplete<P: AsRef<Path>>(
             }
         } else {
             if name == "set" || name == "option" {
-                let Some(name) = command.first_arg else {
+                let Some(name) = command.first_arg() else {
                     continue;
                 };
                 // NOTE: some label is defined in macro with this way
@@ -627,18 +563,9 @@ fn getsubcomplete<P: AsRef<Path>>(
                 if LABEL_FILITER.is_match(name) {
                     continue;
                 }
-                let row = command.node.start_position().row;
-                let column = command.node.start_position().column;
                 let mut document_info = format!("defined variable\nfrom: {}", local_path.display());
 
-                if let Some(line_comment) = comments
-                    .iter()
-                    .find(|c| {
-                        c.node.start_position().row + 1 == row
-                            && c.node.start_position().column == column
-                    })
-                    .map(|c| c.content)
-                {
+                if let Some(line_comment) = analysis.comment_above(command.range.start_point) {
                     document_info = format!("{}\n\n{}", document_info, line_comment);
                 }
                 complete.insert(
@@ -653,7 +580,7 @@ fn getsubcomplete<P: AsRef<Path>>(
                 );
             }
             if name == "find_package" && should_in {
-                let Some(package_name) = command.first_arg else {
+                let Some(package_name) = command.first_arg() else {
                     continue;
                 };
                 let argument_count = command.args.len();
@@ -664,9 +591,8 @@ fn getsubcomplete<P: AsRef<Path>>(
                     if argument_count >= 2 {
                         let mut support_component = false;
                         let mut components_packages = Vec::new();
-                        for index in 1..argument_count {
-                            let package_prefix_node = command.args[index];
-                            let component = package_prefix_node.utf8_text(source_bytes).unwrap();
+                        for arg in &command.args[1..] {
+                            let component = arg.content.as_str();
                             if component == "COMPONENTS" {
                                 support_component = true;
                             } else if component != "REQUIRED" {
@@ -763,10 +689,10 @@ fn getsubcomplete<P: AsRef<Path>>(
 
             #[cfg(unix)]
             if name == "pkg_check_modules" {
-                let args = command.args;
-                let package_names: Vec<&str> = args
+                let package_names: Vec<&str> = command
+                    .args
                     .iter()
-                    .map(|arg| arg.utf8_text(source_bytes).unwrap())
+                    .map(|arg| arg.content.as_str())
                     .collect();
                 if package_names.is_empty() {
                     continue;
@@ -840,7 +766,7 @@ fn get_cmake_package_complete(
     include_files: &mut Vec<PathBuf>,
     complete_packages: &mut Vec<String>,
 ) -> Option<Vec<CompletionItem>> {
-    let packageinfo = CACHE_CMAKE_PACKAGES_WITHKEYS.get(package_name)?;
+    let packageinfo = find_cmake_package(package_name)?;
     let mut complete_infos = Vec::new();
 
     for path in packageinfo.tojump.iter() {
@@ -1117,16 +1043,13 @@ function(bb)
 endfunction()
     "#;
 
-        let mut parse = tree_sitter::Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let thetree = parse.parse(file_info, None).unwrap();
+        let document = Document::new(file_info);
         let dir = tempdir().unwrap();
         let root_cmake = dir.path().join("CMakeList.txt");
         let mut file = File::create(&root_cmake).unwrap();
         writeln!(file, "{}", file_info).unwrap();
         let data = getsubcomplete(
-            thetree.root_node(),
-            file_info,
+            document.analysis(),
             &root_cmake,
             PositionType::VarOrFun,
             None,
@@ -1326,16 +1249,13 @@ endfunction()
 
         let file_info = "set(AB \"100\")\r\n# test hello \r\nfunction(bb)\r\nendfunction()";
 
-        let mut parse = tree_sitter::Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let thetree = parse.parse(file_info, None).unwrap();
+        let document = Document::new(file_info);
         let dir = tempdir().unwrap();
         let root_cmake = dir.path().join("CMakeList.txt");
         let mut file = File::create(&root_cmake).unwrap();
         writeln!(file, "{}", file_info).unwrap();
         let data = getsubcomplete(
-            thetree.root_node(),
-            file_info,
+            document.analysis(),
             &root_cmake,
             PositionType::VarOrFun,
             None,
@@ -135,8 +135,7 @@ const fn builtin_commands_cached_file<'a>(client_support_snippet: bool) -> &'a s
     }
 }
 
-fn gen_builtin_commands() -> Vec<CompletionItem> {
-    let client_support_snippet = to_use_snippet();
+fn gen_builtin_commands(client_support_snippet: bool) -> Vec<CompletionItem> {
     let cached_file = builtin_commands_cached_file(client_support_snippet);
 
     if let Some(cache_dir) = BUILTIN_MODULE_CACHED_DIR.as_ref()
@@ -364,12 +363,39 @@ pub static BUILTIN_COMMAND_SIGNATURE_RES: LazyLock<HashMap<&str, CommandSignatur
     });
 
 /// CMake builtin commands
-pub static BUILTIN_COMMAND: LazyLock<Vec<CompletionItem>> = LazyLock::new(gen_builtin_commands);
+pub static BUILTIN_COMMAND: LazyLock<Vec<CompletionItem>> =
+    LazyLock::new(|| gen_builtin_commands(false));
+
+/// CMake builtin commands, inserted as snippets with their parameters
+pub static BUILTIN_COMMAND_SNIPPET: LazyLock<Vec<CompletionItem>> =
+    LazyLock::new(|| gen_builtin_commands(true));
+
+/// The builtin commands matching the current snippet setting, which can change at runtime
+pub fn builtin_commands() -> &'static [CompletionItem] {
+    if to_use_snippet() {
+        &BUILTIN_COMMAND_SNIPPET
+    } else {
+        &BUILTIN_COMMAND
+    }
+}
 
 /// cmake builtin vars
 pub static BUILTIN_VARIABLE: LazyLock<Result<Vec<CompletionItem>>> =
     LazyLock::new(get_builtin_variables);
 
+/// Whether the command is provided by CMake
+pub fn is_builtin_command(name: &str) -> bool {
+    BUILTIN_COMMAND_SIGNATURE_RES.contains_key(name.to_lowercase().as_str())
+}
+
+/// Whether the variable is provided by CMake, the `CMAKE_` prefix is reserved for CMake too
+pub fn is_builtin_variable(name: &str) -> bool {
+    name.starts_with("CMAKE_")
+        || BUILTIN_VARIABLE
+            .as_ref()
+            .is_ok_and(|variables| variables.iter().any(|variable| variable.label == name))
+}
+
 /// Cmake builtin modules
 pub static BUILTIN_MODULE: LazyLock<Result<Vec<CompletionItem>>> =
     LazyLock::new(get_builtin_modules);
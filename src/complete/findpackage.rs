@@ -2,7 +2,7 @@ use std::sync::LazyLock;
 
 use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Documentation};
 
-use crate::utils::CACHE_CMAKE_PACKAGES;
+use crate::utils::all_cmake_packages;
 
 static FIND_PACKAGE_SPACE_KEYWORDS: LazyLock<Vec<CompletionItem>> = LazyLock::new(|| {
     vec![
@@ -50,8 +50,8 @@ static PKGCONFIG_KEYWORDS: LazyLock<Vec<CompletionItem>> = LazyLock::new(|| {
     ]
 });
 
-pub static CMAKE_SOURCE: LazyLock<Vec<CompletionItem>> = LazyLock::new(|| {
-    let mut data: Vec<CompletionItem> = CACHE_CMAKE_PACKAGES
+pub(super) fn completion_items() -> Vec<CompletionItem> {
+    let mut data: Vec<CompletionItem> = all_cmake_packages()
         .iter()
         .map(|package| CompletionItem {
             label: package.name.clone(),
@@ -72,10 +72,10 @@ pub static CMAKE_SOURCE: LazyLock<Vec<CompletionItem>> = LazyLock::new(|| {
         .collect();
     data.append(&mut FIND_PACKAGE_SPACE_KEYWORDS.clone());
     data
-});
+}
 
 pub(super) fn completion_items_with_prefix(space: &str) -> Vec<CompletionItem> {
-    let mut data: Vec<CompletionItem> = CACHE_CMAKE_PACKAGES
+    let mut data: Vec<CompletionItem> = all_cmake_packages()
         .iter()
         .filter_map(|package| Some((package.name.strip_prefix(space)?, package)))
         .map(|(label, package)| CompletionItem {
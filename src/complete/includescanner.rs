@@ -6,7 +6,7 @@ use std::sync::{Arc, LazyLock, Mutex};
 use tower_lsp::lsp_types::CompletionItem;
 
 use super::getsubcomplete;
-use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::document::Document;
 use crate::utils::treehelper::PositionType;
 
 type CacheData = HashMap<PathBuf, Vec<CompletionItem>>;
@@ -29,12 +29,9 @@ pub fn scanner_include_complete(
         return Some(complete_items.clone());
     }
     let content = fs::read_to_string(path).ok()?;
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(content.clone(), None)?;
+    let document = Document::new(content);
     let result_data = getsubcomplete(
-        tree.root_node(),
-        &content,
+        document.analysis(),
         path,
         postype,
         None,
@@ -66,12 +63,9 @@ pub fn scanner_package_complete(
         return Some(complete_items.clone());
     }
     let content = fs::read_to_string(path).ok()?;
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(&content, None)?;
+    let document = Document::new(content);
     let result_data = getsubcomplete(
-        tree.root_node(),
-        &content,
+        document.analysis(),
         path,
         postype,
         None,
@@ -109,9 +103,7 @@ function(bb)
 endfunction()
     "#;
 
-        let mut parse = tree_sitter::Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let thetree = parse.parse(file_info_0, None).unwrap();
+        let document = Document::new(file_info_0);
         let dir = tempdir().unwrap();
         let root_cmake = dir.path().join("CMakeList.txt");
         let mut file = File::create(&root_cmake).unwrap();
@@ -120,8 +112,7 @@ endfunction()
         let mut file_2 = File::create(&another_cmake).unwrap();
         writeln!(file_2, "{}", file_info_1).unwrap();
         let data = getsubcomplete(
-            thetree.root_node(),
-            file_info_0,
+            document.analysis(),
             &root_cmake,
             PositionType::VarOrFun,
             None,
@@ -196,9 +187,7 @@ function(bb)
 endfunction()
     "#;
 
-        let mut parse = tree_sitter::Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let thetree = parse.parse(file_info_0, None).unwrap();
+        let document = Document::new(file_info_0);
         let dir = tempdir().unwrap();
         let root_cmake = dir.path().join("CMakeList.txt");
         let mut file = File::create(&root_cmake).unwrap();
@@ -207,8 +196,7 @@ endfunction()
         let mut file_2 = File::create(&another_cmake).unwrap();
         writeln!(file_2, "{}", file_info_1).unwrap();
         let data = getsubcomplete(
-            thetree.root_node(),
-            file_info_0,
+            document.analysis(),
             &root_cmake,
             PositionType::VarOrFun,
             None,
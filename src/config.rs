@@ -1,9 +1,12 @@
-use std::path::PathBuf;
-use std::sync::LazyLock;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
 
 use etcetera::{BaseStrategy, choose_base_strategy};
 use serde::{Deserialize, Serialize};
 
+use crate::utils::owning_root;
+
 #[derive(Deserialize, PartialEq, Eq, Debug)]
 pub struct Config {
     /// Check letter case of commands.
@@ -17,6 +20,12 @@ pub struct Config {
     pub line_max_words: usize,
     #[serde(default)]
     pub format: FormatConfig,
+    /// The build directory, relative to the project root.
+    #[serde(default)]
+    pub build_directory: Option<PathBuf>,
+    /// The configure preset of `CMakePresets.json` to use.
+    #[serde(default)]
+    pub configure_preset: Option<String>,
 }
 
 const fn default_max_words() -> usize {
@@ -30,6 +39,8 @@ impl Default for Config {
             enable_external_cmake_lint: false,
             line_max_words: default_max_words(),
             format: FormatConfig::default(),
+            build_directory: None,
+            configure_preset: None,
         }
     }
 }
@@ -72,15 +83,22 @@ pub struct FormatConfig {
     pub args: Option<Vec<String>>,
 }
 
+/// The names of the config file at the root of a project
+const PROJECT_CONFIG_FILES: [&str; 2] = [".neocmake.toml", ".neocmakelint.toml"];
+
+fn find_project_config_file(root: &Path) -> Option<PathBuf> {
+    PROJECT_CONFIG_FILES
+        .iter()
+        .map(|file| root.join(file))
+        .find(|path| path.exists())
+}
+
 fn find_config_file() -> Option<PathBuf> {
     let current_dir = std::env::current_dir().ok()?;
 
-    for file in [".neocmake.toml", ".neocmakelint.toml"] {
-        let path = current_dir.join(file);
-        if path.exists() {
-            tracing::info!("Using project-level config file: {:?}", path);
-            return Some(path);
-        }
+    if let Some(path) = find_project_config_file(&current_dir) {
+        tracing::info!("Using project-level config file: {:?}", path);
+        return Some(path);
     }
 
     let strategy = choose_base_strategy().ok()?;
@@ -97,17 +115,49 @@ fn find_config_file() -> Option<PathBuf> {
     None
 }
 
-pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
-    if let Some(path) = find_config_file()
-        && let Ok(buf) = std::fs::read_to_string(path)
-        && let Ok(config) = toml::from_str::<Config>(&buf)
-    {
-        return config;
-    }
+fn read_config_file(path: &Path) -> Option<Config> {
+    let buf = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&buf).ok()
+}
 
-    Config::default()
+pub static CONFIG: LazyLock<Arc<Config>> = LazyLock::new(|| {
+    Arc::new(
+        find_config_file()
+            .and_then(|path| read_config_file(&path))
+            .unwrap_or_default(),
+    )
 });
 
+/// The config files of the workspace folders, the files outside of them use [CONFIG]
+static PROJECT_CONFIGS: LazyLock<RwLock<HashMap<PathBuf, Arc<Config>>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Read the config file at the root of a workspace folder, if there is one
+pub fn load_project_config(root: &Path) {
+    let Some(config) = find_project_config_file(root).and_then(|path| {
+        tracing::info!("Using project-level config file: {:?}", path);
+        read_config_file(&path)
+    }) else {
+        return;
+    };
+    PROJECT_CONFIGS
+        .write()
+        .unwrap()
+        .insert(root.to_path_buf(), Arc::new(config));
+}
+
+pub fn remove_project_config(root: &Path) {
+    PROJECT_CONFIGS.write().unwrap().remove(root);
+}
+
+/// Get the config of the project which owns the file
+pub fn get_config(path: &Path) -> Arc<Config> {
+    let configs = PROJECT_CONFIGS.read().unwrap();
+    owning_root(configs.keys(), path)
+        .and_then(|root| configs.get(root))
+        .map_or_else(|| CONFIG.clone(), Arc::clone)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,257 @@
+mod analysis;
+
+use std::sync::{Arc, OnceLock};
+
+use tower_lsp::lsp_extensions::TextDocumentContentChangeEventEx;
+use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent};
+use tree_sitter::{InputEdit, Parser, Point, Tree};
+
+pub use self::analysis::{Analysis, ArgumentIndex, CommandIndex, DefinitionIndex, StartBefore};
+use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+
+/// The buffer of a document together with its parsed tree.
+///
+/// The tree is kept in sync with the text, so handlers can use it directly instead of parsing the
+/// buffer again. The [`Analysis`] is computed on the first use for each version, and is shared by
+/// the clones of the document.
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// The version from the client, [`None`] if the document is not opened by the client
+    version: Option<i32>,
+    text: String,
+    tree: Tree,
+    analysis: Arc<OnceLock<Analysis>>,
+}
+
+fn parse(text: &str, old_tree: Option<&Tree>) -> Tree {
+    let mut parse = Parser::new();
+    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+    parse.parse(text, old_tree).unwrap()
+}
+
+impl Document {
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let tree = parse(&text, None);
+        Self {
+            version: None,
+            text,
+            tree,
+            analysis: Arc::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_version(self, version: i32) -> Self {
+        Self {
+            version: Some(version),
+            ..self
+        }
+    }
+
+    pub const fn version(&self) -> Option<i32> {
+        self.version
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub const fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    pub fn analysis(&self) -> &Analysis {
+        self.analysis
+            .get_or_init(|| Analysis::new(&self.text, self.tree.root_node()))
+    }
+
+    /// Apply the changes in the order they are received, then reparse the buffer incrementally.
+    pub fn apply_changes(&mut self, version: i32, changes: &[TextDocumentContentChangeEvent]) {
+        self.version = Some(version);
+        self.analysis = Arc::default();
+        let mut edited = false;
+        for change in changes {
+            if let Some(text) = change.whole_content() {
+                self.text = text.to_string();
+                self.tree = parse(&self.text, None);
+                edited = false;
+                continue;
+            }
+            let Some(partial) = change.partial_content() else {
+                continue;
+            };
+            let start_byte = position_to_byte(&self.text, partial.range.start);
+            let old_end_byte = position_to_byte(&self.text, partial.range.end).max(start_byte);
+            let start_position = byte_to_point(&self.text, start_byte);
+            let old_end_position = byte_to_point(&self.text, old_end_byte);
+
+            self.text
+                .replace_range(start_byte..old_end_byte, &partial.text);
+
+            let new_end_byte = start_byte + partial.text.len();
+            self.tree.edit(&InputEdit {
+                start_byte,
+                old_end_byte,
+                new_end_byte,
+                start_position,
+                old_end_position,
+                new_end_position: byte_to_point(&self.text, new_end_byte),
+            });
+            edited = true;
+        }
+        if edited {
+            self.tree = parse(&self.text, Some(&self.tree));
+        }
+    }
+}
+
+/// Convert a lsp position, which counts utf-16 code units, to the byte offset in the text.
+/// A position after the end of the line is clamped to the end of the line.
+pub fn position_to_byte(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(index) => line_start += index + 1,
+            None => return text.len(),
+        }
+    }
+    let line = &text[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+
+    let mut utf16_count = 0;
+    for (index, c) in line.char_indices() {
+        if utf16_count >= position.character as usize {
+            return line_start + index;
+        }
+        utf16_count += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+pub fn byte_to_point(text: &str, byte: usize) -> Point {
+    let before = &text[..byte];
+    let row = before.matches('\n').count();
+    let column = before.rfind('\n').map_or(byte, |index| byte - index - 1);
+    Point { row, column }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{
+        Range, TextDocumentContentChangePartial, TextDocumentContentChangeWholeDocument,
+    };
+
+    use super::*;
+
+    #[allow(deprecated)]
+    fn partial_change(
+        (start_line, start_character): (u32, u32),
+        (end_line, end_character): (u32, u32),
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent::TextDocumentContentChangePartial(
+            TextDocumentContentChangePartial {
+                range: Range {
+                    start: Position {
+                        line: start_line,
+                        character: start_character,
+                    },
+                    end: Position {
+                        line: end_line,
+                        character: end_character,
+                    },
+                },
+                range_length: None,
+                text: text.to_string(),
+            },
+        )
+    }
+
+    fn assert_same_as_fresh_parse(document: &Document) {
+        let fresh = Document::new(document.text());
+        assert_eq!(
+            document.tree().root_node().to_sexp(),
+            fresh.tree().root_node().to_sexp()
+        );
+    }
+
+    #[test]
+    fn test_insert_and_delete() {
+        let mut document = Document::new("set(A 1)\nmessage(${A})\n");
+        document.apply_changes(1, &[partial_change((0, 7), (0, 7), " 2")]);
+        assert_eq!(document.text(), "set(A 1 2)\nmessage(${A})\n");
+        assert_same_as_fresh_parse(&document);
+
+        document.apply_changes(1, &[partial_change((0, 5), (0, 9), "")]);
+        assert_eq!(document.text(), "set(A)\nmessage(${A})\n");
+        assert_same_as_fresh_parse(&document);
+    }
+
+    #[test]
+    fn test_multiline_changes() {
+        let mut document = Document::new("set(A 1)\nmessage(${A})\n");
+        document.apply_changes(
+            1,
+            &[
+                partial_change((1, 0), (1, 0), "if(A)\n"),
+                partial_change((2, 13), (2, 13), "\nendif()"),
+            ],
+        );
+        assert_eq!(document.text(), "set(A 1)\nif(A)\nmessage(${A})\nendif()\n");
+        assert_same_as_fresh_parse(&document);
+
+        document.apply_changes(1, &[partial_change((0, 4), (1, 4), "B 2)\nif(B")]);
+        assert_eq!(document.text(), "set(B 2)\nif(B)\nmessage(${A})\nendif()\n");
+        assert_same_as_fresh_parse(&document);
+    }
+
+    #[test]
+    fn test_utf16_position() {
+        let mut document = Document::new("message(\"ä😀\")\nset(A)\n");
+        // NOTE: "ä" is one utf-16 unit, while the emoji is two
+        document.apply_changes(1, &[partial_change((0, 12), (0, 12), "b")]);
+        assert_eq!(document.text(), "message(\"ä😀b\")\nset(A)\n");
+        assert_same_as_fresh_parse(&document);
+    }
+
+    #[test]
+    fn test_whole_document() {
+        let mut document = Document::new("set(A 1)\n");
+        document.apply_changes(
+            1,
+            &[
+                partial_change((0, 0), (0, 0), "# comment\n"),
+                TextDocumentContentChangeEvent::TextDocumentContentChangeWholeDocument(
+                    TextDocumentContentChangeWholeDocument {
+                        text: "project(abc)\n".to_string(),
+                    },
+                ),
+            ],
+        );
+        assert_eq!(document.text(), "project(abc)\n");
+        assert_same_as_fresh_parse(&document);
+    }
+
+    #[test]
+    fn test_analysis_per_version() {
+        let mut document = Document::new("function(a)\nendfunction()\n");
+        assert_eq!(document.version(), None);
+        let cloned = document.clone();
+        assert!(std::ptr::eq(document.analysis(), cloned.analysis()));
+        assert_eq!(cloned.analysis().functions[0].name, "a");
+
+        document.apply_changes(2, &[partial_change((0, 9), (0, 10), "b")]);
+        assert_eq!(document.version(), Some(2));
+        assert_eq!(document.analysis().functions[0].name, "b");
+        assert_eq!(cloned.analysis().functions[0].name, "a");
+    }
+
+    #[test]
+    fn test_position_out_of_range() {
+        let mut document = Document::new("set(A)");
+        document.apply_changes(1, &[partial_change((3, 0), (3, 0), "\nset(B)")]);
+        assert_eq!(document.text(), "set(A)\nset(B)");
+        assert_same_as_fresh_parse(&document);
+    }
+}
@@ -0,0 +1,224 @@
+use tree_sitter::{Node, Point, Range};
+
+use crate::utils::query::{
+    get_bracket_comments, get_functions, get_line_comments, get_macros, get_normal_commands,
+    get_variables,
+};
+
+/// An argument, with its text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentIndex {
+    pub content: String,
+    pub range: Range,
+}
+
+impl ArgumentIndex {
+    fn new(node: Node, source: &[u8]) -> Self {
+        Self {
+            content: node.utf8_text(source).unwrap().to_string(),
+            range: node.range(),
+        }
+    }
+}
+
+/// A normal command, like `set(A 1)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandIndex {
+    pub identifier: String,
+    pub identifier_range: Range,
+    pub range: Range,
+    pub args: Vec<ArgumentIndex>,
+}
+
+impl CommandIndex {
+    pub fn first_arg(&self) -> Option<&str> {
+        self.args.first().map(|arg| arg.content.as_str())
+    }
+}
+
+/// A `function()` or `macro()` definition, the name is not part of the args
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionIndex {
+    pub name: String,
+    pub name_range: Range,
+    pub range: Range,
+    pub args: Vec<ArgumentIndex>,
+}
+
+/// A variable reference, like `${A}`, the content is the variable name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableIndex {
+    pub content: String,
+    pub range: Range,
+}
+
+/// A line comment without the `#` or the content of a bracket comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentIndex {
+    pub content: String,
+    pub range: Range,
+}
+
+/// Trait for the indexes, to only take these which start before a point
+pub trait StartBefore {
+    fn range(&self) -> Range;
+
+    fn start_before(&self, end: Option<Point>) -> bool {
+        end.is_none_or(|end| self.range().start_point < end)
+    }
+}
+
+macro_rules! impl_start_before {
+    ($($index: ty),*) => {
+        $(impl StartBefore for $index {
+            fn range(&self) -> Range {
+                self.range
+            }
+        })*
+    };
+}
+
+impl_start_before!(CommandIndex, DefinitionIndex, VariableIndex, CommentIndex);
+
+/// The data queried from a document
+///
+/// It does not borrow the tree, so it can be computed once for a version of the document and
+/// shared between the requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    pub commands: Vec<CommandIndex>,
+    pub functions: Vec<DefinitionIndex>,
+    pub macros: Vec<DefinitionIndex>,
+    pub variables: Vec<VariableIndex>,
+    pub line_comments: Vec<CommentIndex>,
+    pub bracket_comments: Vec<CommentIndex>,
+}
+
+impl Analysis {
+    pub fn new(source: &str, root: Node) -> Self {
+        let source = source.as_bytes();
+        let commands = get_normal_commands(source, root, None)
+            .into_iter()
+            .map(|command| CommandIndex {
+                identifier: command.identifier.to_string(),
+                identifier_range: command.identifier_node.range(),
+                range: command.node.range(),
+                args: command
+                    .args
+                    .into_iter()
+                    .map(|arg| ArgumentIndex::new(arg, source))
+                    .collect(),
+            })
+            .collect();
+        let functions = get_functions(source, root, None)
+            .into_iter()
+            .map(|fun| DefinitionIndex {
+                name: fun.name.to_string(),
+                name_range: fun.name_node.range(),
+                range: fun.node.range(),
+                args: fun.arguments[1..]
+                    .iter()
+                    .map(|arg| ArgumentIndex::new(*arg, source))
+                    .collect(),
+            })
+            .collect();
+        let macros = get_macros(source, root, None)
+            .into_iter()
+            .map(|macro_node| DefinitionIndex {
+                name: macro_node.name.to_string(),
+                name_range: macro_node.name_node.range(),
+                range: macro_node.node.range(),
+                args: macro_node.arguments[1..]
+                    .iter()
+                    .map(|arg| ArgumentIndex::new(*arg, source))
+                    .collect(),
+            })
+            .collect();
+        let variables = get_variables(source, root, None)
+            .into_iter()
+            .map(|var| VariableIndex {
+                content: var.content.to_string(),
+                range: var.node.range(),
+            })
+            .collect();
+        let line_comments = get_line_comments(source, root, None)
+            .into_iter()
+            .map(|comment| CommentIndex {
+                content: comment.content.to_string(),
+                range: comment.node.range(),
+            })
+            .collect();
+        let bracket_comments = get_bracket_comments(source, root, None)
+            .into_iter()
+            .map(|comment| CommentIndex {
+                content: comment.content.to_string(),
+                range: comment.node.range(),
+            })
+            .collect();
+        Self {
+            commands,
+            functions,
+            macros,
+            variables,
+            line_comments,
+            bracket_comments,
+        }
+    }
+
+    /// Find the line comment on the line above the point, starting at the same column
+    pub fn comment_above(&self, Point { row, column }: Point) -> Option<&str> {
+        self.line_comments
+            .iter()
+            .find(|c| c.range.start_point.row + 1 == row && c.range.start_point.column == column)
+            .map(|c| c.content.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+
+    #[test]
+    fn test_analysis() {
+        let source = r#"# the function
+function(hello name)
+  message("${name}")
+endfunction()
+macro(world)
+endmacro()
+#[[bracket]]
+hello(abcd)
+"#;
+        let document = Document::new(source);
+        let analysis = document.analysis();
+
+        assert_eq!(analysis.functions.len(), 1);
+        assert_eq!(analysis.functions[0].name, "hello");
+        assert_eq!(analysis.functions[0].args[0].content, "name");
+        assert_eq!(
+            analysis.comment_above(analysis.functions[0].range.start_point),
+            Some("the function")
+        );
+        assert_eq!(analysis.macros.len(), 1);
+        assert_eq!(analysis.macros[0].name, "world");
+        assert!(analysis.macros[0].args.is_empty());
+
+        let commands: Vec<&str> = analysis
+            .commands
+            .iter()
+            .map(|command| command.identifier.as_str())
+            .collect();
+        assert_eq!(commands, vec!["message", "hello"]);
+        assert_eq!(analysis.commands[1].first_arg(), Some("abcd"));
+
+        assert_eq!(analysis.variables.len(), 1);
+        assert_eq!(analysis.variables[0].content, "name");
+        assert_eq!(analysis.bracket_comments.len(), 1);
+        assert_eq!(analysis.bracket_comments[0].content, "bracket");
+
+        let end = Some(Point { row: 4, column: 0 });
+        assert!(analysis.functions[0].start_before(end));
+        assert!(!analysis.macros[0].start_before(end));
+    }
+}
@@ -0,0 +1,379 @@
+use tower_lsp::lsp_types::{DocumentHighlight, DocumentHighlightKind, Position};
+use tree_sitter::{Node, Point};
+
+use crate::CMakeNodeKinds;
+use crate::document::{Analysis, ArgumentIndex, CommandIndex, Document};
+use crate::jump;
+use crate::utils::query::ToLspRange;
+use crate::utils::treehelper::{BLOCK_KINDS, ToPoint, location_in_range};
+
+pub fn get_highlights(document: &Document, position: Position) -> Option<Vec<DocumentHighlight>> {
+    let point = position.to_point();
+    let highlights = block_highlights(document.tree().root_node(), point)
+        .or_else(|| variable_highlights(document, point))
+        .or_else(|| target_highlights(document, position))?;
+    if highlights.is_empty() {
+        return None;
+    }
+    Some(highlights)
+}
+
+fn highlight(range: tree_sitter::Range, kind: DocumentHighlightKind) -> DocumentHighlight {
+    DocumentHighlight {
+        range: range.lsp_range(),
+        kind: Some(kind),
+    }
+}
+
+/// On the keyword of a block command, like `if` or `endif`, highlight all the keywords of the
+/// block
+fn block_highlights(root: Node, point: Point) -> Option<Vec<DocumentHighlight>> {
+    let node = root.descendant_for_point_range(point, point)?;
+    let command = node.parent()?;
+    let block = command.parent()?;
+    if command.child(0) != Some(node)
+        || !command.kind().ends_with("_command")
+        || !BLOCK_KINDS.contains(&block.kind())
+    {
+        return None;
+    }
+    let mut walk = block.walk();
+    Some(
+        block
+            .children(&mut walk)
+            .filter(|child| child.kind().ends_with("_command"))
+            .filter_map(|child| child.child(0))
+            .map(|keyword| highlight(keyword.range(), DocumentHighlightKind::Text))
+            .collect(),
+    )
+}
+
+/// The keywords of the conditions, which are not variables
+const CONDITION_KEYWORDS: &[&str] = &[
+    "NOT",
+    "AND",
+    "OR",
+    "DEFINED",
+    "EXISTS",
+    "IS_READABLE",
+    "IS_WRITABLE",
+    "IS_EXECUTABLE",
+    "IS_DIRECTORY",
+    "IS_SYMLINK",
+    "IS_ABSOLUTE",
+    "IS_NEWER_THAN",
+    "MATCHES",
+    "LESS",
+    "GREATER",
+    "EQUAL",
+    "LESS_EQUAL",
+    "GREATER_EQUAL",
+    "STRLESS",
+    "STRGREATER",
+    "STREQUAL",
+    "STRLESS_EQUAL",
+    "STRGREATER_EQUAL",
+    "VERSION_LESS",
+    "VERSION_GREATER",
+    "VERSION_EQUAL",
+    "VERSION_LESS_EQUAL",
+    "VERSION_GREATER_EQUAL",
+    "IN_LIST",
+    "PATH_EQUAL",
+];
+
+/// The keywords of the conditions which are followed by a name which is not a variable
+const CONDITION_NAME_KEYWORDS: &[&str] = &["COMMAND", "POLICY", "TARGET", "TEST"];
+
+/// A place where a variable is named
+struct Access<'a> {
+    name: &'a str,
+    range: tree_sitter::Range,
+    kind: DocumentHighlightKind,
+}
+
+/// Get the argument which is the variable set by the command
+fn written_variable(command: &CommandIndex) -> Option<&ArgumentIndex> {
+    match command.identifier.to_lowercase().as_str() {
+        "set" | "unset" | "option" => command.args.first(),
+        // NOTE: list(<operation> <list> ...) modifies the list, while the reading operations write
+        // their result to the last argument
+        "list" => match command.first_arg()? {
+            "LENGTH" | "GET" | "FIND" | "JOIN" | "SUBLIST" => command.args.get(2..)?.last(),
+            "APPEND" | "PREPEND" | "INSERT" | "SORT" | "REVERSE" | "TRANSFORM" | "FILTER" => {
+                command.args.get(1)
+            }
+            operation if operation.starts_with("REMOVE_") || operation.starts_with("POP_") => {
+                command.args.get(1)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Get the argument which is the list read by the command
+fn read_variable(command: &CommandIndex) -> Option<&ArgumentIndex> {
+    if !command.identifier.eq_ignore_ascii_case("list") {
+        return None;
+    }
+    match command.first_arg()? {
+        "LENGTH" | "GET" | "FIND" | "JOIN" | "SUBLIST" => command.args.get(1),
+        _ => None,
+    }
+}
+
+/// The arguments of the `if()`, `elseif()`, `while()` and `foreach()` commands, which are not in
+/// the analysis
+fn block_arguments(
+    node: Node,
+    source: &[u8],
+    output: &mut Vec<(&'static str, Vec<ArgumentIndex>)>,
+) {
+    let kind = node.kind();
+    if matches!(
+        kind,
+        CMakeNodeKinds::IF_COMMAND
+            | CMakeNodeKinds::ELSEIF_COMMAND
+            | CMakeNodeKinds::WHILE_COMMAND
+            | CMakeNodeKinds::FOREACH_COMMAND
+    ) {
+        let mut walk = node.walk();
+        let args = node
+            .children(&mut walk)
+            .find(|child| child.kind() == CMakeNodeKinds::ARGUMENT_LIST)
+            .map(|list| {
+                let mut walk = list.walk();
+                list.children(&mut walk)
+                    .filter(|arg| arg.kind() == CMakeNodeKinds::ARGUMENT)
+                    .map(|arg| ArgumentIndex {
+                        content: arg.utf8_text(source).unwrap_or_default().to_string(),
+                        range: arg.range(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        output.push((kind, args));
+        return;
+    }
+    let mut walk = node.walk();
+    for child in node.children(&mut walk) {
+        block_arguments(child, source, output);
+    }
+}
+
+/// Whether the argument of a condition can be the name of a variable
+fn is_condition_variable(previous: Option<&ArgumentIndex>, arg: &ArgumentIndex) -> bool {
+    let content = arg.content.as_str();
+    !content.starts_with(|c: char| c == '"' || c == '[' || c.is_ascii_digit())
+        && !content.contains("${")
+        && !content.contains("$<")
+        && !CONDITION_KEYWORDS.contains(&content)
+        && !CONDITION_NAME_KEYWORDS.contains(&content)
+        && !previous
+            .is_some_and(|previous| CONDITION_NAME_KEYWORDS.contains(&previous.content.as_str()))
+}
+
+/// The places where the variables are read and written in the document
+fn variable_accesses<'a>(
+    analysis: &'a Analysis,
+    blocks: &'a [(&'static str, Vec<ArgumentIndex>)],
+) -> Vec<Access<'a>> {
+    let access = |arg: &'a ArgumentIndex, kind| Access {
+        name: arg.content.as_str(),
+        range: arg.range,
+        kind,
+    };
+    let mut accesses = vec![];
+    for command in &analysis.commands {
+        if let Some(arg) = written_variable(command) {
+            accesses.push(access(arg, DocumentHighlightKind::Write));
+        }
+        if let Some(arg) = read_variable(command) {
+            accesses.push(access(arg, DocumentHighlightKind::Read));
+        }
+    }
+    for (kind, args) in blocks {
+        if *kind == CMakeNodeKinds::FOREACH_COMMAND {
+            // NOTE: foreach(<item> IN LISTS <lists>... ITEMS <items>...)
+            if let Some(item) = args.first() {
+                accesses.push(access(item, DocumentHighlightKind::Write));
+            }
+            let lists = args
+                .iter()
+                .skip_while(|arg| arg.content != "LISTS")
+                .skip(1)
+                .take_while(|arg| arg.content != "ITEMS");
+            accesses.extend(lists.map(|arg| access(arg, DocumentHighlightKind::Read)));
+            continue;
+        }
+        for (index, arg) in args.iter().enumerate() {
+            let previous = index.checked_sub(1).map(|index| &args[index]);
+            if is_condition_variable(previous, arg) {
+                accesses.push(access(arg, DocumentHighlightKind::Read));
+            }
+        }
+    }
+    accesses.extend(analysis.variables.iter().map(|var| Access {
+        name: var.content.as_str(),
+        range: var.range,
+        kind: DocumentHighlightKind::Read,
+    }));
+    accesses.sort_by_key(|access| access.range.start_byte);
+    accesses
+}
+
+/// Highlight the places where the variable is read and written
+fn variable_highlights(document: &Document, point: Point) -> Option<Vec<DocumentHighlight>> {
+    let mut blocks = vec![];
+    block_arguments(
+        document.tree().root_node(),
+        document.text().as_bytes(),
+        &mut blocks,
+    );
+    let accesses = variable_accesses(document.analysis(), &blocks);
+    let name = accesses
+        .iter()
+        .find(|access| location_in_range(point, access.range))?
+        .name;
+    Some(
+        accesses
+            .iter()
+            .filter(|access| access.name == name)
+            .map(|access| highlight(access.range, access.kind))
+            .collect(),
+    )
+}
+
+/// Highlight the definition of the target and its uses, like the references of the target
+fn target_highlights(document: &Document, position: Position) -> Option<Vec<DocumentHighlight>> {
+    let occurrences = jump::target_occurrences(document);
+    let name = &occurrences
+        .iter()
+        .find(|occurrence| occurrence.contains(position))?
+        .name;
+    Some(
+        occurrences
+            .iter()
+            .filter(|occurrence| &occurrence.name == name)
+            .map(|occurrence| DocumentHighlight {
+                range: occurrence.range,
+                kind: Some(if occurrence.is_definition {
+                    DocumentHighlightKind::Write
+                } else {
+                    DocumentHighlightKind::Read
+                }),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlights_at(source: &str, line: u32, character: u32) -> Vec<(u32, u32, u32)> {
+        let document = Document::new(source);
+        get_highlights(&document, Position { line, character })
+            .unwrap_or_default()
+            .iter()
+            .map(|highlight| {
+                let kind = match highlight.kind {
+                    Some(DocumentHighlightKind::Write) => 2,
+                    Some(DocumentHighlightKind::Read) => 1,
+                    _ => 0,
+                };
+                (
+                    highlight.range.start.line,
+                    highlight.range.start.character,
+                    kind,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_block_highlights() {
+        let source = r"if(A)
+elseif(B)
+  if(C)
+  endif()
+else()
+endif()
+";
+        assert_eq!(
+            highlights_at(source, 4, 1),
+            vec![(0, 0, 0), (1, 0, 0), (4, 0, 0), (5, 0, 0)]
+        );
+        assert_eq!(highlights_at(source, 3, 3), vec![(2, 2, 0), (3, 2, 0)]);
+    }
+
+    #[test]
+    fn test_variable_highlights() {
+        let source = r#"set(SOURCES main.cpp)
+list(APPEND SOURCES lib.cpp)
+message("${SOURCES}")
+set(OTHER ${SOURCES})
+"#;
+        let expected = vec![(0, 4, 2), (1, 12, 2), (2, 11, 1), (3, 12, 1)];
+        assert_eq!(highlights_at(source, 0, 5), expected);
+        assert_eq!(highlights_at(source, 2, 12), expected);
+    }
+
+    #[test]
+    fn test_list_highlights() {
+        let source = r"list(LENGTH SOURCES COUNT)
+list(GET SOURCES 0 FIRST)
+list(REMOVE_ITEM SOURCES main.cpp)
+list(POP_BACK COUNT)
+";
+        let sources = vec![(0, 12, 1), (1, 9, 1), (2, 17, 2)];
+        assert_eq!(highlights_at(source, 0, 13), sources);
+        assert_eq!(highlights_at(source, 2, 18), sources);
+        assert_eq!(highlights_at(source, 0, 21), vec![(0, 20, 2), (3, 14, 2)]);
+        assert_eq!(highlights_at(source, 1, 20), vec![(1, 19, 2)]);
+    }
+
+    #[test]
+    fn test_block_variable_highlights() {
+        let source = r"set(ENABLED ON)
+if(ENABLED AND NOT TARGET ENABLED)
+elseif(DEFINED ENABLED)
+endif()
+foreach(item IN LISTS ENABLED ITEMS ENABLED)
+  message(${item})
+endforeach()
+";
+        let expected = vec![(0, 4, 2), (1, 3, 1), (2, 15, 1), (4, 22, 1)];
+        assert_eq!(highlights_at(source, 0, 5), expected);
+        assert_eq!(highlights_at(source, 1, 4), expected);
+        assert_eq!(highlights_at(source, 5, 12), vec![(4, 8, 2), (5, 12, 1)]);
+    }
+
+    #[test]
+    fn test_target_highlights() {
+        let source = r"add_executable(app main.cpp)
+target_link_libraries(app PRIVATE lib)
+target_include_directories(app PRIVATE include)
+target_link_libraries(other PRIVATE app)
+add_dependencies(other app)
+add_custom_target(copy COMMAND cp $<TARGET_FILE:app> out)
+";
+        assert_eq!(
+            highlights_at(source, 1, 23),
+            vec![
+                (0, 15, 2),
+                (1, 22, 1),
+                (2, 27, 1),
+                (3, 36, 1),
+                (4, 23, 1),
+                (5, 48, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_highlights() {
+        assert!(highlights_at("message(STATUS hello)\n", 0, 16).is_empty());
+    }
+}
@@ -3,7 +3,6 @@ use std::path::{Path, PathBuf};
 use tower_lsp::lsp_types::{DocumentLink, Position, Range};
 
 use crate::Uri;
-use crate::consts::TREESITTER_CMAKE_LANGUAGE;
 use crate::utils::query::get_normal_commands;
 use crate::utils::{NeoStrExt, gen_module_pattern, include_is_module};
 
@@ -13,13 +12,11 @@ const NEED_TO_CHECK_EXTENSION: &[&str] = &[".h", ".hpp", ".c", ".cpp", ".cmake"]
 
 pub fn document_link_search<P: AsRef<Path>>(
     source: &str,
+    root: tree_sitter::Node<'_>,
     current_file: P,
 ) -> Option<Vec<DocumentLink>> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(source, None)?;
     let file_parent = current_file.as_ref().parent()?;
-    let links = document_link_search_inner(source, thetree.root_node(), &file_parent);
+    let links = document_link_search_inner(source, root, &file_parent);
     if links.is_empty() {
         return None;
     }
@@ -52,7 +49,7 @@ pub fn document_link_search_inner<P: AsRef<Path>>(
                 {
                     continue;
                 }
-                let Some(filename) = arg.try_replace_placeholders() else {
+                let Some(filename) = arg.try_replace_placeholders(current_parent.as_ref()) else {
                     continue;
                 };
                 let file_path = current_parent.as_ref().join(filename);
@@ -86,7 +83,7 @@ pub fn document_link_search_inner<P: AsRef<Path>>(
         let Some(first_arg) = command.first_arg else {
             continue;
         };
-        let Some(filename) = first_arg.try_replace_placeholders() else {
+        let Some(filename) = first_arg.try_replace_placeholders(current_parent.as_ref()) else {
             continue;
         };
         let (final_uri, builtin) = if is_subdirectory {
@@ -156,6 +153,7 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
+    use crate::consts::TREESITTER_CMAKE_LANGUAGE;
     use crate::fileapi::cache::Cache;
     use crate::fileapi::set_cache_data;
 
@@ -179,7 +177,7 @@ mod tests {
             ]
         });
         let template_cache: Cache = serde_json::from_value(json_value).unwrap();
-        set_cache_data(template_cache);
+        set_cache_data(dir.path(), template_cache);
         #[cfg(not(windows))]
         let jump_file_src = r#"
 set(ABCD 1234)
@@ -13,11 +13,12 @@ const COMMAND_KEYWORDS: [&str; 5] = [
     "target_include_directories",
 ];
 
-pub async fn get_symbol(_client: &Client, context: &str) -> Option<DocumentSymbolResponse> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(context, None)?;
-    let symbols = get_symbols(tree.root_node(), context);
+pub async fn get_symbol(
+    _client: &Client,
+    root: Node<'_>,
+    context: &str,
+) -> Option<DocumentSymbolResponse> {
+    let symbols = get_symbols(root, context);
     if symbols.is_empty() {
         None
     } else {
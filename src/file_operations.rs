@@ -0,0 +1,512 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionResponse, FileOperationFilter, FileOperationOptions,
+    FileOperationPattern, FileOperationRegistrationOptions, Position, Range, TextEdit, Uri,
+    WorkspaceEdit,
+};
+
+use crate::document::{ArgumentIndex, CommandIndex, Document};
+use crate::document_link::document_link_search_inner;
+use crate::languageserver::get_or_update_document;
+use crate::scansubs::scanned_files;
+use crate::utils::treehelper::{ToPoint, ToPosition};
+
+/// The prefixes of the arguments which mean the directory of the current CMake file
+const CURRENT_DIR_PREFIXES: &[&str] =
+    &["${CMAKE_CURRENT_SOURCE_DIR}/", "${CMAKE_CURRENT_LIST_DIR}/"];
+
+/// The scopes of the sources in `target_sources`
+const SCOPE_KEYWORDS: &[&str] = &["PRIVATE", "PUBLIC", "INTERFACE"];
+
+/// The libraries which cannot have sources to compile
+const NOT_COMPILED_LIBRARIES: &[&str] = &["ALIAS", "IMPORTED", "INTERFACE"];
+
+/// The extensions of the created files which can be added to a target
+const SOURCE_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx", "h", "hh", "hpp", "hxx"];
+
+fn file_operation_filters(glob: &str) -> FileOperationRegistrationOptions {
+    FileOperationRegistrationOptions {
+        filters: vec![FileOperationFilter {
+            scheme: Some("file".to_string()),
+            pattern: FileOperationPattern {
+                glob: glob.to_string(),
+                matches: None,
+                options: None,
+            },
+        }],
+    }
+}
+
+/// The renames of all the files and the creations of the sources are sent to the server
+pub fn file_operation_options() -> FileOperationOptions {
+    FileOperationOptions {
+        did_create: Some(file_operation_filters(&format!(
+            "**/*.{{{}}}",
+            SOURCE_EXTENSIONS.join(",")
+        ))),
+        will_rename: Some(file_operation_filters("**/*")),
+        ..Default::default()
+    }
+}
+
+/// Remove the `.` and `..` of the path without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Where the path is after the renames, [`None`] if it does not move
+fn renamed_path(path: &Path, renames: &[(PathBuf, PathBuf)]) -> Option<PathBuf> {
+    renames.iter().find_map(|(old, new)| {
+        let path = normalize(path);
+        let rest = path.strip_prefix(old).ok()?;
+        if rest.as_os_str().is_empty() {
+            Some(new.clone())
+        } else {
+            Some(new.join(rest))
+        }
+    })
+}
+
+/// The path as written in CMake, relative to the directory and with `/` separators
+fn cmake_relative(path: &Path, dir: &Path) -> Option<String> {
+    let relative = pathdiff::diff_paths(path, dir)?;
+    let components: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(components.join("/"))
+}
+
+/// The edits which keep the paths written in the CMake file valid after the renames, the paths
+/// are resolved like the document links
+pub fn rename_edits(
+    path: &Path,
+    document: &Document,
+    renames: &[(PathBuf, PathBuf)],
+) -> Vec<TextEdit> {
+    let Some(dir) = path.parent() else {
+        return vec![];
+    };
+    // NOTE: the CMake file can be moved with its directory
+    let new_dir = renamed_path(dir, renames).unwrap_or_else(|| dir.to_path_buf());
+    let mut edits = vec![];
+    for link in document_link_search_inner(document.text(), document.tree().root_node(), &dir) {
+        let Some(target) = link.target.and_then(|target| target.to_file_path().ok()) else {
+            continue;
+        };
+        let new_target = renamed_path(&target, renames);
+        if new_target.is_none() && new_dir == dir {
+            continue;
+        }
+        let new_target = new_target.unwrap_or_else(|| normalize(&target));
+        // NOTE: the link ranges are the points of the argument nodes
+        let Some(written) = document
+            .tree()
+            .root_node()
+            .descendant_for_point_range(link.range.start.to_point(), link.range.end.to_point())
+            .and_then(|node| node.utf8_text(document.text().as_bytes()).ok())
+        else {
+            continue;
+        };
+        let quoted = written.starts_with('"');
+        let raw = written.trim_matches('"');
+        let (prefix, rest) = CURRENT_DIR_PREFIXES
+            .iter()
+            .find_map(|prefix| raw.strip_prefix(prefix).map(|rest| (*prefix, rest)))
+            .unwrap_or(("", raw));
+        // NOTE: the paths made of other variables cannot be rewritten
+        if rest.contains("${") {
+            continue;
+        }
+        // NOTE: add_subdirectory links to the CMakeLists.txt of the directory
+        let new_target = if dir.join(rest) == target {
+            new_target
+        } else if target.parent() == Some(&dir.join(rest)) {
+            new_target.parent().unwrap_or(&new_target).to_path_buf()
+        } else {
+            continue;
+        };
+        let new_written = if Path::new(rest).is_absolute() {
+            new_target.display().to_string()
+        } else {
+            let Some(relative) = cmake_relative(&new_target, &new_dir) else {
+                continue;
+            };
+            relative
+        };
+        if new_written == rest {
+            continue;
+        }
+        let offset = u32::from(quoted);
+        edits.push(TextEdit {
+            range: Range {
+                start: Position {
+                    line: link.range.start.line,
+                    character: link.range.start.character + offset,
+                },
+                end: Position {
+                    line: link.range.end.line,
+                    character: link.range.end.character - offset,
+                },
+            },
+            new_text: format!("{prefix}{new_written}"),
+        });
+    }
+    edits
+}
+
+/// The edits of all the scanned CMake files for `workspace/willRenameFiles`
+pub async fn will_rename_files(
+    renames: &[(PathBuf, PathBuf)],
+    documents: &DashMap<Uri, Document>,
+) -> Option<WorkspaceEdit> {
+    let mut changes = HashMap::new();
+    for path in scanned_files().await {
+        let Ok(document) = get_or_update_document(&path, documents).await else {
+            continue;
+        };
+        let edits = rename_edits(&path, &document, renames);
+        if edits.is_empty() {
+            continue;
+        }
+        let Ok(uri) = Uri::from_file_path(&path) else {
+            continue;
+        };
+        changes.insert(uri, edits);
+    }
+    if changes.is_empty() {
+        return None;
+    }
+    Some(WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    })
+}
+
+pub fn is_source_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension))
+}
+
+/// The nearest scanned CMakeLists.txt in the directories of the file
+pub async fn owning_cmake(path: &Path) -> Option<PathBuf> {
+    let files = scanned_files().await;
+    path.ancestors()
+        .skip(1)
+        .map(|dir| dir.join("CMakeLists.txt"))
+        .find(|cmake| files.contains(cmake))
+}
+
+fn lists_source(command: &CommandIndex, relative: &str) -> bool {
+    command.args.iter().any(|arg| {
+        let content = arg.content.trim_matches('"');
+        CURRENT_DIR_PREFIXES
+            .iter()
+            .find_map(|prefix| content.strip_prefix(prefix))
+            .unwrap_or(content)
+            == relative
+    })
+}
+
+/// The edit writing the text after the argument of the command
+fn append_source(
+    text: &str,
+    command: &CommandIndex,
+    last: &ArgumentIndex,
+    relative: &str,
+) -> Option<TextEdit> {
+    let position = last.range.end_point.to_position();
+    // NOTE: the sources written one per line keep their indentation
+    let separator = if last.range.start_point.row == command.identifier_range.start_point.row {
+        " ".to_string()
+    } else {
+        let line = text.lines().nth(last.range.start_point.row)?;
+        let indent = &line[..line.len() - line.trim_start().len()];
+        format!("\n{indent}")
+    };
+    Some(TextEdit {
+        range: Range {
+            start: position,
+            end: position,
+        },
+        new_text: format!("{separator}{relative}"),
+    })
+}
+
+/// The last argument of the `PRIVATE` section of `target_sources`, the keyword itself when the
+/// section is empty
+fn private_section_end(command: &CommandIndex) -> Option<&ArgumentIndex> {
+    let start = command
+        .args
+        .iter()
+        .rposition(|arg| arg.content == "PRIVATE")?;
+    let section = &command.args[start..];
+    let end = section
+        .iter()
+        .skip(1)
+        .position(|arg| SCOPE_KEYWORDS.contains(&arg.content.as_str()))
+        .map_or(section.len(), |index| index + 1);
+    section[..end].last()
+}
+
+/// The edit adding the source to the `PRIVATE` section of the last `target_sources` which has one,
+/// or in a new `PRIVATE` section, so the source is not given to the users of the target
+fn target_sources_edit(
+    text: &str,
+    sources_commands: &[&CommandIndex],
+    relative: &str,
+) -> Option<TextEdit> {
+    if let Some((command, last)) = sources_commands
+        .iter()
+        .rev()
+        .find_map(|command| Some((*command, private_section_end(command)?)))
+    {
+        return append_source(text, command, last, relative);
+    }
+    let command = sources_commands.last()?;
+    append_source(
+        text,
+        command,
+        command.args.last()?,
+        &format!("PRIVATE {relative}"),
+    )
+}
+
+/// The targets of the CMake file which the source can be added to, with the edits adding it to
+/// their `target_sources`, or to their `add_executable` and `add_library` when they have none
+pub fn source_insertions(
+    path: &Path,
+    document: &Document,
+    source: &Path,
+) -> Vec<(String, TextEdit)> {
+    let Some(relative) = path.parent().and_then(|dir| cmake_relative(source, dir)) else {
+        return vec![];
+    };
+    let commands = &document.analysis().commands;
+    let mut insertions = vec![];
+    for command in commands {
+        let identifier = command.identifier.to_lowercase();
+        if identifier != "add_executable" && identifier != "add_library" {
+            continue;
+        }
+        let Some(name) = command.first_arg().map(|name| name.trim_matches('"')) else {
+            continue;
+        };
+        // NOTE: the INTERFACE libraries do not compile their sources
+        if name.contains("${")
+            || command
+                .args
+                .iter()
+                .any(|arg| NOT_COMPILED_LIBRARIES.contains(&arg.content.as_str()))
+        {
+            continue;
+        }
+        let sources_commands: Vec<&CommandIndex> = commands
+            .iter()
+            .filter(|other| {
+                other.identifier.eq_ignore_ascii_case("target_sources")
+                    && other.first_arg().map(|target| target.trim_matches('"')) == Some(name)
+            })
+            .collect();
+        if lists_source(command, &relative)
+            || sources_commands
+                .iter()
+                .any(|other| lists_source(other, &relative))
+        {
+            continue;
+        }
+        let edit = if sources_commands.is_empty() {
+            command
+                .args
+                .last()
+                .and_then(|last| append_source(document.text(), command, last, &relative))
+        } else {
+            target_sources_edit(document.text(), &sources_commands, &relative)
+        };
+        if let Some(edit) = edit {
+            insertions.push((name.to_string(), edit));
+        }
+    }
+    insertions
+}
+
+/// The code actions adding the created sources to the targets of the CMake file
+pub fn add_source_actions(
+    uri: &Uri,
+    path: &Path,
+    document: &Document,
+    sources: &[PathBuf],
+) -> Vec<CodeActionResponse> {
+    let mut actions = vec![];
+    for source in sources {
+        let Some(file_name) = source.file_name() else {
+            continue;
+        };
+        for (target, edit) in source_insertions(path, document, source) {
+            actions.push(CodeActionResponse::CodeAction(CodeAction {
+                title: format!("Add {} to {target}", file_name.to_string_lossy()),
+                kind: Some(CodeActionKind::Refactor),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }));
+        }
+    }
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn edits_of(path: &Path, renames: &[(PathBuf, PathBuf)]) -> Vec<(u32, u32, String)> {
+        let document = Document::new(fs::read_to_string(path).unwrap());
+        rename_edits(path, &document, renames)
+            .into_iter()
+            .map(|edit| {
+                (
+                    edit.range.start.line,
+                    edit.range.start.character,
+                    edit.new_text,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rename_edits() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::create_dir_all(root.join("cmake")).unwrap();
+        fs::write(root.join("src/main.cpp"), "").unwrap();
+        fs::write(root.join("src/util.cpp"), "").unwrap();
+        fs::write(
+            root.join("lib/CMakeLists.txt"),
+            "add_library(lib ../src/util.cpp)\n",
+        )
+        .unwrap();
+        fs::write(root.join("cmake/helper.cmake"), "").unwrap();
+        let top = root.join("CMakeLists.txt");
+        fs::write(
+            &top,
+            r"include(cmake/helper.cmake)
+add_subdirectory(lib)
+add_executable(app src/main.cpp ${CMAKE_CURRENT_LIST_DIR}/src/util.cpp)
+",
+        )
+        .unwrap();
+
+        let renames = [(root.join("src"), root.join("source"))];
+        assert_eq!(
+            edits_of(&top, &renames),
+            vec![
+                (2, 19, "source/main.cpp".to_string()),
+                (
+                    2,
+                    32,
+                    "${CMAKE_CURRENT_LIST_DIR}/source/util.cpp".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            edits_of(&root.join("lib/CMakeLists.txt"), &renames),
+            vec![(0, 16, "../source/util.cpp".to_string())]
+        );
+
+        let renames = [
+            (root.join("lib"), root.join("libs/core")),
+            (
+                root.join("cmake/helper.cmake"),
+                root.join("cmake/Helper.cmake"),
+            ),
+        ];
+        assert_eq!(
+            edits_of(&top, &renames),
+            vec![
+                (0, 8, "cmake/Helper.cmake".to_string()),
+                (1, 17, "libs/core".to_string()),
+            ]
+        );
+        assert_eq!(
+            edits_of(&root.join("lib/CMakeLists.txt"), &renames),
+            vec![(0, 16, "../../src/util.cpp".to_string())]
+        );
+
+        let unicode = root.join("unicode.cmake");
+        fs::write(&unicode, "add_executable(äpp src/main.cpp)\n").unwrap();
+        let renames = [(root.join("src"), root.join("source"))];
+        assert_eq!(
+            edits_of(&unicode, &renames),
+            vec![(0, 20, "source/main.cpp".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_source_insertions() {
+        let source = r"add_executable(app main.cpp)
+add_library(core
+  src/a.cpp
+  src/b.cpp)
+add_library(core::core ALIAS core)
+add_library(util STATIC)
+target_sources(util PRIVATE util.cpp)
+add_library(old src/new.cpp)
+add_library(iface INTERFACE)
+add_library(lib STATIC)
+target_sources(lib PRIVATE a.cpp PUBLIC include/a.h)
+add_library(pub STATIC)
+target_sources(pub PUBLIC include/b.h)
+";
+        let document = Document::new(source);
+        let path = Path::new("/project/CMakeLists.txt");
+        let insertions: Vec<(String, u32, u32, String)> =
+            source_insertions(path, &document, Path::new("/project/src/new.cpp"))
+                .into_iter()
+                .map(|(target, edit)| {
+                    (
+                        target,
+                        edit.range.start.line,
+                        edit.range.start.character,
+                        edit.new_text,
+                    )
+                })
+                .collect();
+        assert_eq!(
+            insertions,
+            vec![
+                ("app".to_string(), 0, 27, " src/new.cpp".to_string()),
+                ("core".to_string(), 3, 11, "\n  src/new.cpp".to_string()),
+                ("util".to_string(), 6, 36, " src/new.cpp".to_string()),
+                ("lib".to_string(), 10, 32, " src/new.cpp".to_string()),
+                (
+                    "pub".to_string(),
+                    12,
+                    37,
+                    " PRIVATE src/new.cpp".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -1,78 +1,225 @@
 pub mod cache;
 pub mod target;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{LazyLock, Mutex};
 
 use cache::Cache;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tower_lsp::lsp_types::CompletionItem;
+use tower_lsp::lsp_types::{CompletionItem, Location, Position, Range, Uri};
 
-use crate::fileapi::target::Target;
+use crate::fileapi::target::{BuildType, CompileInfo, Target, TargetType};
+use crate::utils::owning_root;
 
-static CACHE_DATA: LazyLock<Mutex<Option<Cache>>> = LazyLock::new(|| Mutex::new(None));
-static TARGET_DATA: LazyLock<Mutex<HashMap<String, Target>>> =
+/// The cache of the build directory of every project root
+static CACHE_DATA: LazyLock<Mutex<HashMap<PathBuf, Cache>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// The targets of the build directory of every project root
+static TARGET_DATA: LazyLock<Mutex<HashMap<PathBuf, HashMap<String, Target>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The cache variables of the configure preset of every project root, which are known before
+/// CMake writes the cache
+static PRESET_ENTRIES: LazyLock<Mutex<HashMap<PathBuf, HashMap<String, String>>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
-pub fn update_target_data<P: AsRef<Path>>(target: P) -> Option<Target> {
+pub fn update_target_data<R: AsRef<Path>, P: AsRef<Path>>(root: R, target: P) -> Option<Target> {
     let target = Target::read(target)?;
     let mut data = TARGET_DATA.lock().ok()?;
-    data.insert(target.name.clone(), target.clone());
+    data.entry(root.as_ref().to_path_buf())
+        .or_default()
+        .insert(target.name.clone(), target.clone());
     Some(target)
 }
 
-pub fn update_cache_data<P: AsRef<Path>>(cache_file: P) -> Option<Cache> {
+pub fn update_cache_data<R: AsRef<Path>, P: AsRef<Path>>(root: R, cache_file: P) -> Option<Cache> {
     use std::fs::File;
     let file = File::open(cache_file).ok()?;
 
     let cache: Cache = serde_json::from_reader(file).ok()?;
 
-    set_cache_data(cache)
+    set_cache_data(root, cache)
 }
 
-pub fn get_target_hover(name: &str) -> Option<String> {
+/// Get the targets of the project which owns the file
+pub fn get_targets(path: &Path) -> Option<HashMap<String, Target>> {
     let data = TARGET_DATA.lock().ok()?;
-    Some(data.get(name)?.hover())
+    let root = owning_root(data.keys(), path)?;
+    data.get(root).cloned()
 }
 
-pub fn get_target_data(name: &str) -> Option<Target> {
-    let data = TARGET_DATA.lock().ok()?;
-    data.get(name).cloned()
+pub fn get_target_hover(path: &Path, name: &str) -> Option<String> {
+    Some(get_targets(path)?.get(name)?.hover())
+}
+
+pub fn get_target_data(path: &Path, name: &str) -> Option<Target> {
+    get_targets(path)?.remove(name)
+}
+
+/// A target compiling a file, for the `neocmake/targetsForFile` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTarget {
+    pub name: String,
+    pub build_type: BuildType,
+    pub target_type: TargetType,
+    /// The command creating the target, [`None`] if it is not written in the project
+    pub location: Option<Location>,
+}
+
+/// How a file is compiled by a target, for the `neocmake/compileInfo` request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCompileInfo {
+    pub target: String,
+    pub build_type: BuildType,
+    #[serde(flatten)]
+    pub info: CompileInfo,
+}
+
+/// The top source directory of the project, which the paths of the fileapi replies are relative
+/// to. The root is used until the cache is read
+fn source_dir(root: &Path) -> PathBuf {
+    let Ok(data) = CACHE_DATA.lock() else {
+        return root.to_path_buf();
+    };
+    data.get(root)
+        .and_then(|cache| {
+            cache
+                .entries
+                .iter()
+                .find(|entry| entry.name == "CMAKE_HOME_DIRECTORY")
+        })
+        .map_or_else(|| root.to_path_buf(), |entry| PathBuf::from(&entry.value))
+}
+
+/// Map the targets which have the file in their sources, sorted by name, with the top source
+/// directory
+fn map_targets_with_source<T>(path: &Path, f: impl Fn(&Path, &Target) -> Option<T>) -> Vec<T> {
+    let Ok(data) = TARGET_DATA.lock() else {
+        return vec![];
+    };
+    let Some(root) = owning_root(data.keys(), path) else {
+        return vec![];
+    };
+    let source_dir = source_dir(root);
+    let mut targets: Vec<&Target> = data
+        .get(root)
+        .into_iter()
+        .flat_map(HashMap::values)
+        .filter(|target| target.info.has_source(&source_dir, path))
+        .collect();
+    targets.sort_by(|a, b| a.name.cmp(&b.name));
+    targets
+        .into_iter()
+        .filter_map(|target| f(&source_dir, target))
+        .collect()
+}
+
+/// Get the targets which have the file in their sources
+pub fn get_targets_for_file(path: &Path) -> Vec<FileTarget> {
+    map_targets_with_source(path, |source_dir, target| {
+        Some(FileTarget {
+            name: target.name.clone(),
+            build_type: target.build_type.clone(),
+            target_type: target.info.target_type(),
+            location: target
+                .info
+                .declaration(source_dir)
+                .and_then(|(file, line)| {
+                    let position = Position {
+                        line: line - 1,
+                        character: 0,
+                    };
+                    Some(Location {
+                        uri: Uri::from_file_path(file).ok()?,
+                        range: Range {
+                            start: position,
+                            end: position,
+                        },
+                    })
+                }),
+        })
+    })
+}
+
+/// Get how the targets compile the file
+pub fn get_compile_info_for_file(path: &Path) -> Vec<FileCompileInfo> {
+    map_targets_with_source(path, |source_dir, target| {
+        Some(FileCompileInfo {
+            target: target.name.clone(),
+            build_type: target.build_type.clone(),
+            info: target.info.compile_info(source_dir, path)?,
+        })
+    })
 }
-pub fn get_all_targets() -> Option<HashMap<String, Target>> {
+
+/// Get the targets of all the projects, keyed by the root of each project
+pub fn get_all_targets() -> Option<HashMap<Uri, HashMap<String, Target>>> {
     let data = TARGET_DATA.lock().ok()?;
-    Some(data.clone())
+    Some(
+        data.iter()
+            .filter_map(|(root, targets)| {
+                Some((Uri::from_directory_path(root).ok()?, targets.clone()))
+            })
+            .collect(),
+    )
 }
 
-pub fn get_cache_data() -> Option<Cache> {
+/// Get the cache of the project which owns the file
+pub fn get_cache_data(path: &Path) -> Option<Cache> {
     let data = CACHE_DATA.lock().ok()?;
-    data.clone()
+    let root = owning_root(data.keys(), path)?;
+    data.get(root).cloned()
 }
-pub fn set_cache_data(cache: Cache) -> Option<Cache> {
+
+pub fn set_cache_data<R: AsRef<Path>>(root: R, cache: Cache) -> Option<Cache> {
     let mut data = CACHE_DATA.lock().ok()?;
-    let old_data = data.take();
-    *data = Some(cache);
-    old_data
+    data.insert(root.as_ref().to_path_buf(), cache)
 }
 
-#[allow(dead_code)]
-pub fn clear_cache_data() -> Option<Cache> {
-    let mut data = CACHE_DATA.lock().ok()?;
-    data.take()
+/// Forget the data of a project, when its folder is removed from the workspace
+pub fn clear_root_data(root: &Path) {
+    if let Ok(mut data) = CACHE_DATA.lock() {
+        data.remove(root);
+    }
+    if let Ok(mut data) = TARGET_DATA.lock() {
+        data.remove(root);
+    }
+    if let Ok(mut data) = PRESET_ENTRIES.lock() {
+        data.remove(root);
+    }
+}
+
+pub fn set_preset_entries<R: AsRef<Path>>(root: R, entries: HashMap<String, String>) {
+    if let Ok(mut data) = PRESET_ENTRIES.lock() {
+        data.insert(root.as_ref().to_path_buf(), entries);
+    }
+}
+
+fn get_preset_entries(path: &Path) -> Option<HashMap<String, String>> {
+    let data = PRESET_ENTRIES.lock().ok()?;
+    let root = owning_root(data.keys(), path)?;
+    data.get(root).cloned()
 }
 
 #[inline]
-pub fn get_complete_data() -> Option<Vec<CompletionItem>> {
-    Some(get_cache_data()?.gen_completions())
+pub fn get_complete_data(path: &Path) -> Option<Vec<CompletionItem>> {
+    Some(get_cache_data(path)?.gen_completions())
 }
 
+/// The cached values of the project, the ones of the preset are used until CMake writes the cache
 #[inline]
-pub fn get_entries_data() -> Option<HashMap<String, String>> {
-    let entries = get_cache_data()?.entries;
-    let mut map = HashMap::new();
+pub fn get_entries_data(path: &Path) -> Option<HashMap<String, String>> {
+    let cache = get_cache_data(path);
+    let preset_entries = get_preset_entries(path);
+    if cache.is_none() && preset_entries.as_ref().is_none_or(HashMap::is_empty) {
+        return None;
+    }
+    let mut map = preset_entries.unwrap_or_default();
 
-    for entry in entries {
+    for entry in cache.map(|cache| cache.entries).unwrap_or_default() {
         map.insert(entry.name.clone(), entry.value.clone());
     }
     Some(map)
@@ -140,6 +287,7 @@ impl QueryJson {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fileapi::target::TargetInfo;
 
     #[test]
     fn test_serde() {
@@ -157,4 +305,47 @@ mod tests {
         ))
         .unwrap();
     }
+
+    #[test]
+    fn test_targets_in_source_dir() {
+        // NOTE: the workspace folder is above the top source directory of the project
+        let root = Path::new("/home/cht/cpp");
+        let source_dir = root.join("MimeApp2");
+        let info: TargetInfo =
+            serde_json::from_str(include_str!("../assets_for_test/waycrate.json")).unwrap();
+        TARGET_DATA.lock().unwrap().insert(
+            root.to_path_buf(),
+            HashMap::from([(
+                "waycratelock".to_string(),
+                Target {
+                    build_type: BuildType::Debug,
+                    info,
+                    name: "waycratelock".to_string(),
+                },
+            )]),
+        );
+        let cache: Cache = serde_json::from_value(serde_json::json!({
+            "entries": [{
+                "name": "CMAKE_HOME_DIRECTORY",
+                "properties": [],
+                "type": "INTERNAL",
+                "value": source_dir,
+            }],
+            "kind": "cache",
+            "version": { "major": 2, "minor": 0 },
+        }))
+        .unwrap();
+        set_cache_data(root, cache);
+
+        let main = source_dir.join("src/main.cpp");
+        let targets = get_targets_for_file(&main);
+        assert_eq!(targets.len(), 1);
+        assert_eq!(
+            targets[0].location.as_ref().unwrap().uri,
+            Uri::from_file_path(source_dir.join("CMakeLists.txt")).unwrap()
+        );
+        assert_eq!(get_compile_info_for_file(&main).len(), 1);
+        assert!(get_targets_for_file(&root.join("src/main.cpp")).is_empty());
+        clear_root_data(root);
+    }
 }
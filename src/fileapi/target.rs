@@ -1,6 +1,10 @@
 use crate::fileapi::ApiVersion;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path, sync::LazyLock};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
 
 pub static TARGET_REGEX: LazyLock<regex::Regex> =
     LazyLock::new(|| regex::Regex::new(r"(target-(?P<name>[0-9a-zA-Z\-]+)-.+.json)").unwrap());
@@ -91,7 +95,31 @@ mod compile_group {
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct CommandFragment {
-        fragment: String,
+        pub fragment: String,
+        #[serde(flatten)]
+        _others: HashMap<String, serde_json::Value>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Include {
+        pub path: String,
+        #[serde(default)]
+        pub is_system: bool,
+        #[serde(flatten)]
+        _others: HashMap<String, serde_json::Value>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Define {
+        pub define: String,
+        #[serde(flatten)]
+        _others: HashMap<String, serde_json::Value>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct LanguageStandard {
+        pub standard: String,
         #[serde(flatten)]
         _others: HashMap<String, serde_json::Value>,
     }
@@ -100,23 +128,126 @@ mod compile_group {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CompileGroup {
+    #[serde(default)]
     compile_command_fragments: Vec<compile_group::CommandFragment>,
+    #[serde(default)]
+    includes: Vec<compile_group::Include>,
+    #[serde(default)]
+    defines: Vec<compile_group::Define>,
     language: String,
+    language_standard: Option<compile_group::LanguageStandard>,
+    #[serde(default)]
+    source_indexes: Vec<usize>,
     #[serde(flatten)]
     _others: HashMap<String, serde_json::Value>,
 }
 
+/// How the sources of a compile group are built
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileInfo {
+    pub language: String,
+    pub standard: Option<String>,
+    pub includes: Vec<String>,
+    pub system_includes: Vec<String>,
+    pub defines: Vec<String>,
+    /// The fragments of the compile command, which can hold several flags
+    pub flags: Vec<String>,
+    pub sources: Vec<String>,
+}
+
+impl CompileInfo {
+    pub fn hover(&self) -> String {
+        let mut hover_info = format!("  language: {}\n", self.language);
+        if let Some(standard) = &self.standard {
+            hover_info.push_str(&format!("  standard: {standard}\n"));
+        }
+        if !self.flags.is_empty() {
+            hover_info.push_str(&format!("  flags: {}\n", self.flags.join(" ")));
+        }
+        for define in &self.defines {
+            hover_info.push_str(&format!("  define: {define}\n"));
+        }
+        for include in &self.includes {
+            hover_info.push_str(&format!("  include: {include}\n"));
+        }
+        for include in &self.system_includes {
+            hover_info.push_str(&format!("  system include: {include}\n"));
+        }
+        hover_info
+    }
+}
+
+impl CompileGroup {
+    fn info(&self, sources: &[Source]) -> CompileInfo {
+        let (system_includes, includes): (Vec<_>, Vec<_>) =
+            self.includes.iter().partition(|include| include.is_system);
+        CompileInfo {
+            language: self.language.clone(),
+            standard: self
+                .language_standard
+                .as_ref()
+                .map(|standard| standard.standard.clone()),
+            includes: includes
+                .into_iter()
+                .map(|include| include.path.clone())
+                .collect(),
+            system_includes: system_includes
+                .into_iter()
+                .map(|include| include.path.clone())
+                .collect(),
+            defines: self
+                .defines
+                .iter()
+                .map(|define| define.define.clone())
+                .collect(),
+            flags: self
+                .compile_command_fragments
+                .iter()
+                .map(|fragment| fragment.fragment.clone())
+                .collect(),
+            sources: self
+                .source_indexes
+                .iter()
+                .filter_map(|index| sources.get(*index))
+                .map(|source| source.path.clone())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktraceNode {
+    file: usize,
+    /// NOTE: the deferred calls have the line -1
+    line: Option<i32>,
+    command: Option<usize>,
+    parent: Option<usize>,
+}
+
+/// The call stacks of the commands which made the target, the nodes point to their caller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktraceGraph {
+    commands: Vec<String>,
+    files: Vec<String>,
+    nodes: Vec<BacktraceNode>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Source {
     backtrace: i32,
     backtraces: Vec<i32>,
     path: String,
+    compile_group_index: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TargetInfo {
     artifacts: Vec<Artifact>,
+    backtrace: Option<usize>,
+    backtrace_graph: Option<BacktraceGraph>,
     codemodel_version: ApiVersion,
     compile_groups: Option<Vec<CompileGroup>>,
     #[serde(rename = "type")]
@@ -140,8 +271,35 @@ impl TargetInfo {
         for Source { path, .. } in &self.sources {
             hover_info.push_str(&format!("  path: {path}\n"));
         }
+        for info in self.compile_infos() {
+            hover_info.push('\n');
+            hover_info.push_str("compile group:\n");
+            hover_info.push_str(&info.hover());
+        }
         hover_info
     }
+
+    /// The compile groups, with their sources
+    pub fn compile_infos(&self) -> Vec<CompileInfo> {
+        self.compile_groups
+            .iter()
+            .flatten()
+            .map(|group| group.info(&self.sources))
+            .collect()
+    }
+
+    /// How the file is compiled, [`None`] if it is not a compiled source of the target
+    pub fn compile_info(&self, source_dir: &Path, file: &Path) -> Option<CompileInfo> {
+        let source = self
+            .sources
+            .iter()
+            .find(|source| source_dir.join(&source.path) == file)?;
+        let group = self
+            .compile_groups
+            .as_ref()?
+            .get(source.compile_group_index?)?;
+        Some(group.info(&self.sources))
+    }
     pub fn target_type(&self) -> TargetType {
         if self.type_ == "EXECUTABLE" {
             return TargetType::Executable;
@@ -151,6 +309,33 @@ impl TargetInfo {
     pub fn artifacts(&self) -> &[Artifact] {
         &self.artifacts
     }
+
+    /// If the file is one of the sources, which are relative to the top source directory
+    pub fn has_source(&self, source_dir: &Path, file: &Path) -> bool {
+        self.sources
+            .iter()
+            .any(|Source { path, .. }| source_dir.join(path) == file)
+    }
+
+    /// The file and the line of the command creating the target
+    ///
+    /// NOTE: the target can be created in a function of a module, so the innermost call written
+    /// in the project is used
+    pub fn declaration(&self, source_dir: &Path) -> Option<(PathBuf, u32)> {
+        let graph = self.backtrace_graph.as_ref()?;
+        let mut index = self.backtrace;
+        while let Some(node) = index.and_then(|index| graph.nodes.get(index)) {
+            let file = source_dir.join(graph.files.get(node.file)?);
+            if let Some(line) = node.line.and_then(|line| u32::try_from(line).ok())
+                && line > 0
+                && file.starts_with(source_dir)
+            {
+                return Some((file, line));
+            }
+            index = node.parent;
+        }
+        None
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -161,6 +346,8 @@ pub enum TargetType {
 
 #[cfg(test)]
 mod test {
+    use std::path::Path;
+
     use crate::fileapi::target::{TARGET_REGEX, TargetInfo, TargetType};
 
     #[test]
@@ -171,6 +358,53 @@ mod test {
         assert_eq!(target.artifacts[0].path, "waycratelock");
     }
 
+    #[test]
+    fn target_sources_and_declaration() {
+        let file = include_str!("../../assets_for_test/waycrate.json");
+        let target: TargetInfo = serde_json::from_str(file).unwrap();
+        let source_dir = Path::new("/home/cht/cpp/MimeApp2");
+        assert!(target.has_source(source_dir, &source_dir.join("src/main.cpp")));
+        assert!(!target.has_source(source_dir, &source_dir.join("src/other.cpp")));
+        // NOTE: qt_add_executable calls add_executable in the Qt modules
+        assert_eq!(
+            target.declaration(source_dir),
+            Some((source_dir.join("CMakeLists.txt"), 31))
+        );
+    }
+
+    #[test]
+    fn target_compile_info() {
+        let file = include_str!("../../assets_for_test/waycrate.json");
+        let target: TargetInfo = serde_json::from_str(file).unwrap();
+        let source_dir = Path::new("/home/cht/cpp/MimeApp2");
+        let info = target
+            .compile_info(source_dir, &source_dir.join("src/main.cpp"))
+            .unwrap();
+        assert_eq!(info.language, "CXX");
+        assert_eq!(info.standard.as_deref(), Some("20"));
+        assert_eq!(
+            info.flags,
+            vec!["-g -std=gnu++20", "-mno-direct-extern-access"]
+        );
+        assert!(info.defines.contains(&"QT_CORE_LIB".to_string()));
+        assert!(
+            info.includes
+                .contains(&"/home/cht/cpp/MimeApp2/src".to_string())
+        );
+        assert!(
+            info.system_includes
+                .contains(&"/usr/include/qt6".to_string())
+        );
+        assert!(info.sources.contains(&"src/main.cpp".to_string()));
+        assert_eq!(target.compile_infos(), vec![info]);
+        // NOTE: the resources are sources which are not compiled
+        assert!(
+            target
+                .compile_info(source_dir, &source_dir.join("assets/resource.qrc"))
+                .is_none()
+        );
+    }
+
     #[test]
     fn build_type_read() {
         let file_name = "target-waycratelock-Debug-7d1c13a099b19b474ca1.json";
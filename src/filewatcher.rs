@@ -1,14 +1,22 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{LazyLock, Mutex};
 
+use crate::utils::owning_root;
+
 // match like ss_DIR:PATH=ss_DIR-NOTFOUND
 static NOT_FOUND_LIBRARY: LazyLock<regex::Regex> = LazyLock::new(|| {
     regex::Regex::new(r"^(?P<library>[\da-zA-Z]+)_DIR:PATH=([\da-zA-Z]+)_DIR-NOTFOUND$").unwrap()
 });
 
-static ERROR_PACKAGES: LazyLock<Mutex<Vec<String>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+/// The packages which are not found in the CMakeCache.txt of every project root
+static ERROR_PACKAGES: LazyLock<Mutex<HashMap<PathBuf, Vec<String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
-pub fn refresh_error_packages<P: AsRef<Path>>(cmake_cache: P) -> Option<Vec<String>> {
+pub fn refresh_error_packages<R: AsRef<Path>, P: AsRef<Path>>(
+    root: R,
+    cmake_cache: P,
+) -> Option<Vec<String>> {
     use std::fs;
     let mut toswap_packages: Vec<String> = Vec::new();
     let context = fs::read_to_string(cmake_cache.as_ref()).ok()?;
@@ -19,22 +27,25 @@ pub fn refresh_error_packages<P: AsRef<Path>>(cmake_cache: P) -> Option<Vec<Stri
         toswap_packages.push(cap["library"].to_string());
     }
     let mut packages = ERROR_PACKAGES.lock().ok()?;
-    std::mem::swap(&mut *packages, &mut toswap_packages);
+    let packages = packages.entry(root.as_ref().to_path_buf()).or_default();
+    std::mem::swap(packages, &mut toswap_packages);
     Some(toswap_packages)
 }
 
-pub fn clear_error_packages() -> Option<Vec<String>> {
+pub fn clear_error_packages<R: AsRef<Path>>(root: R) -> Option<Vec<String>> {
     let mut packages = ERROR_PACKAGES.lock().ok()?;
-    let mut old_packages = vec![];
-    std::mem::swap(&mut *packages, &mut old_packages);
-    Some(old_packages)
+    Some(packages.remove(root.as_ref()).unwrap_or_default())
 }
 
-pub fn get_error_packages() -> Vec<String> {
+/// Get the packages which are not found by the project which owns the file
+pub fn get_error_packages(path: &Path) -> Vec<String> {
     let Ok(packages) = ERROR_PACKAGES.lock() else {
         return vec![];
     };
-    packages.to_vec()
+    owning_root(packages.keys(), path)
+        .and_then(|root| packages.get(root))
+        .cloned()
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -62,13 +73,15 @@ ss_DIR:PATH=ss_DIR-NOTFOUND
 ";
         let mut cache_file = File::create(&error_cmake).unwrap();
         writeln!(cache_file, "{}", cache_info).unwrap();
-        let origin = refresh_error_packages(error_cmake).unwrap();
+        let origin = refresh_error_packages(dir.path(), error_cmake).unwrap();
         assert!(origin.is_empty());
-        let error_packages = get_error_packages();
+        let source = dir.path().join("CMakeLists.txt");
+        let error_packages = get_error_packages(&source);
         assert_eq!(error_packages, vec!["ss"]);
-        let cleared_packages = clear_error_packages().unwrap();
+        assert!(get_error_packages(Path::new("/other/CMakeLists.txt")).is_empty());
+        let cleared_packages = clear_error_packages(dir.path()).unwrap();
         assert_eq!(cleared_packages, vec!["ss"]);
-        let error_packages_after = get_error_packages();
+        let error_packages_after = get_error_packages(&source);
         assert!(error_packages_after.is_empty());
     }
 }
@@ -0,0 +1,225 @@
+use tower_lsp::lsp_types::{FoldingRange, FoldingRangeKind};
+use tree_sitter::Node;
+
+use crate::CMakeNodeKinds;
+use crate::document::Document;
+use crate::utils::treehelper::BLOCK_KINDS;
+
+pub fn get_folding_ranges(document: &Document) -> Option<Vec<FoldingRange>> {
+    let mut ranges = vec![];
+    get_block_ranges(document.tree().root_node(), &mut ranges);
+    get_comment_ranges(document, &mut ranges);
+    if ranges.is_empty() {
+        return None;
+    }
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    Some(ranges)
+}
+
+const fn folding_range(
+    start_line: usize,
+    end_line: usize,
+    kind: Option<FoldingRangeKind>,
+) -> FoldingRange {
+    FoldingRange {
+        start_line: start_line as u32,
+        start_character: None,
+        end_line: end_line as u32,
+        end_character: None,
+        kind,
+        collapsed_text: None,
+    }
+}
+
+/// Every branch of a block is folded until the line before the next command of the block, so
+/// `elseif()`, `else()` and the end command stay visible, the same for the `)` of argument lists,
+/// which are folded for the commands opening the blocks too
+fn get_block_ranges(node: Node, ranges: &mut Vec<FoldingRange>) {
+    if BLOCK_KINDS.contains(&node.kind()) {
+        let mut walk = node.walk();
+        let rows: Vec<usize> = node
+            .children(&mut walk)
+            .filter(|child| child.kind().ends_with("_command"))
+            .map(|child| child.start_position().row)
+            .collect();
+        for pair in rows.windows(2) {
+            let (start, end) = (pair[0], pair[1].saturating_sub(1));
+            if end > start {
+                ranges.push(folding_range(start, end, None));
+            }
+        }
+    } else if node.kind().ends_with("_command")
+        && let Some(argument_list) = node
+            .children(&mut node.walk())
+            .find(|child| child.kind() == CMakeNodeKinds::ARGUMENT_LIST)
+        && let Some(last_arg) =
+            argument_list.child(argument_list.child_count().saturating_sub(1) as u32)
+    {
+        let start = node.start_position().row;
+        let end = last_arg.end_position().row;
+        if end > start {
+            ranges.push(folding_range(start, end, None));
+        }
+    } else if node.kind() == CMakeNodeKinds::BRACKET_COMMENT {
+        let start = node.start_position().row;
+        let end = node.end_position().row;
+        if end > start {
+            ranges.push(folding_range(start, end, Some(FoldingRangeKind::Comment)));
+        }
+        return;
+    }
+
+    let mut walk = node.walk();
+    for child in node.children(&mut walk) {
+        get_block_ranges(child, ranges);
+    }
+}
+
+/// Fold the runs of line comments
+fn get_comment_ranges(document: &Document, ranges: &mut Vec<FoldingRange>) {
+    let analysis = document.analysis();
+    // NOTE: only the comments which take the whole line are folded together, comments after
+    // commands are left alone
+    let lines: Vec<&str> = document.text().lines().collect();
+    let mut comment_rows = analysis.line_comments.iter().filter_map(|comment| {
+        let point = comment.range.start_point;
+        lines
+            .get(point.row)
+            .and_then(|line| line.get(..point.column))
+            .is_some_and(|prefix| prefix.trim().is_empty())
+            .then_some(point.row)
+    });
+    let Some(mut start) = comment_rows.next() else {
+        return;
+    };
+    let mut end = start;
+    for row in comment_rows {
+        if row == end + 1 {
+            end = row;
+            continue;
+        }
+        if end > start {
+            ranges.push(folding_range(start, end, Some(FoldingRangeKind::Comment)));
+        }
+        start = row;
+        end = row;
+    }
+    if end > start {
+        ranges.push(folding_range(start, end, Some(FoldingRangeKind::Comment)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(ranges: &[FoldingRange]) -> Vec<(u32, u32, bool)> {
+        ranges
+            .iter()
+            .map(|range| {
+                (
+                    range.start_line,
+                    range.end_line,
+                    range.kind == Some(FoldingRangeKind::Comment),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_block_folding() {
+        let source = r#"if(A)
+  message("a")
+elseif(B)
+  message("b")
+else()
+  foreach(item IN LISTS items)
+    message(${item})
+  endforeach()
+endif()
+function(hello name)
+  while(A)
+    block()
+      set(B 1)
+    endblock()
+  endwhile()
+endfunction()
+macro(world)
+endmacro()
+"#;
+        let document = Document::new(source);
+        let ranges = get_folding_ranges(&document).unwrap();
+        assert_eq!(
+            lines(&ranges),
+            vec![
+                (0, 1, false),
+                (2, 3, false),
+                (4, 7, false),
+                (5, 6, false),
+                (9, 14, false),
+                (10, 13, false),
+                (11, 12, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_argument_list_folding() {
+        let source = r"set(SOURCES
+    main.cpp
+    hello.cpp
+)
+set(A 1)
+";
+        let document = Document::new(source);
+        let ranges = get_folding_ranges(&document).unwrap();
+        assert_eq!(lines(&ranges), vec![(0, 2, false)]);
+    }
+
+    #[test]
+    fn test_block_argument_list_folding() {
+        let source = r"if(A
+    AND B
+)
+  message(a)
+endif()
+function(hello
+    name
+    value
+)
+endfunction()
+";
+        let document = Document::new(source);
+        let ranges = get_folding_ranges(&document).unwrap();
+        assert_eq!(
+            lines(&ranges),
+            vec![(0, 1, false), (0, 3, false), (5, 7, false), (5, 8, false)]
+        );
+    }
+
+    #[test]
+    fn test_comment_folding() {
+        let source = r"# first
+# second
+set(A 1) # after
+# alone
+#[[
+bracket
+]]
+# third
+  # fourth
+";
+        let document = Document::new(source);
+        let ranges = get_folding_ranges(&document).unwrap();
+        assert_eq!(
+            lines(&ranges),
+            vec![(0, 1, true), (4, 6, true), (7, 8, true)]
+        );
+    }
+
+    #[test]
+    fn test_no_folding() {
+        let document = Document::new("set(A 1)\n");
+        assert_eq!(get_folding_ranges(&document), None);
+    }
+}
@@ -8,7 +8,7 @@ use tokio::process::Command;
 use tower_lsp::lsp_types;
 
 use crate::CMakeNodeKinds;
-use crate::config::CONFIG;
+use crate::config;
 use crate::consts::TREESITTER_CMAKE_LANGUAGE;
 use crate::utils::treehelper::contain_comment;
 
@@ -117,14 +117,16 @@ fn get_space(spacelen: u32, use_space: bool) -> String {
 pub async fn getformat(
     root_path: Option<&Path>,
     source: &str,
+    root: tree_sitter::Node<'_>,
     client: &tower_lsp::Client,
     spacelen: u32,
     use_space: bool,
     insert_final_newline: bool,
 ) -> Option<Vec<TextEdit>> {
-    if let Some(program) = &CONFIG.format.program {
+    let config = root_path.map_or_else(|| config::CONFIG.clone(), config::get_config);
+    if let Some(program) = &config.format.program {
         let mut cmd = Command::new(program);
-        if let Some(ref args) = CONFIG.format.args {
+        if let Some(ref args) = config.format.args {
             cmd.args(args);
         }
         if let Some(root_path) = root_path {
@@ -225,18 +227,14 @@ pub async fn getformat(
         }]);
     }
 
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(source, None).unwrap();
-
-    if tree.root_node().has_error() {
+    if root.has_error() {
         client
             .log_message(MessageType::Warning, "Error source")
             .await;
         return None;
     }
     let (mut new_text, endline) = format_content(
-        tree.root_node(),
+        root,
         &source.lines().collect(),
         spacelen,
         use_space,
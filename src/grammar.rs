@@ -6,14 +6,15 @@ use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position};
 use tree_sitter::{Point, Query, QueryCursor, StreamingIterator};
 
-use crate::config::{self, CONFIG, CommandCase};
+use crate::config::{self, CommandCase};
 use crate::consts::TREESITTER_CMAKE_LANGUAGE;
 use crate::utils::query::{get_functions, get_macros, get_normal_commands};
-use crate::utils::treehelper::ToPosition;
+use crate::utils::treehelper::{ToPosition, all_repeated_arguments};
 use crate::utils::{NeoStrExt, include_is_module};
 
 const INCLUDE_CHECK_KEYWORDS: &[&str; 2] = &["include", "add_subdirectory"];
 
+#[derive(Debug, Clone, Copy, Hash)]
 pub struct LintConfigInfo {
     pub use_lint: bool,
     pub use_extra_cmake_lint: bool,
@@ -30,6 +31,10 @@ pub enum ErrorType {
         max: u32,
     },
     Gammar,
+    /// The closer of a block does not repeat the arguments of the opener
+    BlockMismatch {
+        expected: String,
+    },
     #[default]
     Other,
 }
@@ -37,6 +42,7 @@ pub enum ErrorType {
 pub fn checkerror<P: AsRef<Path>>(
     local_path: &P,
     source: &str,
+    root: tree_sitter::Node<'_>,
     LintConfigInfo {
         use_lint,
         use_extra_cmake_lint,
@@ -48,10 +54,7 @@ pub fn checkerror<P: AsRef<Path>>(
     } else {
         None
     };
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(source, None)?;
-    let mut result = checkerror_inner(local_path, source, thetree.root_node(), use_lint);
+    let mut result = checkerror_inner(local_path, source, root, use_lint);
     if let Some(v) = cmake_lint_info {
         let error_info = result.get_or_insert(vec![]);
         error_info.extend(v);
@@ -75,7 +78,7 @@ fn run_cmake_lint<P: AsRef<Path>>(
         return run_extra_lint(path);
     }
     let mut info = vec![];
-    let max_len = CONFIG.line_max_words;
+    let max_len = config::get_config(path.as_ref()).line_max_words;
     for (index, line) in contexts.iter().enumerate() {
         let len = line.len();
         if len > max_len {
@@ -209,6 +212,7 @@ fn checkerror_inner<P: AsRef<Path>>(
     }
     let source_bytes = source.as_bytes();
     let local_path = local_path.as_ref();
+    let command_case = config::get_config(local_path).command_case;
     let mut output = vec![];
 
     let query_error = Query::new(&TREESITTER_CMAKE_LANGUAGE, ERROR_QUERY).unwrap();
@@ -236,7 +240,7 @@ fn checkerror_inner<P: AsRef<Path>>(
             });
         }
     }
-    if use_lint && let Some(command_case) = config::CONFIG.command_case {
+    if use_lint && let Some(command_case) = command_case {
         let macros = get_macros(source_bytes, input, None);
         let functions = get_functions(source_bytes, input, None);
         for macro_node in macros {
@@ -305,7 +309,7 @@ fn checkerror_inner<P: AsRef<Path>>(
         let name = query_now.identifier;
         let name_node = query_now.identifier_node;
         if use_lint
-            && let Some(command_case) = config::CONFIG.command_case
+            && let Some(command_case) = command_case
             && let Some(hint) = command_case.check(name)
         {
             let pointx = name_node.start_position().to_position();
@@ -334,7 +338,7 @@ fn checkerror_inner<P: AsRef<Path>>(
         }
         let lowercase_name = name.to_lowercase();
         if lowercase_name == "find_package" {
-            let errorpackages = crate::filewatcher::get_error_packages();
+            let errorpackages = crate::filewatcher::get_error_packages(local_path);
             if errorpackages.is_empty() {
                 continue;
             }
@@ -371,7 +375,7 @@ fn checkerror_inner<P: AsRef<Path>>(
             let Some(first_arg) = query_now.first_arg else {
                 continue;
             };
-            let Some(first_arg) = first_arg.try_replace_placeholders() else {
+            let Some(first_arg) = first_arg.try_replace_placeholders(local_path) else {
                 continue;
             };
             let first_arg_node = query_now.args[0];
@@ -488,6 +492,50 @@ fn checkerror_inner<P: AsRef<Path>>(
         }
     }
 
+    // NOTE: the mismatched closers are a style lint
+    if use_lint {
+        let mut blocks = vec![];
+        all_repeated_arguments(input, &mut blocks);
+        for block in blocks {
+            if block.matches(source) {
+                continue;
+            }
+            let Some(repeated) = block.repeated else {
+                continue;
+            };
+            let keyword = |command: tree_sitter::Node| {
+                command
+                    .child(0)
+                    .and_then(|keyword| keyword.utf8_text(source_bytes).ok())
+                    .unwrap_or_default()
+                    .to_owned()
+            };
+            let expected = block.expected.utf8_text(source_bytes).unwrap().to_owned();
+            let pointx = repeated.start_position().to_position();
+            let pointy = repeated.end_position().to_position();
+            let range = Range {
+                start: pointx,
+                end: pointy,
+            };
+            output.push(Diagnostic {
+                range,
+                message: format!(
+                    "Arguments of {}() do not match {}({expected})",
+                    keyword(block.closer),
+                    keyword(block.opener)
+                )
+                .into(),
+                severity: Some(DiagnosticSeverity::Warning),
+                code: None,
+                code_description: None,
+                source: None,
+                related_information: None,
+                tags: None,
+                data: Some(serde_json::to_value(ErrorType::BlockMismatch { expected }).unwrap()),
+            });
+        }
+    }
+
     if output.is_empty() {
         None
     } else {
@@ -547,7 +595,7 @@ mod tests {
             dir.path().display()
         );
         let template_cache: cache::Cache = serde_json::from_str(&json_value).unwrap();
-        set_cache_data(template_cache);
+        set_cache_data(dir.path(), template_cache);
         let gammar_file_src = r#"
 include("${ROOT_DIR}/hello.cmake")
 include("${ROOT_DIR}/hello_unexist.cmake")
@@ -695,6 +743,57 @@ include(abcd.text)
         );
     }
 
+    #[test]
+    fn block_mismatch_check() {
+        let source = r"function(hello)
+endfunction(world)
+if(A)
+endif(A)
+";
+        let mut parse = tree_sitter::Parser::new();
+        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
+        let thetree = parse.parse(source, None).unwrap();
+
+        use tower_lsp::lsp_types::Range;
+        assert_eq!(
+            checkerror_inner(
+                std::path::Path::new("."),
+                source,
+                thetree.root_node(),
+                false
+            ),
+            None
+        );
+        assert_eq!(
+            checkerror_inner(std::path::Path::new("."), source, thetree.root_node(), true),
+            Some(vec![Diagnostic {
+                range: Range {
+                    start: Position {
+                        line: 1,
+                        character: 12
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 17
+                    }
+                },
+                message: "Arguments of endfunction() do not match function(hello)".into(),
+                severity: Some(DiagnosticSeverity::Warning),
+                code: None,
+                code_description: None,
+                source: None,
+                related_information: None,
+                tags: None,
+                data: Some(
+                    serde_json::to_value(ErrorType::BlockMismatch {
+                        expected: "hello".to_owned()
+                    })
+                    .unwrap()
+                ),
+            }])
+        );
+    }
+
     #[test]
     fn test_lint_regex() {
         let input = r"aa.cmake:38,00: [C0305] too many newlines between statements
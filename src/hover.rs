@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use lsp_types::Position;
 use tower_lsp::lsp_types;
 use tree_sitter::Node;
@@ -11,7 +13,7 @@ use crate::utils::packagepkgconfig::PKG_CONFIG_PACKAGES_WITHKEY;
 use crate::utils::packagepkgconfig::PkgConfig;
 use crate::utils::treehelper::CurrentNodeInfo;
 use crate::utils::treehelper::{MESSAGE_STORAGE, PositionType, ToPoint};
-use crate::utils::{CACHE_CMAKE_PACKAGES_WITHKEYS, CMakePackage, PackageType, get_the_packagename};
+use crate::utils::{CMakePackage, PackageType, find_cmake_package, get_the_packagename};
 
 #[inline]
 #[cfg(unix)]
@@ -46,7 +48,12 @@ PackageVersion: {}
 }
 
 /// get the doc for on hover
-pub async fn get_hovered_doc(location: Position, root: Node<'_>, source: &str) -> Option<String> {
+pub async fn get_hovered_doc(
+    location: Position,
+    root: Node<'_>,
+    source: &str,
+    path: &Path,
+) -> Option<String> {
     let current_node_info = CurrentNodeInfo::get(source, root, location.to_point());
     let message = current_node_info.content()?;
     let inner_result = match current_node_info.pos_type() {
@@ -58,21 +65,21 @@ pub async fn get_hovered_doc(location: Position, root: Node<'_>, source: &str) -
         }
         PositionType::FindPackageSpace(spacename) => {
             let space_package_name = format!("{spacename}{message}");
-            let mut value = CACHE_CMAKE_PACKAGES_WITHKEYS.get(&space_package_name);
+            let mut value = find_cmake_package(&space_package_name);
             if value.is_none() {
-                value = CACHE_CMAKE_PACKAGES_WITHKEYS.get(message);
+                value = find_cmake_package(message);
             }
-            value.map(cmakepackage_document_fmt)
+            value.as_ref().map(cmakepackage_document_fmt)
         }
         PositionType::FindPackage | PositionType::TargetInclude | PositionType::TargetLink => {
             let package = get_the_packagename(message);
-            let mut value = CACHE_CMAKE_PACKAGES_WITHKEYS.get(package);
+            let mut value = find_cmake_package(package);
             if value.is_none() {
-                value = CACHE_CMAKE_PACKAGES_WITHKEYS.get(&package.to_lowercase());
+                value = find_cmake_package(&package.to_lowercase());
             }
-            value.map(cmakepackage_document_fmt)
+            value.as_ref().map(cmakepackage_document_fmt)
         }
-        PositionType::Target => get_target_hover(message),
+        PositionType::Target => get_target_hover(path, message),
         _ => {
             let mut value = MESSAGE_STORAGE.get(message);
             if value.is_none() {
@@ -93,7 +100,7 @@ pub async fn get_hovered_doc(location: Position, root: Node<'_>, source: &str) -
         .document_info
         .clone();
     // use cache_data to show info first
-    if let Some(cache_data) = fileapi::get_entries_data()
+    if let Some(cache_data) = fileapi::get_entries_data(path)
         && let Some(value) = cache_data.get(message)
     {
         return Some(format!("current cached value : {value}\n\n{cached_info}"));
@@ -125,6 +132,7 @@ find_package(bash-completion-fake)
             },
             thetree.root_node(),
             content,
+            Path::new("/tmp/CMakeLists.txt"),
         )
         .await
         .unwrap();
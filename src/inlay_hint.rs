@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{InlayHint, InlayHintKind, Label, Position, Range, Tooltip, Uri};
+
+use crate::complete::builtin::is_builtin_command;
+use crate::document::{Analysis, CommandIndex, Document};
+use crate::languageserver::get_or_update_document;
+use crate::utils::treehelper::ToPosition;
+use crate::{fileapi, jump};
+
+/// The longer values are cut, the full value is in the tooltip
+const MAX_VALUE_LENGTH: usize = 30;
+
+pub async fn get_inlay_hints<P: AsRef<Path>>(
+    document: &Document,
+    range: Range,
+    path: P,
+    documents: &DashMap<Uri, Document>,
+) -> Option<Vec<InlayHint>> {
+    let analysis = document.analysis();
+    let mut hints = vec![];
+    if let Some(values) = fileapi::get_entries_data(path.as_ref()) {
+        hints.append(&mut variable_hints(analysis, range, &values));
+    }
+    let parameters = function_parameters(analysis, range, path.as_ref(), documents).await;
+    hints.append(&mut parameter_hints(analysis, range, &parameters));
+    if hints.is_empty() {
+        return None;
+    }
+    hints.sort_by_key(|hint| (hint.position.line, hint.position.character));
+    Some(hints)
+}
+
+const fn in_range(range: Range, position: Position) -> bool {
+    range.start.line <= position.line && position.line <= range.end.line
+}
+
+/// Show the cached values after the variable references, like `${CMAKE_BUILD_TYPE} = Debug`
+fn variable_hints(
+    analysis: &Analysis,
+    range: Range,
+    values: &HashMap<String, String>,
+) -> Vec<InlayHint> {
+    analysis
+        .variables
+        .iter()
+        .filter(|var| in_range(range, var.range.end_point.to_position()))
+        .filter_map(|var| {
+            let value = values.get(&var.content)?;
+            let label = if value.chars().count() > MAX_VALUE_LENGTH {
+                let cut: String = value.chars().take(MAX_VALUE_LENGTH).collect();
+                format!("= {cut}…")
+            } else {
+                format!("= {value}")
+            };
+            // NOTE: the range is the one of the name, the hint goes after the `}`
+            let mut position = var.range.end_point.to_position();
+            position.character += 1;
+            Some(InlayHint {
+                position,
+                label: Label::String(label),
+                kind: None,
+                text_edits: None,
+                tooltip: Some(Tooltip::String(value.clone())),
+                padding_left: Some(true),
+                padding_right: None,
+                data: None,
+            })
+        })
+        .collect()
+}
+
+/// Get the parameters of the user functions and macros called in the range, the keys are
+/// lowercase because the commands are case insensitive
+async fn function_parameters(
+    analysis: &Analysis,
+    range: Range,
+    path: &Path,
+    documents: &DashMap<Uri, Document>,
+) -> HashMap<String, Vec<String>> {
+    let mut parameters = HashMap::new();
+    let mut not_found = HashSet::new();
+    for def in analysis.functions.iter().chain(analysis.macros.iter()) {
+        parameters.insert(
+            def.name.to_lowercase(),
+            def.args.iter().map(|arg| arg.content.clone()).collect(),
+        );
+    }
+    for command in analysis
+        .commands
+        .iter()
+        .filter(|command| in_range(range, command.range.start_point.to_position()))
+    {
+        let identifier = command.identifier.to_lowercase();
+        // NOTE: the builtin commands are not in the cache, looking for them walks all the files
+        if parameters.contains_key(&identifier)
+            || not_found.contains(&identifier)
+            || is_builtin_command(&identifier)
+        {
+            continue;
+        }
+        let Some(definition) = jump::get_cached_def(path, &identifier, documents).await else {
+            not_found.insert(identifier);
+            continue;
+        };
+        let Ok(def_path) = definition.loc.uri.to_file_path() else {
+            continue;
+        };
+        if !definition.is_function {
+            continue;
+        }
+        let Ok(def_document) = get_or_update_document(&def_path, documents).await else {
+            continue;
+        };
+        let def_analysis = def_document.analysis();
+        if let Some(def) = def_analysis
+            .functions
+            .iter()
+            .chain(def_analysis.macros.iter())
+            .find(|def| def.name.eq_ignore_ascii_case(&identifier))
+        {
+            parameters.insert(
+                identifier,
+                def.args.iter().map(|arg| arg.content.clone()).collect(),
+            );
+        }
+    }
+    parameters
+}
+
+/// Show the parameter names before the arguments of the calls to user functions, the extra
+/// arguments go to `ARGN` and get no hint
+fn parameter_hints(
+    analysis: &Analysis,
+    range: Range,
+    parameters: &HashMap<String, Vec<String>>,
+) -> Vec<InlayHint> {
+    let call_hints = |command: &CommandIndex| -> Vec<InlayHint> {
+        let Some(names) = parameters.get(&command.identifier.to_lowercase()) else {
+            return vec![];
+        };
+        command
+            .args
+            .iter()
+            .zip(names)
+            .filter(|(arg, name)| arg.content != **name)
+            .filter(|(arg, _)| in_range(range, arg.range.start_point.to_position()))
+            .map(|(arg, name)| InlayHint {
+                position: arg.range.start_point.to_position(),
+                label: Label::String(format!("{name}:")),
+                kind: Some(InlayHintKind::Parameter),
+                text_edits: None,
+                tooltip: None,
+                padding_left: None,
+                padding_right: Some(true),
+                data: None,
+            })
+            .collect()
+    };
+    analysis.commands.iter().flat_map(call_hints).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL_RANGE: Range = Range {
+        start: Position {
+            line: 0,
+            character: 0,
+        },
+        end: Position {
+            line: u32::MAX,
+            character: 0,
+        },
+    };
+
+    fn labels(hints: &[InlayHint]) -> Vec<(u32, u32, String)> {
+        hints
+            .iter()
+            .map(|hint| {
+                let Label::String(label) = &hint.label else {
+                    unreachable!()
+                };
+                (hint.position.line, hint.position.character, label.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_variable_hints() {
+        let document = Document::new(
+            r#"message("${CMAKE_BUILD_TYPE}")
+message(${UNKNOWN} ${LONG_VALUE})
+"#,
+        );
+        let values = HashMap::from([
+            ("CMAKE_BUILD_TYPE".to_string(), "Debug".to_string()),
+            ("LONG_VALUE".to_string(), "a".repeat(40)),
+        ]);
+        let hints = variable_hints(document.analysis(), FULL_RANGE, &values);
+        assert_eq!(
+            labels(&hints),
+            vec![
+                (0, 28, "= Debug".to_string()),
+                (1, 32, format!("= {}…", "a".repeat(30))),
+            ]
+        );
+        assert_eq!(hints[1].tooltip, Some(Tooltip::String("a".repeat(40))));
+    }
+
+    #[tokio::test]
+    async fn test_parameter_hints() {
+        let document = Document::new(
+            r"function(copy_file source destination)
+endfunction()
+copy_file(a.txt b.txt extra)
+COPY_FILE(source out.txt)
+message(STATUS hello)
+",
+        );
+        let documents = DashMap::new();
+        let parameters = function_parameters(
+            document.analysis(),
+            FULL_RANGE,
+            Path::new("/tmp/CMakeLists.txt"),
+            &documents,
+        )
+        .await;
+        let hints = parameter_hints(document.analysis(), FULL_RANGE, &parameters);
+        assert_eq!(
+            labels(&hints),
+            vec![
+                (2, 10, "source:".to_string()),
+                (2, 16, "destination:".to_string()),
+                (3, 17, "destination:".to_string()),
+            ]
+        );
+    }
+}
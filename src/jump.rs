@@ -7,29 +7,30 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use tower_lsp::lsp_types::{Location, MessageType, Position, Range, Uri};
 
-use crate::languageserver::get_or_update_buffer_contents;
+use crate::document::{Analysis, Document};
+use crate::languageserver::{get_or_update_document, read_document};
 use crate::scansubs::TREE_CMAKE_MAP;
 use crate::utils::NeoStrExt;
 /// provide go to definition
 use crate::{
-    consts::TREESITTER_CMAKE_LANGUAGE,
     scansubs::TREE_MAP,
     utils::{
-        CACHE_CMAKE_PACKAGES_WITHKEYS, gen_module_pattern, get_the_packagename, include_is_module,
+        find_cmake_package, gen_module_pattern, get_the_packagename, include_is_module,
         replace_placeholders,
         treehelper::{ToPoint, ToPosition},
     },
 };
+mod cache_option;
 mod findpackage;
 mod include;
+mod occurrence;
 mod subdirectory;
-use tree_sitter::Node;
+mod target;
+use tree_sitter::Point;
 
-use crate::utils::treehelper::{CurrentNodeInfo, PositionType, location_range_contain};
-
-use crate::utils::query::{
-    get_functions, get_line_comments, get_macros, get_normal_commands, get_variables,
-};
+use self::occurrence::{Occurrence, project_locations};
+pub use self::target::target_occurrences;
+use crate::utils::treehelper::{CurrentNodeInfo, PositionType, location_in_range};
 
 /// Storage the information when jump
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -52,13 +53,9 @@ struct CacheDataUnit {
     is_function: bool,
 }
 
-pub async fn update_cache<P: AsRef<Path>>(path: P, context: &str) -> Option<()> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(context, None)?;
+pub async fn update_cache<P: AsRef<Path>>(path: P, document: &Document) -> Option<()> {
     let result_data = getsubdef(
-        tree.root_node(),
-        context,
+        document.analysis(),
         path.as_ref(),
         PositionType::VarOrFun,
         &mut Vec::new(),
@@ -93,14 +90,14 @@ pub async fn update_cache<P: AsRef<Path>>(path: P, context: &str) -> Option<()>
 
 #[derive(Debug, Clone)]
 pub struct ReferenceInfo {
-    is_function: bool,
-    loc: Location,
+    pub is_function: bool,
+    pub loc: Location,
 }
 
 pub async fn get_cached_def<P: AsRef<Path>>(
     path: P,
     key: &str,
-    documents: &DashMap<Uri, String>,
+    documents: &DashMap<Uri, Document>,
 ) -> Option<ReferenceInfo> {
     let mut path = path.as_ref().to_path_buf();
 
@@ -121,8 +118,8 @@ pub async fn get_cached_def<P: AsRef<Path>>(
         });
     }
     drop(jump_cache);
-    if let Ok(context) = get_or_update_buffer_contents(&path, documents).await {
-        update_cache(&path, context.as_str()).await;
+    if let Ok(document) = get_or_update_document(&path, documents).await {
+        update_cache(&path, &document).await;
         let jump_cache = JUMP_CACHE.lock().await;
         if let Some(JumpCacheUnit {
             location,
@@ -151,8 +148,8 @@ pub async fn get_cached_def<P: AsRef<Path>>(
             });
         }
         drop(jump_cache);
-        if let Ok(context) = get_or_update_buffer_contents(&path, documents).await {
-            update_cache(&path, context.as_str()).await;
+        if let Ok(document) = get_or_update_document(&path, documents).await {
+            update_cache(&path, &document).await;
             let jump_cache = JUMP_CACHE.lock().await;
             if let Some(JumpCacheUnit {
                 location,
@@ -172,20 +169,103 @@ pub async fn get_cached_def<P: AsRef<Path>>(
     None
 }
 
+/// The symbols which are shared by all the files of the project
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectSymbolKind {
+    Target,
+    CacheOption,
+}
+
+impl ProjectSymbolKind {
+    fn occurrences(self) -> fn(&Document) -> Vec<Occurrence> {
+        match self {
+            Self::Target => target::target_occurrences,
+            Self::CacheOption => cache_option::option_occurrences,
+        }
+    }
+}
+
+/// A target or a cache option under the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSymbol {
+    pub kind: ProjectSymbolKind,
+    pub name: String,
+    pub range: Range,
+    pub locations: Vec<Location>,
+}
+
+/// Find the symbol under the cursor and where it is defined in the project, with its uses when
+/// `with_uses` is true
+pub async fn project_symbol(
+    document: &Document,
+    originuri: &Path,
+    position: Position,
+    with_uses: bool,
+    kinds: &[ProjectSymbolKind],
+    documents: &DashMap<Uri, Document>,
+) -> Option<ProjectSymbol> {
+    for &kind in kinds {
+        let occurrences = kind.occurrences();
+        let Some(occurrence) = occurrences(document)
+            .into_iter()
+            .find(|occurrence| occurrence.contains(position))
+        else {
+            continue;
+        };
+        if let Some(locations) = project_locations(
+            &occurrence.name,
+            originuri,
+            with_uses,
+            documents,
+            occurrences,
+        )
+        .await
+        {
+            return Some(ProjectSymbol {
+                kind,
+                name: occurrence.name,
+                range: occurrence.range,
+                locations,
+            });
+        }
+    }
+    None
+}
+
+/// Whether all the definitions among the locations of a target are ALIAS or IMPORTED targets,
+/// whose names can have `::`
+pub async fn target_allows_namespace(
+    locations: &[Location],
+    documents: &DashMap<Uri, Document>,
+) -> bool {
+    for location in locations {
+        let Ok(path) = location.uri.to_file_path() else {
+            continue;
+        };
+        let Ok(document) = get_or_update_document(&path, documents).await else {
+            continue;
+        };
+        if target::definition_allows_namespace(&document, location.range) == Some(false) {
+            return false;
+        }
+    }
+    true
+}
+
 /// find the definition
 pub async fn godef<P: AsRef<Path>>(
     location: Position,
-    source: &str,
+    document: &Document,
     originuri: P,
     client: &tower_lsp::Client,
     is_jump: bool,
     just_var_or_fun: bool,
-    documents: &DashMap<Uri, String>,
+    documents: &DashMap<Uri, Document>,
 ) -> Option<Vec<Location>> {
     let current_point = location.to_point();
     let locations = godef_inner(
         current_point,
-        source,
+        document,
         originuri,
         is_jump,
         just_var_or_fun,
@@ -202,17 +282,36 @@ pub async fn godef<P: AsRef<Path>>(
 
 async fn godef_inner<P: AsRef<Path>>(
     location: tree_sitter::Point,
-    source: &str,
+    document: &Document,
     originuri: P,
     is_jump: bool,
     just_var_or_fun: bool,
-    documents: &DashMap<Uri, String>,
+    documents: &DashMap<Uri, Document>,
 ) -> Option<Vec<Location>> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(source, None)?;
+    // NOTE: the targets of the project are found in all the scanned files, before the packages
+    // and the variables with the same name. The definitions of the cache options are already
+    // found as variables, but their uses are searched in all the files too
+    let kinds: &[ProjectSymbolKind] = if is_jump {
+        &[ProjectSymbolKind::Target]
+    } else {
+        &[ProjectSymbolKind::Target, ProjectSymbolKind::CacheOption]
+    };
+    if !just_var_or_fun
+        && let Some(symbol) = project_symbol(
+            document,
+            originuri.as_ref(),
+            location.to_position(),
+            !is_jump,
+            kinds,
+            documents,
+        )
+        .await
+    {
+        return Some(symbol.locations);
+    }
 
-    let current_node_info = CurrentNodeInfo::get(source, tree.root_node(), location);
+    let current_node_info =
+        CurrentNodeInfo::get(document.text(), document.tree().root_node(), location);
     let jumptype = current_node_info.pos_type();
     let tofind = current_node_info.content()?;
 
@@ -239,8 +338,7 @@ async fn godef_inner<P: AsRef<Path>>(
                     return None;
                 }
                 let jumps = query_reference(
-                    tree.root_node(),
-                    source.as_bytes(),
+                    document.analysis(),
                     location,
                     tofind,
                     originuri,
@@ -255,7 +353,7 @@ async fn godef_inner<P: AsRef<Path>>(
 
             let loc = jump_cache.uri.to_file_path().ok()?;
             locations.push(jump_cache.clone());
-            let mut defdata = reference_all(&loc, tofind, is_function).await;
+            let mut defdata = reference_all(&loc, tofind, is_function, documents).await;
             locations.append(&mut defdata);
             // NOTE: ensure there is not same location, or it will cause problems
             locations.dedup();
@@ -275,17 +373,23 @@ async fn godef_inner<P: AsRef<Path>>(
         PositionType::FindPkgConfig => None,
         PositionType::Target => None,
         PositionType::Include => {
-            let fixed_url = replace_placeholders(tofind)?;
+            let fixed_url = replace_placeholders(tofind, originuri.as_ref())?;
             include::cmpinclude(originuri, &fixed_url)
         }
         PositionType::SubDir => {
-            let fixed_url = replace_placeholders(tofind)?;
+            let fixed_url = replace_placeholders(tofind, originuri.as_ref())?;
             subdirectory::cmpsubdirectory(originuri, &fixed_url)
         }
     }
 }
 
-async fn reference_all<P: AsRef<Path>>(path: P, tofind: &str, is_function: bool) -> Vec<Location> {
+/// Find the name in the file and in the files which are related to it by the scan
+pub async fn reference_all<P: AsRef<Path>>(
+    path: P,
+    tofind: &str,
+    is_function: bool,
+    documents: &DashMap<Uri, Document>,
+) -> Vec<Location> {
     let mut results = vec![];
     let from = path.as_ref();
     let mut paths: Vec<PathBuf> = if from
@@ -321,24 +425,12 @@ async fn reference_all<P: AsRef<Path>>(path: P, tofind: &str, is_function: bool)
     paths.push(from.to_path_buf());
 
     for rp in paths {
-        let Ok(source) = tokio::fs::read_to_string(&rp).await else {
-            continue;
-        };
-        let mut parse = tree_sitter::Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let Some(tree) = parse.parse(&source, None) else {
+        let Ok(document) = read_document(&rp, documents).await else {
             continue;
         };
-        let newsource = source.as_bytes();
-        if let Some(mut locs) = query_reference(
-            tree.root_node(),
-            newsource,
-            None,
-            tofind,
-            rp,
-            is_function,
-            false,
-        ) {
+        if let Some(mut locs) =
+            query_reference(document.analysis(), None, tofind, rp, is_function, false)
+        {
             results.append(&mut locs);
         }
     }
@@ -347,8 +439,7 @@ async fn reference_all<P: AsRef<Path>>(path: P, tofind: &str, is_function: bool)
 
 /// sub get the def
 fn query_reference<P: AsRef<Path>, L: Into<Option<tree_sitter::Point>>>(
-    root: Node,
-    source: &[u8],
+    analysis: &Analysis,
     location: L,
     tofind: &str,
     originuri: P,
@@ -357,97 +448,42 @@ fn query_reference<P: AsRef<Path>, L: Into<Option<tree_sitter::Point>>>(
 ) -> Option<Vec<Location>> {
     let location = location.into();
     let mut definitions: Vec<Location> = vec![];
-    let funcs = get_functions(source, root, None);
-    let macros = get_macros(source, root, None);
+    let to_location = |range: tree_sitter::Range| Location {
+        uri: Uri::from_file_path(originuri.as_ref()).unwrap(),
+        range: Range {
+            start: range.start_point.to_position(),
+            end: range.end_point.to_position(),
+        },
+    };
     if is_function && !jump {
-        let commands = get_normal_commands(source, root, None);
-        for fun in funcs {
-            let fun_name = fun.name;
-            if fun_name != tofind {
-                continue;
-            }
-            let fun_node = fun.arguments[0];
-            definitions.push(Location {
-                uri: Uri::from_file_path(originuri.as_ref()).unwrap(),
-                range: Range {
-                    start: fun_node.start_position().to_position(),
-                    end: fun_node.end_position().to_position(),
-                },
-            });
-        }
-        for macro_node in macros {
-            let macro_name = macro_node.name;
-            if macro_name != tofind {
+        for fun in analysis.functions.iter().chain(analysis.macros.iter()) {
+            if fun.name != tofind {
                 continue;
             }
-            let macro_node = macro_node.arguments[0];
-            definitions.push(Location {
-                uri: Uri::from_file_path(originuri.as_ref()).unwrap(),
-                range: Range {
-                    start: macro_node.start_position().to_position(),
-                    end: macro_node.end_position().to_position(),
-                },
-            });
+            definitions.push(to_location(fun.name_range));
         }
-        for cmd in commands {
-            let cmd_name = cmd.identifier;
-            if cmd_name != tofind {
+        // NOTE: the commands are case insensitive
+        for cmd in &analysis.commands {
+            if !cmd.identifier.eq_ignore_ascii_case(tofind) {
                 continue;
             }
-            let cmd_node = cmd.identifier_node;
-            definitions.push(Location {
-                uri: Uri::from_file_path(originuri.as_ref()).unwrap(),
-                range: Range {
-                    start: cmd_node.start_position().to_position(),
-                    end: cmd_node.end_position().to_position(),
-                },
-            });
+            definitions.push(to_location(cmd.identifier_range));
         }
     } else {
-        if let Some(location) = location
-            && let Some(f_v) = funcs
-                .iter()
-                .find(|n| location_range_contain(location, n.node))
-            && let Some(arg) = f_v.args(source).iter().find(|arg| arg.content == tofind)
-        {
-            let var_node = arg.node;
-            definitions.push(Location {
-                uri: Uri::from_file_path(originuri.as_ref()).unwrap(),
-                range: Range {
-                    start: var_node.start_position().to_position(),
-                    end: var_node.end_position().to_position(),
-                },
-            });
-        }
-        if let Some(location) = location
-            && let Some(m_v) = macros
-                .iter()
-                .find(|n| location_range_contain(location, n.node))
-            && let Some(arg) = m_v.args(source).iter().find(|arg| arg.content == tofind)
-        {
-            let var_node = arg.node;
-            definitions.push(Location {
-                uri: Uri::from_file_path(originuri.as_ref()).unwrap(),
-                range: Range {
-                    start: var_node.start_position().to_position(),
-                    end: var_node.end_position().to_position(),
-                },
-            });
+        for defs in [&analysis.functions, &analysis.macros] {
+            if let Some(location) = location
+                && let Some(f_v) = defs.iter().find(|n| location_in_range(location, n.range))
+                && let Some(arg) = f_v.args.iter().find(|arg| arg.content == tofind)
+            {
+                definitions.push(to_location(arg.range));
+            }
         }
         if !jump {
-            let vars = get_variables(source, root, None);
-            for var in vars {
+            for var in &analysis.variables {
                 if var.content != tofind {
                     continue;
                 }
-                let var_node = var.node;
-                definitions.push(Location {
-                    uri: Uri::from_file_path(originuri.as_ref()).unwrap(),
-                    range: Range {
-                        start: var_node.start_position().to_position(),
-                        end: var_node.end_position().to_position(),
-                    },
-                });
+                definitions.push(to_location(var.range));
             }
         }
     }
@@ -461,8 +497,7 @@ fn query_reference<P: AsRef<Path>, L: Into<Option<tree_sitter::Point>>>(
 /// use position to make only can complete which has show before
 #[allow(clippy::too_many_arguments)]
 fn getsubdef<P: AsRef<Path>>(
-    input: tree_sitter::Node,
-    source: &str,
+    analysis: &Analysis,
     local_path: P,
     postype: PositionType,
     include_files: &mut Vec<PathBuf>,
@@ -473,84 +508,41 @@ fn getsubdef<P: AsRef<Path>>(
     let local_path = local_path.as_ref();
     let mut defs: Vec<CacheDataUnit> = vec![];
 
-    let source_bytes = source.as_bytes();
-
-    // NOTE: prepare
-    let comments = get_line_comments(source_bytes, input, None);
-
-    let macros = get_macros(source_bytes, input, None);
-    let functions = get_functions(source_bytes, input, None);
-    let normal_commands = get_normal_commands(source_bytes, input, None);
+    // NOTE: check functions and macros
+    for (defs_of_kind, kind) in [
+        (&analysis.functions, "function"),
+        (&analysis.macros, "macro"),
+    ] {
+        for fun in defs_of_kind {
+            let row = fun.name_range.start_point.row;
+            let column = fun.range.start_point.column;
 
-    // NOTE: check functions
-    for fun in functions {
-        let name = fun.name;
-        let row = fun.arguments[0].start_position().row;
-        let column = fun.node.start_position().column;
+            let start = fun.name_range.start_point.to_position();
+            let end = fun.name_range.end_point.to_position();
 
-        let fun_node = fun.arguments[0];
-        let start = fun_node.start_position().to_position();
-        let end = fun_node.end_position().to_position();
-
-        let mut document_info = format!("defined function\nfrom: {}", local_path.display());
-        if let Some(line_comment) = comments
-            .iter()
-            .find(|c| {
-                c.node.start_position().row + 1 == row && c.node.start_position().column == column
-            })
-            .map(|c| c.content)
-        {
-            document_info = format!("{}\n\n{}", document_info, line_comment);
-        }
-        defs.push(CacheDataUnit {
-            key: name.to_string(),
-            location: Location {
-                uri: Uri::from_file_path(local_path).unwrap(),
-                range: Range { start, end },
-            },
-            document_info,
-            is_function: true,
-        });
-    }
-
-    // NOTE: check macros
-    for macro_node in macros {
-        let name = macro_node.name;
-        let row = macro_node.arguments[0].start_position().row;
-        let column = macro_node.node.start_position().column;
-
-        let fun_node = macro_node.arguments[0];
-        let start = fun_node.start_position().to_position();
-        let end = fun_node.end_position().to_position();
-
-        let mut document_info = format!("defined macro\nfrom: {}", local_path.display());
-        if let Some(line_comment) = comments
-            .iter()
-            .find(|c| {
-                c.node.start_position().row + 1 == row && c.node.start_position().column == column
-            })
-            .map(|c| c.content)
-        {
-            document_info = format!("{}\n\n{}", document_info, line_comment);
+            let mut document_info = format!("defined {kind}\nfrom: {}", local_path.display());
+            if let Some(line_comment) = analysis.comment_above(Point { row, column }) {
+                document_info = format!("{}\n\n{}", document_info, line_comment);
+            }
+            defs.push(CacheDataUnit {
+                key: fun.name.clone(),
+                location: Location {
+                    uri: Uri::from_file_path(local_path).unwrap(),
+                    range: Range { start, end },
+                },
+                document_info,
+                is_function: true,
+            });
         }
-        defs.push(CacheDataUnit {
-            key: name.to_string(),
-            location: Location {
-                uri: Uri::from_file_path(local_path).unwrap(),
-                range: Range { start, end },
-            },
-            document_info,
-            is_function: true,
-        });
     }
     // NOTE: check normal_commands
-    for command in normal_commands {
+    for command in &analysis.commands {
         let name = command.identifier.to_lowercase();
         if name == "include" && should_in {
-            let Some(first_arg) = command.first_arg else {
+            let Some(first_arg) = command.first_arg() else {
                 continue;
             };
-            let Some(file_name) = first_arg.try_replace_placeholders() else {
+            let Some(file_name) = first_arg.try_replace_placeholders(local_path) else {
                 continue;
             };
             let (is_builtin, subpath) = {
@@ -591,7 +583,7 @@ fn getsubdef<P: AsRef<Path>>(
             }
         }
         if name == "find_package" && should_in {
-            let Some(package_name) = command.first_arg else {
+            let Some(package_name) = command.first_arg() else {
                 continue;
             };
             let argument_count = command.args.len();
@@ -601,9 +593,8 @@ fn getsubdef<P: AsRef<Path>>(
                 if argument_count >= 2 {
                     let mut support_component = false;
                     let mut components_packages = Vec::new();
-                    for index in 1..argument_count {
-                        let package_prefix_node = command.args[index];
-                        let component = package_prefix_node.utf8_text(source_bytes).unwrap();
+                    for arg in &command.args[1..] {
+                        let component = arg.content.as_str();
                         if component == "COMPONENTS" {
                             support_component = true;
                         } else if component != "REQUIRED" {
@@ -643,25 +634,18 @@ fn getsubdef<P: AsRef<Path>>(
                 defs.append(&mut completedefs);
             }
         } else if name == "set" || name == "option" {
-            let Some(name) = command.first_arg else {
+            let Some(name) = command.first_arg() else {
                 continue;
             };
-            let row = command.identifier_node.start_position().row;
+            let row = command.identifier_range.start_point.row;
             let mut document_info = format!("defined variable\nfrom: {}", local_path.display());
 
-            let column = command.node.start_position().column;
-            let val_name = command.args[0];
-            let h = val_name.start_position().row;
-            let x = val_name.start_position().column;
-            let y = val_name.end_position().column;
-            if let Some(line_comment) = comments
-                .iter()
-                .find(|c| {
-                    c.node.start_position().row + 1 == row
-                        && c.node.start_position().column == column
-                })
-                .map(|c| c.content)
-            {
+            let column = command.range.start_point.column;
+            let val_name = command.args[0].range;
+            let h = val_name.start_point.row;
+            let x = val_name.start_point.column;
+            let y = val_name.end_point.column;
+            if let Some(line_comment) = analysis.comment_above(Point { row, column }) {
                 document_info = format!("{}\n\n{}", document_info, line_comment);
             }
             defs.push(CacheDataUnit {
@@ -693,7 +677,7 @@ fn get_cmake_package_defs(
     include_files: &mut Vec<PathBuf>,
     complete_packages: &mut Vec<String>,
 ) -> Option<Vec<CacheDataUnit>> {
-    let packageinfo = CACHE_CMAKE_PACKAGES_WITHKEYS.get(package_name)?;
+    let packageinfo = find_cmake_package(package_name)?;
     let mut complete_infos = Vec::new();
 
     for path in packageinfo.tojump.iter() {
@@ -732,9 +716,10 @@ mod tests {
         let subdir_file = subdir.join("CMakeLists.txt");
         File::create_new(&subdir_file).unwrap();
 
+        let document = Document::new(jump_file_src);
         let locations = godef_inner(
             Point { row: 0, column: 20 },
-            jump_file_src,
+            &document,
             &top_cmake,
             true,
             false,
@@ -780,9 +765,10 @@ add_subdirectory(abcd_test)
         let subdir_file = subdir.join("CMakeLists.txt");
         File::create_new(&subdir_file).unwrap();
 
+        let document = Document::new(jump_file_src);
         let locations = godef_inner(
             Point { row: 2, column: 18 },
-            jump_file_src,
+            &document,
             &top_cmake,
             true,
             false,
@@ -809,7 +795,7 @@ add_subdirectory(abcd_test)
         );
         let locations_2 = godef_inner(
             Point { row: 4, column: 13 },
-            jump_file_src,
+            &document,
             &top_cmake,
             true,
             false,
@@ -860,14 +846,11 @@ set(EFGH "abcd")
         let include_cmake_path_2 = dir.path().join("efg_test.cmake");
         File::create(&include_cmake_path_2).unwrap();
 
-        let mut parse = tree_sitter::Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let thetree = parse.parse(top_cmake_context, None).unwrap();
+        let document = Document::new(top_cmake_context);
 
         let mut include_files = vec![];
         let data = getsubdef(
-            thetree.root_node(),
-            top_cmake_context,
+            document.analysis(),
             &top_cmake_path,
             PositionType::VarOrFun,
             &mut include_files,
@@ -929,4 +912,55 @@ set(EFGH "abcd")
             vec![include_cmake_path_2, include_cmake_path]
         );
     }
+
+    #[tokio::test]
+    async fn test_project_symbol() {
+        let source = "add_library(mylib a.cpp)\ntarget_link_libraries(app mylib)\n";
+        let dir = tempdir().unwrap();
+        let top_cmake = dir.path().join("CMakeLists.txt");
+        fs::write(&top_cmake, source).unwrap();
+        let document = Document::new(source);
+        let documents = DashMap::new();
+        let kinds = [ProjectSymbolKind::Target, ProjectSymbolKind::CacheOption];
+
+        let symbol = project_symbol(
+            &document,
+            &top_cmake,
+            Position::new(1, 28),
+            false,
+            &kinds,
+            &documents,
+        )
+        .await
+        .unwrap();
+        assert_eq!(symbol.kind, ProjectSymbolKind::Target);
+        assert_eq!(symbol.name, "mylib");
+        assert_eq!(symbol.locations.len(), 1);
+        assert_eq!(symbol.locations[0].range.start, Position::new(0, 12));
+
+        let symbol = project_symbol(
+            &document,
+            &top_cmake,
+            Position::new(0, 13),
+            true,
+            &kinds,
+            &documents,
+        )
+        .await
+        .unwrap();
+        assert_eq!(symbol.locations.len(), 2);
+
+        assert!(
+            project_symbol(
+                &document,
+                &top_cmake,
+                Position::new(1, 23),
+                true,
+                &kinds,
+                &documents,
+            )
+            .await
+            .is_none()
+        );
+    }
 }
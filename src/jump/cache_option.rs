@@ -0,0 +1,79 @@
+use super::occurrence::{Collector, Occurrence, conditions};
+use crate::document::Document;
+
+/// The cache options defined and used in the document, like `option(WITH_TESTS "" ON)` and
+/// `set(ROOT_DIR "" CACHE PATH "")`
+pub fn option_occurrences(document: &Document) -> Vec<Occurrence> {
+    let analysis = document.analysis();
+    let mut collector = Collector::new(document.text());
+    for command in &analysis.commands {
+        let Some(first) = command.args.first() else {
+            continue;
+        };
+        match command.identifier.to_lowercase().as_str() {
+            "option" | "cmake_dependent_option" => collector.push_argument(first, true),
+            "set" => {
+                let is_cache = command.args.iter().any(|arg| arg.content == "CACHE");
+                collector.push_argument(first, is_cache);
+            }
+            "unset" => collector.push_argument(first, false),
+            "mark_as_advanced" => {
+                for arg in command
+                    .args
+                    .iter()
+                    .filter(|arg| !matches!(arg.content.as_str(), "FORCE" | "CLEAR"))
+                {
+                    collector.push_argument(arg, false);
+                }
+            }
+            _ => {}
+        }
+    }
+    for variable in &analysis.variables {
+        collector.push_range(&variable.content, variable.range.start_byte, false);
+    }
+    // NOTE: `if(WITH_TESTS)` reads the variable without `${}`
+    for args in conditions(document) {
+        for arg in args.iter().filter(|arg| !arg.content.starts_with('"')) {
+            collector.push_argument(arg, false);
+        }
+    }
+    collector.occurrences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_occurrences() {
+        let source = r#"option(WITH_TESTS "Build the tests" ON)
+set(ROOT_DIR "/opt" CACHE PATH "The root")
+if(WITH_TESTS AND NOT "WITH_TESTS")
+  message("${ROOT_DIR}")
+endif()
+"#;
+        let document = Document::new(source);
+        let occurrences: Vec<(String, u32, u32, bool)> = option_occurrences(&document)
+            .into_iter()
+            .filter(|occurrence| occurrence.name != "AND" && occurrence.name != "NOT")
+            .map(|occurrence| {
+                (
+                    occurrence.name,
+                    occurrence.range.start.line,
+                    occurrence.range.start.character,
+                    occurrence.is_definition,
+                )
+            })
+            .collect();
+        assert_eq!(
+            occurrences,
+            vec![
+                ("WITH_TESTS".to_string(), 0, 7, true),
+                ("ROOT_DIR".to_string(), 1, 4, true),
+                ("ROOT_DIR".to_string(), 3, 13, false),
+                ("WITH_TESTS".to_string(), 2, 3, false),
+            ]
+        );
+    }
+}
@@ -2,10 +2,10 @@ use lsp_types::Uri;
 use tower_lsp::lsp_types;
 
 use super::Location;
-use crate::utils::CACHE_CMAKE_PACKAGES_WITHKEYS;
+use crate::utils::find_cmake_package;
 
 pub(super) fn cmpfindpackage(input: &str) -> Option<Vec<Location>> {
-    CACHE_CMAKE_PACKAGES_WITHKEYS.get(input).map(|context| {
+    find_cmake_package(input).map(|context| {
         context
             .tojump
             .iter()
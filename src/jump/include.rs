@@ -7,7 +7,7 @@ use lsp_types::Uri;
 use tower_lsp::lsp_types;
 
 use super::{CacheDataUnit, Location, gen_module_pattern, getsubdef};
-use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::document::Document;
 use crate::utils::include_is_module;
 use crate::utils::treehelper::PositionType;
 
@@ -63,12 +63,9 @@ pub fn scanner_include_defs(
         return Some(complete_items.clone());
     }
     let content = fs::read_to_string(path).ok()?;
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(&content, None)?;
+    let document = Document::new(content);
     let result_data = getsubdef(
-        thetree.root_node(),
-        &content,
+        document.analysis(),
         path,
         postype,
         include_files,
@@ -99,12 +96,9 @@ pub fn scanner_package_defs(
         return Some(complete_items.clone());
     }
     let content = fs::read_to_string(path).ok()?;
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(&content, None)?;
+    let document = Document::new(content);
     let result_data = getsubdef(
-        thetree.root_node(),
-        &content,
+        document.analysis(),
         path,
         postype,
         include_files,
@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{Location, Position, Range, Uri};
+use tree_sitter::{Query, QueryCursor, StreamingIterator};
+
+use crate::CMakeNodeKinds;
+use crate::consts::TREESITTER_CMAKE_LANGUAGE;
+use crate::document::{ArgumentIndex, Document, byte_to_point};
+use crate::languageserver::get_or_update_document;
+use crate::scansubs::scanned_files;
+use crate::utils::treehelper::ToPosition;
+
+const CONDITION_QUERY: &str = r"
+[
+    (if_command (argument_list) @condition)
+    (elseif_command (argument_list) @condition)
+    (while_command (argument_list) @condition)
+]
+";
+
+/// A name written in a document, where it is defined or used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub name: String,
+    pub range: Range,
+    pub is_definition: bool,
+}
+
+impl Occurrence {
+    pub fn contains(&self, position: Position) -> bool {
+        self.range.start <= position && position <= self.range.end
+    }
+}
+
+/// Collect the occurrences with their ranges in the document
+pub struct Collector<'a> {
+    text: &'a str,
+    pub occurrences: Vec<Occurrence>,
+}
+
+impl<'a> Collector<'a> {
+    pub const fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            occurrences: Vec::new(),
+        }
+    }
+
+    pub fn push_range(&mut self, name: &str, start_byte: usize, is_definition: bool) {
+        if name.is_empty() || name.contains("${") || name.contains("$<") {
+            return;
+        }
+        self.occurrences.push(Occurrence {
+            name: name.to_string(),
+            range: Range {
+                start: byte_to_point(self.text, start_byte).to_position(),
+                end: byte_to_point(self.text, start_byte + name.len()).to_position(),
+            },
+            is_definition,
+        });
+    }
+
+    /// The whole argument is the name, the quotes excluded
+    pub fn push_argument(&mut self, arg: &ArgumentIndex, is_definition: bool) {
+        let quoted = arg.content.starts_with('"');
+        let name = arg.content.trim_matches('"');
+        let start = arg.range.start_byte + usize::from(quoted);
+        self.push_range(name, start, is_definition);
+    }
+}
+
+/// The arguments of the `if()`, `elseif()` and `while()` commands
+pub fn conditions(document: &Document) -> Vec<Vec<ArgumentIndex>> {
+    let source = document.text().as_bytes();
+    let query = Query::new(&TREESITTER_CMAKE_LANGUAGE, CONDITION_QUERY).unwrap();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, document.tree().root_node(), source);
+    let mut conditions = vec![];
+    while let Some(m) = matches.next() {
+        let Some(argument_list) = m.nodes_for_capture_index(0).next() else {
+            continue;
+        };
+        let mut walk = argument_list.walk();
+        let args = argument_list
+            .children(&mut walk)
+            .filter(|node| node.kind() == CMakeNodeKinds::ARGUMENT)
+            .map(|node| ArgumentIndex {
+                content: node.utf8_text(source).unwrap().to_string(),
+                range: node.range(),
+            })
+            .collect();
+        conditions.push(args);
+    }
+    conditions
+}
+
+/// Find the name in the scanned files and in the current file, with its uses when `with_uses` is
+/// true. Nothing is returned if the project does not define the name
+pub async fn project_locations(
+    name: &str,
+    originuri: &Path,
+    with_uses: bool,
+    documents: &DashMap<Uri, Document>,
+    occurrences: fn(&Document) -> Vec<Occurrence>,
+) -> Option<Vec<Location>> {
+    let mut files = scanned_files().await;
+    if !files.iter().any(|file| file == originuri) {
+        files.push(originuri.to_path_buf());
+    }
+    let mut defined = false;
+    let mut locations = vec![];
+    for file in files {
+        let Ok(document) = get_or_update_document(&file, documents).await else {
+            continue;
+        };
+        let Ok(uri) = Uri::from_file_path(&file) else {
+            continue;
+        };
+        for occurrence in occurrences(&document) {
+            if occurrence.name != name {
+                continue;
+            }
+            defined |= occurrence.is_definition;
+            if with_uses || occurrence.is_definition {
+                locations.push(Location {
+                    uri: uri.clone(),
+                    range: occurrence.range,
+                });
+            }
+        }
+    }
+    defined.then_some(locations)
+}
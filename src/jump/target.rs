@@ -0,0 +1,234 @@
+use std::sync::LazyLock;
+
+use tower_lsp::lsp_types::Range;
+
+use super::occurrence::{Collector, Occurrence, conditions};
+use crate::document::{ArgumentIndex, Document};
+use crate::utils::query::ToLspRange;
+use crate::utils::{LINK_KEYWORDS, TARGET_DEFINITION_COMMANDS};
+
+/// The generator expressions which take a target, like `$<TARGET_FILE:app>`
+static TARGET_GENEX_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"\$<TARGET_[A-Z_]+:(?P<target>[^,>$<]+)").unwrap());
+
+/// The keywords which end the targets of `install(TARGETS ...)`
+const INSTALL_KEYWORDS: &[&str] = &[
+    "EXPORT",
+    "RUNTIME_DEPENDENCIES",
+    "RUNTIME_DEPENDENCY_SET",
+    "ARCHIVE",
+    "LIBRARY",
+    "RUNTIME",
+    "OBJECTS",
+    "FRAMEWORK",
+    "BUNDLE",
+    "PRIVATE_HEADER",
+    "PUBLIC_HEADER",
+    "RESOURCE",
+    "FILE_SET",
+    "CXX_MODULES_BMI",
+    "DESTINATION",
+    "INCLUDES",
+    "PERMISSIONS",
+    "CONFIGURATIONS",
+    "COMPONENT",
+    "OPTIONAL",
+    "EXCLUDE_FROM_ALL",
+];
+
+/// The arguments after the keyword, until the next keyword
+fn keyword_targets<'a>(args: &'a [ArgumentIndex], keyword: &str) -> Vec<&'a ArgumentIndex> {
+    args.iter()
+        .skip_while(|arg| arg.content != keyword)
+        .skip(1)
+        .take_while(|arg| {
+            let content = arg.content.as_str();
+            !INSTALL_KEYWORDS.contains(&content)
+                && !matches!(
+                    content,
+                    "PROPERTY" | "APPEND" | "APPEND_STRING" | "NAMESPACE" | "FILE"
+                )
+        })
+        .collect()
+}
+
+fn push_genexes(collector: &mut Collector, arg: &ArgumentIndex) {
+    for captures in TARGET_GENEX_REGEX.captures_iter(&arg.content) {
+        let target = captures.name("target").unwrap();
+        collector.push_range(
+            target.as_str(),
+            arg.range.start_byte + target.start(),
+            false,
+        );
+    }
+}
+
+fn push_command(collector: &mut Collector, identifier: &str, args: &[ArgumentIndex]) {
+    let contents: Vec<&str> = args.iter().map(|arg| arg.content.as_str()).collect();
+    let uses: Vec<&ArgumentIndex> = match identifier {
+        id if TARGET_DEFINITION_COMMANDS.contains(&id) => {
+            if let Some(first) = args.first() {
+                collector.push_argument(first, true);
+            }
+            // NOTE: add_library(alias ALIAS target)
+            match contents.as_slice() {
+                [_, "ALIAS", _] => vec![&args[2]],
+                _ => vec![],
+            }
+        }
+        "target_link_libraries" => args
+            .iter()
+            .filter(|arg| !LINK_KEYWORDS.contains(&arg.content.as_str()))
+            .collect(),
+        id if id.starts_with("target_") => args.first().into_iter().collect(),
+        "add_dependencies" => args.iter().collect(),
+        "set_target_properties" => args
+            .iter()
+            .take_while(|arg| arg.content != "PROPERTIES")
+            .collect(),
+        "get_target_property" => args.get(1).into_iter().collect(),
+        "set_property" => keyword_targets(args, "TARGET"),
+        "install" | "export" => keyword_targets(args, "TARGETS"),
+        "add_custom_command" => keyword_targets(args, "TARGET")
+            .into_iter()
+            .take(1)
+            .collect(),
+        _ => vec![],
+    };
+    for arg in uses {
+        collector.push_argument(arg, false);
+    }
+    for arg in args {
+        push_genexes(collector, arg);
+    }
+}
+
+/// The targets created and used in the document, `if(TARGET app)` included
+pub fn target_occurrences(document: &Document) -> Vec<Occurrence> {
+    let mut collector = Collector::new(document.text());
+    for command in &document.analysis().commands {
+        push_command(
+            &mut collector,
+            &command.identifier.to_lowercase(),
+            &command.args,
+        );
+    }
+    for args in conditions(document) {
+        for pair in args.windows(2) {
+            if pair[0].content == "TARGET" {
+                collector.push_argument(&pair[1], false);
+            }
+        }
+        for arg in &args {
+            push_genexes(&mut collector, arg);
+        }
+    }
+    collector.occurrences
+}
+
+/// Whether the target created at the range is an ALIAS or an IMPORTED target, the only targets
+/// whose names can have `::`. [`None`] if no target is created there
+pub fn definition_allows_namespace(document: &Document, range: Range) -> Option<bool> {
+    let command = document.analysis().commands.iter().find(|command| {
+        TARGET_DEFINITION_COMMANDS.contains(&command.identifier.to_lowercase().as_str())
+            && command.args.first().is_some_and(|arg| {
+                let arg_range = arg.range.lsp_range();
+                arg_range.start <= range.start && range.end <= arg_range.end
+            })
+    })?;
+    Some(
+        command
+            .args
+            .iter()
+            .any(|arg| arg.content == "ALIAS" || arg.content == "IMPORTED"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_occurrences() {
+        let source = r#"add_library(mylib STATIC a.cpp)
+add_library(my::lib ALIAS mylib)
+add_executable(app main.cpp)
+target_link_libraries(app PRIVATE mylib "other")
+add_dependencies(app mylib)
+set_target_properties(app mylib PROPERTIES OUTPUT_NAME app2)
+add_custom_target(copy COMMAND cp $<TARGET_FILE:mylib> $<TARGET_PROPERTY:app,NAME>)
+if(TARGET mylib)
+endif()
+"#;
+        let document = Document::new(source);
+        let occurrences: Vec<(String, u32, u32, bool)> = target_occurrences(&document)
+            .into_iter()
+            .map(|occurrence| {
+                (
+                    occurrence.name,
+                    occurrence.range.start.line,
+                    occurrence.range.start.character,
+                    occurrence.is_definition,
+                )
+            })
+            .collect();
+        let expected = [
+            ("mylib", 0, 12, true),
+            ("my::lib", 1, 12, true),
+            ("mylib", 1, 26, false),
+            ("app", 2, 15, true),
+            ("app", 3, 22, false),
+            ("mylib", 3, 34, false),
+            ("other", 3, 41, false),
+            ("app", 4, 17, false),
+            ("mylib", 4, 21, false),
+            ("app", 5, 22, false),
+            ("mylib", 5, 26, false),
+            ("copy", 6, 18, true),
+            ("mylib", 6, 48, false),
+            ("app", 6, 73, false),
+            ("mylib", 7, 10, false),
+        ];
+        assert_eq!(
+            occurrences,
+            expected
+                .iter()
+                .map(|(name, line, character, is_definition)| (
+                    name.to_string(),
+                    *line,
+                    *character,
+                    *is_definition
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_definition_allows_namespace() {
+        let document = Document::new(
+            r"add_library(mylib a.cpp)
+add_library(my::lib ALIAS mylib)
+add_library(ext::lib SHARED IMPORTED)
+",
+        );
+        let occurrences = target_occurrences(&document);
+        let allows: Vec<(String, Option<bool>)> = occurrences
+            .iter()
+            .map(|occurrence| {
+                (
+                    occurrence.name.clone(),
+                    definition_allows_namespace(&document, occurrence.range),
+                )
+            })
+            .collect();
+        assert_eq!(
+            allows,
+            vec![
+                ("mylib".to_string(), Some(false)),
+                ("my::lib".to_string(), Some(true)),
+                ("mylib".to_string(), None),
+                ("ext::lib".to_string(), Some(true)),
+            ]
+        );
+    }
+}
@@ -1,7 +1,10 @@
 mod cache;
 mod config;
+mod diagnostic;
+mod settings;
 #[cfg(test)]
 mod test;
+mod workspace;
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -11,42 +14,62 @@ use std::sync::atomic::{AtomicBool, Ordering};
 
 use dashmap::DashMap;
 use tower_lsp::jsonrpc::{Error as LspError, Result};
-use tower_lsp::lsp_extensions::TextDocumentContentChangeEventEx;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{LanguageServer, lsp_types};
-use tree_sitter::Parser;
 
-use self::config::Config;
+pub use self::config::Config;
+use self::diagnostic::presets_diagnostic_report;
+pub use self::workspace::ProjectRoot;
 use super::Backend;
-use crate::config::CONFIG;
-use crate::consts::TREESITTER_CMAKE_LANGUAGE;
-use crate::fileapi::DEFAULT_QUERY;
+use crate::document::Document;
 use crate::fileapi::target::{TARGET_REGEX, Target};
+use crate::fileapi::{FileCompileInfo, FileTarget};
 use crate::formatting::getformat;
 use crate::grammar::{ErrorType, LintConfigInfo, checkerror};
+use crate::presets::{PRESETS_FILE, USER_PRESETS_FILE};
 use crate::scansubs::cache_project_data;
-use crate::semantic_token::LEGEND_TYPE;
 use crate::signature_help::get_signature_help;
-use crate::utils::{VCPKG_LIBS, VCPKG_PREFIX, did_vcpkg_project, treehelper};
+use crate::utils::treehelper;
 use crate::{
-    BackendInitInfo, complete, document_link, document_symbol, fileapi, filewatcher, hover, jump,
-    quick_fix, scansubs, semantic_token, utils,
+    BackendInitInfo, call_hierarchy, code_lens, complete, document_highlight, document_link,
+    document_symbol, file_operations, fileapi, filewatcher, folding_range, hover, inlay_hint, jump,
+    linked_editing, presets, quick_fix, scansubs, selection_range, semantic_token, utils,
+    workspace_symbol,
 };
 
 static CLIENT_CAPABILITIES: RwLock<Option<TextDocumentClientCapabilities>> = RwLock::new(None);
 static ENABLE_SNIPPET: AtomicBool = AtomicBool::new(false);
 
-pub async fn get_or_update_buffer_contents<P: AsRef<Path>>(
+pub async fn get_or_update_document<P: AsRef<Path>>(
     path: P,
-    documents: &DashMap<Uri, String>,
-) -> std::io::Result<String> {
+    documents: &DashMap<Uri, Document>,
+) -> std::io::Result<Document> {
     let uri = Uri::from_file_path(&path).unwrap();
-    if let Some(text) = documents.get(&uri) {
-        return Ok(text.to_string());
+    if let Some(document) = documents.get(&uri) {
+        return Ok(document.clone());
     }
     let text = tokio::fs::read_to_string(&path).await?;
-    documents.insert(uri, text.clone());
-    Ok(text)
+    let document = Document::new(text);
+    documents.insert(uri, document.clone());
+    Ok(document)
+}
+
+/// Get the document synced by the client, or read it without caching it, for the requests which
+/// look at many files which are not opened
+///
+/// NOTE: the documents without a version were read from the disk, they can be stale
+pub async fn read_document<P: AsRef<Path>>(
+    path: P,
+    documents: &DashMap<Uri, Document>,
+) -> std::io::Result<Document> {
+    let uri = Uri::from_file_path(&path).unwrap();
+    if let Some(document) = documents.get(&uri)
+        && document.version().is_some()
+    {
+        return Ok(document.clone());
+    }
+    let text = tokio::fs::read_to_string(&path).await?;
+    Ok(Document::new(text))
 }
 
 fn set_client_text_document(text_document: Option<TextDocumentClientCapabilities>) {
@@ -78,10 +101,6 @@ pub fn to_use_snippet() -> bool {
 }
 
 impl Backend {
-    fn root_path(&self) -> Option<&PathBuf> {
-        self.root_path.get_or_init(|| None).as_ref()
-    }
-
     fn init_info(&self) -> &BackendInitInfo {
         self.init_info
             .get()
@@ -89,19 +108,19 @@ impl Backend {
     }
 
     fn path_in_project<P: AsRef<Path>>(&self, path: P) -> bool {
-        let Some(root_path) = self.root_path() else {
+        if self.roots.is_empty() {
             return true;
-        };
-
-        let Some(diff) = pathdiff::diff_paths(path, root_path) else {
-            return false;
-        };
+        }
         use std::path::Component;
-        diff.components()
-            .all(|component| component != Component::ParentDir)
+        self.roots.iter().any(|root| {
+            pathdiff::diff_paths(path.as_ref(), root.key()).is_some_and(|diff| {
+                diff.components()
+                    .all(|component| component != Component::ParentDir)
+            })
+        })
     }
 
-    async fn publish_diagnostics(&self, uri: Uri, context: &str, lint_info: LintConfigInfo) {
+    async fn publish_diagnostics(&self, uri: Uri, document: &Document, lint_info: LintConfigInfo) {
         let Ok(file_path) = uri.to_file_path() else {
             tracing::error!("Cannot transport {uri:?} to file_path");
             self.client
@@ -117,24 +136,31 @@ impl Backend {
             return;
         }
 
-        if let Some(pusheddiagnoses) = checkerror(&file_path, context, lint_info) {
+        if let Some(pusheddiagnoses) = checkerror(
+            &file_path,
+            document.text(),
+            document.tree().root_node(),
+            lint_info,
+        ) {
             self.client
-                .publish_diagnostics(uri, pusheddiagnoses, None)
+                .publish_diagnostics(uri, pusheddiagnoses, document.version())
                 .await;
         } else {
-            self.client.publish_diagnostics(uri, vec![], None).await;
+            self.client
+                .publish_diagnostics(uri, vec![], document.version())
+                .await;
         }
     }
 
     async fn update_diagnostics(&self) {
         for item in &self.documents {
             let uri = item.key();
-            let text = item.value();
+            let document = item.value();
             self.publish_diagnostics(
                 uri.clone(),
-                text,
+                document,
                 LintConfigInfo {
-                    use_lint: self.init_info().enable_lint,
+                    use_lint: self.settings().is_lint_enabled(),
                     use_extra_cmake_lint: true,
                 },
             )
@@ -146,9 +172,11 @@ impl Backend {
 impl Backend {
     async fn did_change_inner(&self, params: DidChangeTextDocumentParams) -> Option<()> {
         let uri = params.text_document.text_document_identifier.uri;
-        let content_change = params.content_changes.into_iter().next()?;
-        let text = content_change.whole_content()?;
-        self.update_cache(uri.clone(), text);
+        self.update_cache_with_changes(
+            uri.clone(),
+            params.text_document.version,
+            &params.content_changes,
+        );
         self.client
             .log_message(MessageType::Info, &format!("update file: {}", uri.as_str()))
             .await;
@@ -165,85 +193,48 @@ impl LanguageServer for Backend {
 
         init_snippet_setting(initial_config.use_snippets());
 
-        let do_format = initial_config.is_format_enabled();
-
-        let scan_cmake_in_package = initial_config.is_scan_cmake_in_package();
-
-        let enable_lint = initial_config.is_lint_enabled();
+        let text_document = initial.capabilities.text_document.as_ref();
+        let workspace = initial.capabilities.workspace.as_ref();
+        let dynamic_formatting = text_document
+            .and_then(|text_document| text_document.formatting.as_ref())
+            .and_then(|formatting| formatting.dynamic_registration)
+            .unwrap_or(false);
+        let dynamic_semantic_tokens = text_document
+            .and_then(|text_document| text_document.semantic_tokens.as_ref())
+            .and_then(|semantic_tokens| semantic_tokens.dynamic_registration)
+            .unwrap_or(false);
+
+        // NOTE: the capabilities which can be registered later are only registered in
+        // `initialized`, so that they can be toggled by the settings
+        let do_format = initial_config.is_format_enabled() && !dynamic_formatting;
+        let do_semantic_token = initial_config.enable_semantic_token() && !dynamic_semantic_tokens;
 
         self.init_info
             .set(BackendInitInfo {
-                scan_cmake_in_package,
-                enable_lint,
+                scan_cmake_in_package: initial_config.is_scan_cmake_in_package(),
+                pull_settings: workspace
+                    .and_then(|workspace| workspace.configuration)
+                    .unwrap_or(false),
+                dynamic_formatting,
+                dynamic_semantic_tokens,
+                presets_methods: settings::presets_methods(text_document),
+                refresh_diagnostics: workspace
+                    .and_then(|workspace| workspace.diagnostics.as_ref())
+                    .and_then(|diagnostics| diagnostics.refresh_support)
+                    .unwrap_or(false),
             })
             .expect("here should be the first place to init the init_info");
+        *self.settings.write().unwrap() = initial_config;
 
-        if let Some(workspace) = initial.capabilities.workspace
-            && let Some(watch_file) = workspace.did_change_watched_files
-            && watch_file.dynamic_registration == Some(true)
-            && watch_file.relative_pattern_support == Some(true)
-        {
-            // NOTE: I think it only contains one workspace
-            if let Some(ref top_path) = initial
-                .workspace_folders_initialize_params
-                .workspace_folders
-                .as_ref()
-                .and_then(|folders| match folders {
-                    WorkspaceFolders::Null => None,
-                    WorkspaceFolders::WorkspaceFolderList(list) => list.first(),
-                })
-                .and_then(|folder| folder.uri.to_file_path().ok())
-            {
-                let path = top_path.join("build").join("CMakeCache.txt");
-                if path.exists() {
-                    filewatcher::refresh_error_packages(path);
-                }
-
-                tracing::info!("find cache-v2 json, start reading the data");
-                let cache_path = top_path
-                    .join("build")
-                    .join(".cmake")
-                    .join("api")
-                    .join("v1")
-                    .join("reply");
-                if cache_path.is_dir() {
-                    use std::fs;
-                    if let Ok(entries) = fs::read_dir(cache_path) {
-                        for entry in entries.flatten() {
-                            let file_path = entry.path();
-                            if file_path.is_file() {
-                                let Some(file_name) = file_path.file_name() else {
-                                    continue;
-                                };
-                                let file_name = file_name.to_string_lossy().to_string();
-                                if file_name.starts_with("cache-v2") && file_name.ends_with(".json")
-                                {
-                                    fileapi::update_cache_data(&file_path);
-                                }
-                                if TARGET_REGEX.is_match(&file_name) {
-                                    fileapi::update_target_data(file_path);
-                                }
-                            }
-                        }
-                    }
-                }
-                tracing::info!("Finish getting the data in cache-v2 json");
-            }
-        }
-
-        if let Some(ref project_root) = initial
+        if let Some(WorkspaceFolders::WorkspaceFolderList(folders)) = &initial
             .workspace_folders_initialize_params
             .workspace_folders
-            .as_ref()
-            .and_then(|folders| match folders {
-                WorkspaceFolders::Null => None,
-                WorkspaceFolders::WorkspaceFolderList(list) => list.first(),
-            })
-            .and_then(|folder| folder.uri.to_file_path().ok())
         {
-            self.root_path
-                .set(Some(project_root.to_path_buf()))
-                .expect("here should be the only place to set the root_path");
+            for folder in folders {
+                if let Ok(path) = folder.uri.to_file_path() {
+                    self.add_root(path.to_path_buf()).await;
+                }
+            }
         }
 
         set_client_text_document(initial.capabilities.text_document);
@@ -256,11 +247,15 @@ impl LanguageServer for Backend {
                 version: Some(version),
             }),
             capabilities: ServerCapabilities {
-                rename_provider: Some(RenameProvider::Bool(true)),
+                inlay_hint_provider: Some(InlayHintProvider::Bool(true)),
+                rename_provider: Some(RenameProvider::RenameOptions(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                })),
                 code_action_provider: Some(CodeActionProvider::Bool(true)),
                 text_document_sync: Some(TextDocumentSync::Options(TextDocumentSyncOptions {
                     open_close: Some(true),
-                    change: Some(TextDocumentSyncKind::Full),
+                    change: Some(TextDocumentSyncKind::Incremental),
                     will_save: Some(false),
                     will_save_wait_until: Some(false),
                     save: Some(Save::Bool(true)),
@@ -278,6 +273,25 @@ impl LanguageServer for Backend {
                     work_done_progress_options: Default::default(),
                 }),
                 document_symbol_provider: Some(DocumentSymbolProvider::Bool(true)),
+                folding_range_provider: Some(FoldingRangeProvider::Bool(true)),
+                selection_range_provider: Some(SelectionRangeProvider::Bool(true)),
+                call_hierarchy_provider: Some(CallHierarchyProvider::Bool(true)),
+                workspace_symbol_provider: Some(WorkspaceSymbolProvider::Bool(true)),
+                document_highlight_provider: Some(DocumentHighlightProvider::Bool(true)),
+                linked_editing_range_provider: Some(LinkedEditingRangeProvider::Bool(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: Default::default(),
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        code_lens::BUILD_COMMAND.to_string(),
+                        code_lens::RUN_COMMAND.to_string(),
+                        code_lens::TEST_COMMAND.to_string(),
+                        code_lens::REFERENCES_COMMAND.to_string(),
+                    ],
+                    work_done_progress_options: Default::default(),
+                }),
                 definition_provider: Some(DefinitionProvider::Bool(true)),
                 document_formatting_provider: if do_format { Some(true.into()) } else { None },
                 hover_provider: Some(true.into()),
@@ -286,38 +300,12 @@ impl LanguageServer for Backend {
                         supported: Some(true),
                         change_notifications: Some(true.into()),
                     }),
-                    None,
+                    Some(file_operations::file_operation_options()),
                     None,
                 )),
-                semantic_tokens_provider: if initial_config.enable_semantic_token() {
+                semantic_tokens_provider: if do_semantic_token {
                     Some(SemanticTokensProvider::SemanticTokensRegistrationOptions(
-                        SemanticTokensRegistrationOptions {
-                            text_document_registration_options: {
-                                TextDocumentRegistrationOptions {
-                                    document_selector: Some(vec![
-                                        TextDocumentFilter::Language(TextDocumentFilterLanguage {
-                                            language: "cmake".to_string(),
-                                            scheme: Some("file".to_string()),
-                                            pattern: None,
-                                        })
-                                        .into(),
-                                    ]),
-                                }
-                            },
-                            semantic_tokens_options: SemanticTokensOptions {
-                                work_done_progress_options: WorkDoneProgressOptions::default(),
-                                legend: SemanticTokensLegend {
-                                    token_types: LEGEND_TYPE
-                                        .iter()
-                                        .map(|tp| tp.to_string())
-                                        .collect(),
-                                    token_modifiers: vec![],
-                                },
-                                range: None,
-                                full: Some(true.into()),
-                            },
-                            static_registration_options: StaticRegistrationOptions::default(),
-                        },
+                        settings::semantic_tokens_registration_options(),
                     ))
                 } else {
                     None
@@ -327,7 +315,7 @@ impl LanguageServer for Backend {
                     DiagnosticOptions {
                         identifier: Some("neocmakelsp".to_owned()),
                         inter_file_dependencies: true,
-                        workspace_diagnostics: false,
+                        workspace_diagnostics: true,
                         work_done_progress_options: WorkDoneProgressOptions {
                             work_done_progress: None,
                         },
@@ -363,6 +351,14 @@ impl LanguageServer for Backend {
                             | lsp_types::WatchKind::Change,
                     ),
                 },
+                FileSystemWatcher {
+                    glob_pattern: GlobPattern::Pattern("**/CMake*Presets.json".to_string()),
+                    kind: Some(
+                        lsp_types::WatchKind::Create
+                            | lsp_types::WatchKind::Delete
+                            | lsp_types::WatchKind::Change,
+                    ),
+                },
                 FileSystemWatcher {
                     glob_pattern: GlobPattern::Pattern("**/CMakeLists.txt".to_string()),
                     kind: Some(lsp_types::WatchKind::Create | lsp_types::WatchKind::Delete),
@@ -381,6 +377,11 @@ impl LanguageServer for Backend {
             .await
             .unwrap();
 
+        self.register_dynamic_capabilities().await;
+        if let Some(settings) = self.fetch_settings(LspAny::Null).await {
+            self.apply_settings(settings).await;
+        }
+
         self.client
             .log_message(MessageType::Info, "initialized!")
             .await;
@@ -394,52 +395,11 @@ impl LanguageServer for Backend {
             .begin()
             .await;
 
-        if let Some(ref project_root) = self.root_path() {
+        for project_root in self.root_paths() {
             progress
                 .report_with_message(&format!("start scanning {}", project_root.display()), 10)
                 .await;
-            scansubs::scan_all(&project_root, true).await;
-            let build_dir = project_root.join("build");
-            if build_dir.is_dir()
-                && let Some(query) = &*DEFAULT_QUERY
-            {
-                query.write_to_build_dir(build_dir.as_path()).ok();
-            }
-            if did_vcpkg_project(project_root) {
-                progress
-                    .report_with_message("find vcpkg dir, start scanning", 20)
-                    .await;
-                tracing::info!("This project is vcpkg project, start init vcpkg data");
-                let vcpkg_installed_path = project_root.join("vcpkg_installed");
-
-                #[cfg(unix)]
-                {
-                    use crate::utils::packagepkgconfig::QUERYSRULES;
-                    // When it is found to be a vcpkg project, the pc will be searched first from the vcpkg download directory.
-                    QUERYSRULES.lock().unwrap().insert(
-                        0,
-                        Box::leak(
-                            format!("{}/*.pc", vcpkg_installed_path.to_str().unwrap())
-                                .into_boxed_str(),
-                        ),
-                    );
-                }
-
-                // add vcpkg prefix
-                VCPKG_PREFIX.lock().unwrap().push(Box::leak(
-                    vcpkg_installed_path
-                        .to_str()
-                        .unwrap()
-                        .to_string()
-                        .into_boxed_str(),
-                ));
-
-                let paths = utils::make_vcpkg_package_search_path(&vcpkg_installed_path);
-                let mut vcpkg_libs = VCPKG_LIBS.lock().unwrap();
-                for t in paths {
-                    vcpkg_libs.push(Box::leak(t.into_boxed_str()));
-                }
-            }
+            self.scan_root(&project_root).await;
         }
 
         progress
@@ -462,30 +422,52 @@ impl LanguageServer for Backend {
         progress.report_with_message("Scan finished", 100).await;
 
         progress.finish().await;
+
+        for project_root in self.root_paths() {
+            self.choose_build_dir(&project_root).await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
         // NOTE: cache data when receive shutdown signal
-        if let Some(project_root) = self.root_path() {
+        for project_root in self.root_paths() {
             cache_project_data(project_root).await;
         }
         exit(0)
     }
 
-    async fn did_change_workspace_folders(&self, _: DidChangeWorkspaceFoldersParams) {
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        for folder in params.event.removed {
+            if let Ok(path) = folder.uri.to_file_path() {
+                self.remove_root(&path).await;
+            }
+        }
+        for folder in params.event.added {
+            if let Ok(path) = folder.uri.to_file_path() {
+                let path = path.to_path_buf();
+                self.add_root(path.clone()).await;
+                self.scan_root(&path).await;
+                self.choose_build_dir(&path).await;
+            }
+        }
+        self.update_diagnostics().await;
         self.client
             .log_message(MessageType::Info, "workspace folders changed!")
             .await;
     }
 
-    async fn did_change_configuration(&self, _: DidChangeConfigurationParams) {
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
         self.client
             .log_message(MessageType::Info, "configuration changed!")
             .await;
+        if let Some(settings) = self.fetch_settings(params.settings).await {
+            self.apply_settings(settings).await;
+        }
     }
 
     async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
         let mut has_cached_changed = false;
+        let mut presets_roots: Vec<PathBuf> = vec![];
         for change in params.changes {
             let Ok(file_path) = change.uri.to_file_path() else {
                 continue;
@@ -498,50 +480,125 @@ impl LanguageServer for Backend {
                 continue;
             };
 
-            if file_name.ends_with("json") && file_name.starts_with("cache-v2") {
-                fileapi::update_cache_data(&file_path);
+            if file_name == PRESETS_FILE || file_name == USER_PRESETS_FILE {
+                has_cached_changed = true;
+                if let Some(root) = self.root_of(&file_path)
+                    && !presets_roots.contains(&root)
+                {
+                    presets_roots.push(root);
+                }
             }
-            if TARGET_REGEX.is_match(&file_name) {
-                fileapi::update_target_data(&file_path);
+            if file_name.ends_with("json")
+                && file_name.starts_with("cache-v2")
+                && let Some(root) = self.root_of_build_file(&file_path)
+            {
+                fileapi::update_cache_data(root, &file_path);
+            }
+            if TARGET_REGEX.is_match(&file_name)
+                && let Some(root) = self.root_of_build_file(&file_path)
+            {
+                fileapi::update_target_data(root, &file_path);
             }
             if file_name.ends_with("txt") {
                 has_cached_changed = true;
                 if file_name == "CMakeLists.txt" {
-                    let Some(path) = self.root_path() else {
+                    let Some(root) = self.root_of(&file_path) else {
                         continue;
                     };
-                    scansubs::scan_all(path, false).await;
+                    scansubs::scan_all(root, false).await;
                     continue;
                 }
                 self.client
                     .log_message(MessageType::Info, "CMakeCache changed")
                     .await;
+                let Some(root) = self.root_of_build_file(&file_path) else {
+                    continue;
+                };
                 if change.kind == FileChangeType::Deleted {
-                    filewatcher::clear_error_packages();
+                    filewatcher::clear_error_packages(root);
                 } else {
-                    filewatcher::refresh_error_packages(file_path);
+                    filewatcher::refresh_error_packages(root, file_path);
                 }
             }
         }
+        if !presets_roots.is_empty() {
+            self.reload_build_dirs(presets_roots).await;
+        }
         if has_cached_changed {
             self.update_diagnostics().await;
         }
+        // NOTE: the lints look at the other files, the build directory and the CMakeCache.txt
+        self.refresh_pull_diagnostics().await;
         self.client
             .log_message(MessageType::Info, "watched files have changed!")
             .await;
     }
 
+    async fn will_rename_files(&self, params: RenameFilesParams) -> Result<Option<WorkspaceEdit>> {
+        let renames: Vec<(PathBuf, PathBuf)> = params
+            .files
+            .into_iter()
+            .filter_map(|file| {
+                let old_path = Uri::parse(&file.old_uri).ok()?.to_file_path().ok()?;
+                let new_path = Uri::parse(&file.new_uri).ok()?.to_file_path().ok()?;
+                Some((old_path, new_path))
+            })
+            .collect();
+        Ok(file_operations::will_rename_files(&renames, &self.documents).await)
+    }
+
+    async fn did_create_files(&self, params: CreateFilesParams) {
+        for file in params.files {
+            let Some(path) = Uri::parse(&file.uri)
+                .ok()
+                .and_then(|uri| uri.to_file_path().ok())
+            else {
+                continue;
+            };
+            if !file_operations::is_source_file(&path) {
+                continue;
+            }
+            let Some(cmake) = file_operations::owning_cmake(&path).await else {
+                continue;
+            };
+            let Ok(document) = get_or_update_document(&cmake, &self.documents).await else {
+                continue;
+            };
+            if file_operations::source_insertions(&cmake, &document, &path).is_empty() {
+                continue;
+            }
+            self.client
+                .show_message(
+                    MessageType::Info,
+                    format!(
+                        "{} can be added to a target with the code actions of {}",
+                        path.display(),
+                        cmake.display()
+                    ),
+                )
+                .await;
+            self.created_sources.insert(path, cmake);
+        }
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let TextDocumentItem { uri, text, .. } = params.text_document;
-        self.update_cache(uri.clone(), &text);
+        let TextDocumentItem {
+            uri, text, version, ..
+        } = params.text_document;
+        let document = Document::new(text).with_version(version);
+        if cache::is_presets_uri(&uri) {
+            self.presets_documents.insert(uri, document);
+            return;
+        }
+        self.documents.insert(uri.clone(), document.clone());
 
         let Ok(path) = uri.to_file_path() else {
             tracing::error!("Can't create path from {}", uri.as_str());
             return;
         };
 
-        complete::update_cache(&path, &text).await;
-        jump::update_cache(&path, &text).await;
+        complete::update_cache(&path, &document).await;
+        jump::update_cache(&path, &document).await;
 
         self.client
             .log_message(MessageType::Info, format!("Opened file {}", path.display()))
@@ -553,7 +610,7 @@ impl LanguageServer for Backend {
         params: CodeActionParams,
     ) -> Result<Option<Vec<CodeActionResponse>>> {
         let uri = params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
@@ -568,7 +625,26 @@ impl LanguageServer for Backend {
             })
             .collect();
 
-        Ok(quick_fix::lint_fix_action(&text, &actions, uri))
+        let mut actions = quick_fix::lint_fix_action(
+            document.text(),
+            document.tree().root_node(),
+            &actions,
+            uri.clone(),
+        )
+        .unwrap_or_default();
+        if let Ok(path) = uri.to_file_path() {
+            self.created_sources.retain(|source, _| source.exists());
+            let sources: Vec<PathBuf> = self
+                .created_sources
+                .iter()
+                .filter(|entry| *entry.value() == path)
+                .map(|entry| entry.key().clone())
+                .collect();
+            actions.extend(file_operations::add_source_actions(
+                &uri, &path, &document, &sources,
+            ));
+        }
+        Ok((!actions.is_empty()).then_some(actions))
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -577,7 +653,7 @@ impl LanguageServer for Backend {
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
         let uri = params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return;
         };
 
@@ -586,11 +662,11 @@ impl LanguageServer for Backend {
             return;
         };
 
-        let has_root = self.root_path().is_some();
+        let has_root = self.root_of(&path).is_some();
         if has_root {
             scansubs::scan_dir(&path, false).await;
-            complete::update_cache(&path, &text).await;
-            jump::update_cache(&path, &text).await;
+            complete::update_cache(&path, &document).await;
+            jump::update_cache(&path, &document).await;
         }
         self.client
             .log_message(MessageType::Info, "file saved!")
@@ -599,17 +675,24 @@ impl LanguageServer for Backend {
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         let uri = params.text_document_position_params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
-        let position = params.text_document_position_params.position;
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(None);
+        };
 
-        let mut parse = Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let tree = parse.parse(&text, None).unwrap();
+        let position = params.text_document_position_params.position;
 
-        let Some(value) = hover::get_hovered_doc(position, tree.root_node(), &text).await else {
+        let Some(value) = hover::get_hovered_doc(
+            position,
+            document.tree().root_node(),
+            document.text(),
+            &path,
+        )
+        .await
+        else {
             return Ok(None);
         };
         Ok(Some(Hover {
@@ -625,10 +708,14 @@ impl LanguageServer for Backend {
     }
 
     async fn formatting(&self, input: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        if !self.settings().is_format_enabled() {
+            return Ok(None);
+        }
         let uri = input.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
+        let root_path = uri.to_file_path().ok().and_then(|path| self.root_of(&path));
 
         self.client
             .log_message(
@@ -643,8 +730,9 @@ impl LanguageServer for Backend {
         };
         let insert_final_newline = input.options.insert_final_newline.unwrap_or(false);
         Ok(getformat(
-            self.root_path().map(|p| p.as_path()),
-            &text,
+            root_path.as_deref(),
+            document.text(),
+            document.tree().root_node(),
             &self.client,
             space_line,
             input.options.insert_spaces,
@@ -654,6 +742,8 @@ impl LanguageServer for Backend {
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.presets_documents.remove(&params.text_document.uri);
+        self.semantic_tokens.remove(&params.text_document.uri);
         self.client
             .log_message(
                 MessageType::Info,
@@ -666,11 +756,17 @@ impl LanguageServer for Backend {
         self.client.log_message(MessageType::Info, "Complete").await;
 
         let uri = input.text_document_position_params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let location = input.text_document_position_params.position;
+        if let Some(document) = self.get_presets_document(&uri)
+            && let Ok(path) = uri.to_file_path()
+        {
+            return Ok(presets::getcomplete(&path, document.text(), location)
+                .map(CompletionResponse::CompletionItemList));
+        }
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
-        let location = input.text_document_position_params.position;
         let Ok(path) = uri.to_file_path() else {
             tracing::error!("Cannot get path from {}", uri.as_str());
             return Err(LspError::internal_error());
@@ -679,7 +775,7 @@ impl LanguageServer for Backend {
         let triggered = input.context.and_then(|context| context.trigger_character);
 
         Ok(complete::getcomplete(
-            &text,
+            &document,
             triggered,
             location,
             &self.client,
@@ -692,7 +788,7 @@ impl LanguageServer for Backend {
 
     async fn references(&self, input: ReferenceParams) -> Result<Option<Vec<Location>>> {
         let uri = input.text_document_position_params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
@@ -704,7 +800,7 @@ impl LanguageServer for Backend {
 
         Ok(jump::godef(
             location,
-            &text,
+            &document,
             &path,
             &self.client,
             false,
@@ -714,9 +810,118 @@ impl LanguageServer for Backend {
         .await)
     }
 
+    async fn prepare_call_hierarchy(
+        &self,
+        input: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let uri = input.text_document_position_params.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+
+        let location = input.text_document_position_params.position;
+        let Ok(path) = uri.to_file_path() else {
+            tracing::error!("Cannot get path from {uri:?}");
+            return Err(LspError::internal_error());
+        };
+
+        Ok(
+            call_hierarchy::prepare_call_hierarchy(&document, location, &path, &self.documents)
+                .await,
+        )
+    }
+
+    async fn incoming_calls(
+        &self,
+        input: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        Ok(call_hierarchy::incoming_calls(&input.item, &self.documents).await)
+    }
+
+    async fn outgoing_calls(
+        &self,
+        input: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        Ok(call_hierarchy::outgoing_calls(&input.item, &self.documents).await)
+    }
+
+    async fn document_highlight(
+        &self,
+        input: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let uri = input.text_document_position_params.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+
+        let location = input.text_document_position_params.position;
+        Ok(document_highlight::get_highlights(&document, location))
+    }
+
+    async fn linked_editing_range(
+        &self,
+        input: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        let uri = input.text_document_position_params.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+
+        let location = input.text_document_position_params.position;
+        Ok(linked_editing::get_linked_editing_ranges(
+            &document, location,
+        ))
+    }
+
+    async fn code_lens(&self, input: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let uri = input.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+
+        let Ok(path) = uri.to_file_path() else {
+            tracing::error!("Cannot get path from {uri:?}");
+            return Err(LspError::internal_error());
+        };
+
+        Ok(code_lens::get_code_lenses(&document, path, &self.documents).await)
+    }
+
+    async fn execute_command(&self, input: ExecuteCommandParams) -> Result<Option<LspAny>> {
+        let root = self.command_root(input.arguments.as_deref().unwrap_or_default());
+        let build = root.as_deref().and_then(|root| self.build_of(root));
+        code_lens::execute_command(
+            &self.client,
+            &input.command,
+            input.arguments.as_deref().unwrap_or_default(),
+            root.as_deref().zip(build.as_ref()),
+        )
+        .await
+    }
+
+    async fn symbol(
+        &self,
+        input: WorkspaceSymbolParams,
+    ) -> Result<Option<WorkspaceSymbolResponse>> {
+        Ok(workspace_symbol::workspace_symbols(&input.query, &self.documents).await)
+    }
+
+    async fn inlay_hint(&self, input: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = input.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+        let Ok(path) = uri.to_file_path() else {
+            tracing::error!("Cannot get path from {uri:?}");
+            return Err(LspError::internal_error());
+        };
+
+        Ok(inlay_hint::get_inlay_hints(&document, input.range, path, &self.documents).await)
+    }
+
     async fn rename(&self, input: RenameParams) -> Result<Option<WorkspaceEdit>> {
         let uri = input.text_document_position_params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
@@ -727,23 +932,44 @@ impl LanguageServer for Backend {
             return Err(LspError::internal_error());
         };
 
-        Ok(self
-            .rename_symbol(&edited, location, path, &self.client, &text)
-            .await)
+        self.rename_symbol(&edited, location, path, &self.client, &document)
+            .await
+    }
+
+    async fn prepare_rename(
+        &self,
+        input: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResult>> {
+        let uri = input.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+        let Ok(path) = uri.to_file_path() else {
+            tracing::error!("Cannot get path from {uri:?}");
+            return Err(LspError::internal_error());
+        };
+
+        self.prepare_rename_symbol(input.position, path, &document)
+            .await
     }
 
     async fn goto_definition(&self, input: DefinitionParams) -> Result<Option<DefinitionResponse>> {
         let uri = input.text_document_position_params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        if let Some(document) = self.get_presets_document(&uri)
+            && let Ok(path) = uri.to_file_path()
+        {
+            let position = input.text_document_position_params.position;
+            return Ok(presets::godef(&path, document.text(), position)
+                .map(|locations| Definition::LocationList(locations).into()));
+        }
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
         let location = input.text_document_position_params.position;
 
-        let mut parse = Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let tree = parse.parse(&text, None).unwrap();
-        let origin_selection_range = treehelper::get_position_range(location, tree.root_node());
+        let root = document.tree().root_node();
+        let origin_selection_range = treehelper::get_position_range(location, root);
 
         let Ok(path) = uri.to_file_path() else {
             tracing::error!("Cannot get path from {uri:?}");
@@ -752,7 +978,7 @@ impl LanguageServer for Backend {
 
         let Some(range) = jump::godef(
             location,
-            &text,
+            &document,
             &path,
             &self.client,
             true,
@@ -780,17 +1006,17 @@ impl LanguageServer for Backend {
 
     async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
         let uri = params.text_document_position_params.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
         let position = params.text_document_position_params.position;
 
-        let mut parse = Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let tree = parse.parse(&text, None).unwrap();
-
-        Ok(get_signature_help(position, tree.root_node(), &text))
+        Ok(get_signature_help(
+            position,
+            document.tree().root_node(),
+            document.text(),
+        ))
     }
 
     async fn document_symbol(
@@ -798,72 +1024,128 @@ impl LanguageServer for Backend {
         input: DocumentSymbolParams,
     ) -> Result<Option<DocumentSymbolResponse>> {
         let uri = input.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
-        Ok(document_symbol::get_symbol(&self.client, &text).await)
+        Ok(
+            document_symbol::get_symbol(&self.client, document.tree().root_node(), document.text())
+                .await,
+        )
     }
 
     async fn diagnostic(
         &self,
         params: DocumentDiagnosticParams,
     ) -> Result<DocumentDiagnosticReport> {
-        let empty = DocumentDiagnosticReport::RelatedFullDocumentDiagnosticReport(
-            RelatedFullDocumentDiagnosticReport {
-                related_documents: None,
-                full_document_diagnostic_report: FullDocumentDiagnosticReport {
-                    result_id: None,
-                    items: vec![],
-                },
-            },
-        );
-
         let uri = params.text_document.uri;
-        let (Some(text), Some(path)) = (self.get_cached_buffer(&uri), uri.to_file_path().ok())
+        if let Some(document) = self.get_presets_document(&uri)
+            && let Ok(path) = uri.to_file_path()
+        {
+            return Ok(presets_diagnostic_report(&path, &document));
+        }
+        let (Some(document), Some(path)) =
+            (self.get_cached_document(&uri), uri.to_file_path().ok())
         else {
-            return Ok(empty);
+            return Ok(
+                DocumentDiagnosticReport::RelatedFullDocumentDiagnosticReport(
+                    RelatedFullDocumentDiagnosticReport {
+                        related_documents: None,
+                        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                            result_id: None,
+                            items: vec![],
+                        },
+                    },
+                ),
+            );
         };
 
-        let Some(pusheddiagnoses) = checkerror(
-            &path,
-            &text,
-            LintConfigInfo {
-                use_lint: self.init_info().enable_lint,
-                use_extra_cmake_lint: CONFIG.enable_external_cmake_lint,
-            },
-        ) else {
-            return Ok(empty);
-        };
+        Ok(self.document_diagnostic_report(&path, &document, params.previous_result_id.as_deref()))
+    }
 
-        Ok(
-            DocumentDiagnosticReport::RelatedFullDocumentDiagnosticReport(
-                RelatedFullDocumentDiagnosticReport {
-                    related_documents: None,
-                    full_document_diagnostic_report: FullDocumentDiagnosticReport {
-                        result_id: None,
-                        items: pusheddiagnoses,
-                    },
-                },
-            ),
-        )
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> Result<WorkspaceDiagnosticReport> {
+        Ok(self
+            .workspace_diagnostic_report(&params.previous_result_ids, params.partial_result_params)
+            .await)
     }
 
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
     ) -> Result<Option<SemanticTokens>> {
+        if !self.settings().enable_semantic_token() {
+            return Ok(None);
+        }
         let uri = params.text_document.uri.clone();
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let (Some(document), Some(path)) =
+            (self.get_cached_document(&uri), uri.to_file_path().ok())
+        else {
             return Ok(None);
         };
 
-        Ok(semantic_token::semantic_token(&self.client, &text).await)
+        let Some(tokens) = semantic_token::semantic_token(&self.client, &document, &path).await
+        else {
+            return Ok(None);
+        };
+        self.semantic_tokens.insert(uri, tokens.clone());
+        Ok(Some(tokens))
+    }
+
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Result<Option<SemanticTokensDeltaResponse>> {
+        if !self.settings().enable_semantic_token() {
+            return Ok(None);
+        }
+        let uri = params.text_document.uri;
+        let (Some(document), Some(path)) =
+            (self.get_cached_document(&uri), uri.to_file_path().ok())
+        else {
+            return Ok(None);
+        };
+
+        let Some(tokens) = semantic_token::semantic_token(&self.client, &document, &path).await
+        else {
+            return Ok(None);
+        };
+        // NOTE: when the client does not have the previous result, send all the tokens
+        let response = match self.semantic_tokens.insert(uri, tokens.clone()) {
+            Some(previous) if previous.result_id.as_ref() == Some(&params.previous_result_id) => {
+                semantic_token::semantic_token_delta(&previous, &tokens).into()
+            }
+            _ => tokens.into(),
+        };
+        Ok(Some(response))
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokens>> {
+        if !self.settings().enable_semantic_token() {
+            return Ok(None);
+        }
+        let uri = params.text_document.uri;
+        let (Some(document), Some(path)) =
+            (self.get_cached_document(&uri), uri.to_file_path().ok())
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(semantic_token::semantic_token_range(
+            &document,
+            &path,
+            params.range,
+        )))
     }
 
     async fn document_link(&self, input: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
         let uri = input.text_document.uri;
-        let Some(text) = self.get_cached_buffer(&uri) else {
+        let Some(document) = self.get_cached_document(&uri) else {
             return Ok(None);
         };
 
@@ -872,12 +1154,62 @@ impl LanguageServer for Backend {
             return Err(LspError::internal_error());
         };
 
-        Ok(document_link::document_link_search(&text, file_path))
+        Ok(document_link::document_link_search(
+            document.text(),
+            document.tree().root_node(),
+            file_path,
+        ))
+    }
+
+    async fn folding_range(&self, input: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let uri = input.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+
+        Ok(folding_range::get_folding_ranges(&document))
+    }
+
+    async fn selection_range(
+        &self,
+        input: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let uri = input.text_document.uri;
+        let Some(document) = self.get_cached_document(&uri) else {
+            return Ok(None);
+        };
+
+        Ok(Some(selection_range::get_selection_ranges(
+            &document,
+            &input.positions,
+        )))
     }
 }
 
 impl Backend {
-    pub async fn cmake_targets(&self) -> Result<Option<HashMap<String, Target>>> {
+    pub async fn cmake_targets(&self) -> Result<Option<HashMap<Uri, HashMap<String, Target>>>> {
         Ok(fileapi::get_all_targets())
     }
+
+    pub async fn targets_for_file(
+        &self,
+        TextDocumentIdentifier { uri }: TextDocumentIdentifier,
+    ) -> Result<Vec<FileTarget>> {
+        let Ok(path) = uri.to_file_path() else {
+            tracing::error!("Cannot get path from {}", uri.as_str());
+            return Err(LspError::internal_error());
+        };
+        Ok(fileapi::get_targets_for_file(&path))
+    }
+
+    pub async fn compile_info(
+        &self,
+        TextDocumentIdentifier { uri }: TextDocumentIdentifier,
+    ) -> Result<Vec<FileCompileInfo>> {
+        let Ok(path) = uri.to_file_path() else {
+            tracing::error!("Cannot get path from {}", uri.as_str());
+            return Err(LspError::internal_error());
+        };
+        Ok(fileapi::get_compile_info_for_file(&path))
+    }
 }
@@ -1,17 +1,55 @@
-use tower_lsp::lsp_types::Uri;
+use tower_lsp::lsp_extensions::TextDocumentContentChangeEventEx;
+use tower_lsp::lsp_types::{TextDocumentContentChangeEvent, Uri};
 
 use crate::Backend;
+use crate::document::Document;
+use crate::presets::is_presets_file;
+
+/// The presets files are kept apart, they are not parsed as cmake
+pub fn is_presets_uri(uri: &Uri) -> bool {
+    uri.to_file_path().is_ok_and(|path| is_presets_file(&path))
+}
 
 impl Backend {
-    /// Get the cloned value for an [`Uri`] key if it exists.
+    /// Get the cloned document, with the parsed tree, for an [`Uri`] key if it exists.
     #[inline]
-    pub fn get_cached_buffer(&self, uri: &Uri) -> Option<String> {
+    pub fn get_cached_document(&self, uri: &Uri) -> Option<Document> {
         self.documents.get(uri).map(|v| v.value().clone())
     }
 
-    /// Update a cache entry.
+    /// Get the cloned presets document for an [`Uri`] key if it is opened.
     #[inline]
-    pub fn update_cache(&self, uri: Uri, text: impl Into<String>) {
-        self.documents.insert(uri, text.into());
+    pub fn get_presets_document(&self, uri: &Uri) -> Option<Document> {
+        self.presets_documents.get(uri).map(|v| v.value().clone())
+    }
+
+    /// Apply the changes of a `textDocument/didChange` notification to a cache entry.
+    pub fn update_cache_with_changes(
+        &self,
+        uri: Uri,
+        version: i32,
+        changes: &[TextDocumentContentChangeEvent],
+    ) {
+        let documents = if is_presets_uri(&uri) {
+            &self.presets_documents
+        } else {
+            &self.documents
+        };
+        if let Some(mut document) = documents.get_mut(&uri) {
+            document.apply_changes(version, changes);
+            return;
+        }
+        // NOTE: the ranges of the changes mean nothing without the opened text, only a change of
+        // the whole text can create the document
+        let Some(full) = changes
+            .iter()
+            .rposition(|change| change.whole_content().is_some())
+        else {
+            tracing::warn!("Ignore the changes of {}, it was not opened", uri.as_str());
+            return;
+        };
+        let mut document = Document::new("");
+        document.apply_changes(version, &changes[full..]);
+        documents.insert(uri, document);
     }
 }
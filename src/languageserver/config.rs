@@ -1,6 +1,8 @@
+use std::path::{Path, PathBuf};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, PartialEq, Eq, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
 pub struct Config {
     #[serde(default)]
     pub format: FormatConfig,
@@ -12,6 +14,12 @@ pub struct Config {
     pub lint: LintConfig,
     #[serde(default)]
     pub use_snippets: bool,
+    /// The build directory, relative to the workspace folders
+    #[serde(default)]
+    pub build_directory: Option<PathBuf>,
+    /// The configure preset of `CMakePresets.json` to use
+    #[serde(default)]
+    pub configure_preset: Option<String>,
 }
 
 const fn scan_cmake_in_package_default() -> bool {
@@ -37,6 +45,14 @@ impl Config {
     pub const fn use_snippets(&self) -> bool {
         self.use_snippets
     }
+
+    pub fn build_directory(&self) -> Option<&Path> {
+        self.build_directory.as_deref()
+    }
+
+    pub fn configure_preset(&self) -> Option<&str> {
+        self.configure_preset.as_deref()
+    }
 }
 
 impl Default for Config {
@@ -47,6 +63,8 @@ impl Default for Config {
             semantic_token: false,
             lint: LintConfig::default(),
             use_snippets: false,
+            build_directory: None,
+            configure_preset: None,
         }
     }
 }
@@ -55,7 +73,7 @@ const fn default_enable() -> bool {
     true
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Eq, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
 pub struct FormatConfig {
     #[serde(default = "default_enable")]
     pub enable: bool,
@@ -67,7 +85,7 @@ impl Default for FormatConfig {
     }
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Eq, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
 pub struct LintConfig {
     #[serde(default = "default_enable")]
     pub enable: bool,
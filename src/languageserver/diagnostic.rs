@@ -0,0 +1,222 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+use tower_lsp::lsp_types::{
+    Diagnostic, DocumentDiagnosticReport, FullDocumentDiagnosticReport, PartialResultParams,
+    PreviousResultId, ProgressNotification, ProgressParams, RelatedFullDocumentDiagnosticReport,
+    RelatedUnchangedDocumentDiagnosticReport, UnchangedDocumentDiagnosticReport, Uri,
+    WorkspaceDiagnosticReport, WorkspaceDiagnosticReportPartialResult,
+    WorkspaceDocumentDiagnosticReport, WorkspaceFullDocumentDiagnosticReport,
+    WorkspaceUnchangedDocumentDiagnosticReport,
+};
+
+use super::read_document;
+use crate::Backend;
+use crate::config;
+use crate::document::Document;
+use crate::grammar::{LintConfigInfo, checkerror};
+use crate::presets::check_presets_file;
+use crate::scansubs::scanned_files;
+
+/// The result id changes with the document, with the lint settings and with the generation of the
+/// files which the lints look at
+fn diagnostic_result_id(document: &Document, lint_info: LintConfigInfo, generation: u64) -> String {
+    let mut hasher = DefaultHasher::new();
+    document.version().hash(&mut hasher);
+    document.text().hash(&mut hasher);
+    lint_info.hash(&mut hasher);
+    generation.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+/// The diagnostics of a document, [`None`] if they did not change since the previous result
+struct DiagnosticResult {
+    result_id: String,
+    items: Option<Vec<Diagnostic>>,
+}
+
+/// The diagnostics of a presets file
+///
+/// NOTE: they depend on the included files, so they are always sent in full
+pub(super) fn presets_diagnostic_report(
+    path: &Path,
+    document: &Document,
+) -> DocumentDiagnosticReport {
+    RelatedFullDocumentDiagnosticReport {
+        related_documents: None,
+        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+            result_id: None,
+            items: check_presets_file(path, document.text()),
+        },
+    }
+    .into()
+}
+
+impl Backend {
+    fn pull_lint_info(&self, path: &Path) -> LintConfigInfo {
+        LintConfigInfo {
+            use_lint: self.settings().is_lint_enabled(),
+            use_extra_cmake_lint: config::get_config(path).enable_external_cmake_lint,
+        }
+    }
+
+    fn lint_document(
+        &self,
+        path: &Path,
+        document: &Document,
+        previous_result_id: Option<&str>,
+    ) -> DiagnosticResult {
+        let lint_info = self.pull_lint_info(path);
+        let generation = self.diagnostic_generation.load(Ordering::Relaxed);
+        let result_id = diagnostic_result_id(document, lint_info, generation);
+        if previous_result_id == Some(result_id.as_str()) {
+            return DiagnosticResult {
+                result_id,
+                items: None,
+            };
+        }
+        let items = checkerror(
+            &path,
+            document.text(),
+            document.tree().root_node(),
+            lint_info,
+        )
+        .unwrap_or_default();
+        DiagnosticResult {
+            result_id,
+            items: Some(items),
+        }
+    }
+
+    pub(super) fn document_diagnostic_report(
+        &self,
+        path: &Path,
+        document: &Document,
+        previous_result_id: Option<&str>,
+    ) -> DocumentDiagnosticReport {
+        let DiagnosticResult { result_id, items } =
+            self.lint_document(path, document, previous_result_id);
+        match items {
+            Some(items) => RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                    result_id: Some(result_id),
+                    items,
+                },
+            }
+            .into(),
+            None => RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                    result_id,
+                },
+            }
+            .into(),
+        }
+    }
+
+    /// Make the previous result ids stale, after a change of the files or the build directories
+    /// which the lints look at, and ask the client to pull the diagnostics again
+    pub(super) async fn refresh_pull_diagnostics(&self) {
+        self.diagnostic_generation.fetch_add(1, Ordering::Relaxed);
+        if self.init_info().refresh_diagnostics {
+            self.client.workspace_diagnostic_refresh().await.ok();
+        }
+    }
+
+    /// Lint all the files of the project, the reports are streamed when the client gives a
+    /// partial result token
+    pub(super) async fn workspace_diagnostic_report(
+        &self,
+        previous_result_ids: &[PreviousResultId],
+        PartialResultParams {
+            partial_result_token,
+        }: PartialResultParams,
+    ) -> WorkspaceDiagnosticReport {
+        let mut items = vec![];
+        for path in scanned_files().await {
+            if !self.path_in_project(&path) {
+                continue;
+            }
+            let Ok(document) = read_document(&path, &self.documents).await else {
+                continue;
+            };
+            let uri = Uri::from_file_path(&path).unwrap();
+            let previous_result_id = previous_result_ids
+                .iter()
+                .find(|previous| previous.uri == uri)
+                .map(|previous| previous.value.as_str());
+            let DiagnosticResult {
+                result_id,
+                items: diagnostics,
+            } = self.lint_document(&path, &document, previous_result_id);
+            let version = document.version();
+            let report: WorkspaceDocumentDiagnosticReport = match diagnostics {
+                Some(diagnostics) => WorkspaceFullDocumentDiagnosticReport {
+                    uri,
+                    version,
+                    full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                        result_id: Some(result_id),
+                        items: diagnostics,
+                    },
+                }
+                .into(),
+                None => WorkspaceUnchangedDocumentDiagnosticReport {
+                    uri,
+                    version,
+                    unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                        result_id,
+                    },
+                }
+                .into(),
+            };
+            let Some(token) = partial_result_token.clone() else {
+                items.push(report);
+                continue;
+            };
+            let partial = WorkspaceDiagnosticReportPartialResult {
+                items: vec![report],
+            };
+            self.client
+                .send_notification::<ProgressNotification>(ProgressParams {
+                    token,
+                    value: serde_json::to_value(partial).unwrap(),
+                })
+                .await;
+        }
+        WorkspaceDiagnosticReport { items }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_id() {
+        let lint_info = LintConfigInfo {
+            use_lint: true,
+            use_extra_cmake_lint: false,
+        };
+        let document = Document::new("set(A 1)\n").with_version(1);
+        let id = diagnostic_result_id(&document, lint_info, 0);
+        assert_eq!(id, diagnostic_result_id(&document.clone(), lint_info, 0));
+        assert_ne!(
+            id,
+            diagnostic_result_id(&Document::new("set(A 1)\n").with_version(2), lint_info, 0)
+        );
+        assert_ne!(id, diagnostic_result_id(&document, lint_info, 1));
+        assert_ne!(
+            id,
+            diagnostic_result_id(
+                &document,
+                LintConfigInfo {
+                    use_lint: false,
+                    use_extra_cmake_lint: false,
+                },
+                0
+            )
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use tower_lsp::lsp_types::{
+    CompletionOptions, CompletionRegistrationOptions, ConfigurationItem, DefinitionOptions,
+    DefinitionRegistrationOptions, DiagnosticOptions, DiagnosticRegistrationOptions,
+    DocumentFormattingOptions, DocumentFormattingRegistrationOptions, GlobPattern, LspAny,
+    MessageType, Registration, SemanticTokensFullDelta, SemanticTokensLegend,
+    SemanticTokensOptions, SemanticTokensRegistrationOptions, StaticRegistrationOptions,
+    TextDocumentChangeRegistrationOptions, TextDocumentClientCapabilities, TextDocumentFilter,
+    TextDocumentFilterLanguage, TextDocumentFilterPattern, TextDocumentRegistrationOptions,
+    TextDocumentSyncKind, Unregistration, WorkDoneProgressOptions,
+};
+
+use super::config::Config;
+use super::init_snippet_setting;
+use crate::Backend;
+use crate::presets::{PRESETS_FILE, USER_PRESETS_FILE};
+use crate::semantic_token::{LEGEND_MODIFIERS, LEGEND_TYPE};
+
+/// The section of the client settings which belongs to neocmakelsp
+const SETTINGS_SECTION: &str = "neocmake";
+
+const FORMATTING_ID: &str = "neocmakelsp/formatting";
+const FORMATTING_METHOD: &str = "textDocument/formatting";
+const SEMANTIC_TOKENS_ID: &str = "neocmakelsp/semanticTokens";
+const SEMANTIC_TOKENS_METHOD: &str = "textDocument/semanticTokens";
+
+const DID_OPEN_METHOD: &str = "textDocument/didOpen";
+const DID_CHANGE_METHOD: &str = "textDocument/didChange";
+const DID_CLOSE_METHOD: &str = "textDocument/didClose";
+const COMPLETION_METHOD: &str = "textDocument/completion";
+const DEFINITION_METHOD: &str = "textDocument/definition";
+const DIAGNOSTIC_METHOD: &str = "textDocument/diagnostic";
+
+fn cmake_registration_options() -> TextDocumentRegistrationOptions {
+    TextDocumentRegistrationOptions {
+        document_selector: Some(vec![
+            TextDocumentFilter::Language(TextDocumentFilterLanguage {
+                language: "cmake".to_string(),
+                scheme: Some("file".to_string()),
+                pattern: None,
+            })
+            .into(),
+        ]),
+    }
+}
+
+/// The presets files are json, so the client has to be told to send them
+fn presets_registration_options() -> TextDocumentRegistrationOptions {
+    let filter = |file: &str| {
+        TextDocumentFilter::Pattern(TextDocumentFilterPattern {
+            language: None,
+            scheme: Some("file".to_string()),
+            pattern: GlobPattern::Pattern(format!("**/{file}")),
+        })
+        .into()
+    };
+    TextDocumentRegistrationOptions {
+        document_selector: Some(vec![filter(PRESETS_FILE), filter(USER_PRESETS_FILE)]),
+    }
+}
+
+pub fn semantic_tokens_registration_options() -> SemanticTokensRegistrationOptions {
+    SemanticTokensRegistrationOptions {
+        text_document_registration_options: cmake_registration_options(),
+        semantic_tokens_options: SemanticTokensOptions {
+            work_done_progress_options: WorkDoneProgressOptions::default(),
+            legend: SemanticTokensLegend {
+                token_types: LEGEND_TYPE.iter().map(|tp| tp.to_string()).collect(),
+                token_modifiers: LEGEND_MODIFIERS
+                    .iter()
+                    .map(|modifier| modifier.to_string())
+                    .collect(),
+            },
+            range: Some(true.into()),
+            full: Some(SemanticTokensFullDelta { delta: Some(true) }.into()),
+        },
+        static_registration_options: StaticRegistrationOptions::default(),
+    }
+}
+
+fn formatting_registration() -> Registration {
+    let options = DocumentFormattingRegistrationOptions {
+        text_document_registration_options: cmake_registration_options(),
+        document_formatting_options: DocumentFormattingOptions::default(),
+    };
+    Registration {
+        id: FORMATTING_ID.to_string(),
+        method: FORMATTING_METHOD.to_string(),
+        register_options: Some(serde_json::to_value(options).unwrap()),
+    }
+}
+
+fn semantic_tokens_registration() -> Registration {
+    Registration {
+        id: SEMANTIC_TOKENS_ID.to_string(),
+        method: SEMANTIC_TOKENS_METHOD.to_string(),
+        register_options: Some(
+            serde_json::to_value(semantic_tokens_registration_options()).unwrap(),
+        ),
+    }
+}
+
+/// The methods which the client can register for the presets files, nothing is registered if the
+/// client cannot send the presets files
+pub fn presets_methods(
+    text_document: Option<&TextDocumentClientCapabilities>,
+) -> Vec<&'static str> {
+    let Some(text_document) = text_document else {
+        return Vec::new();
+    };
+    let synchronization = text_document
+        .synchronization
+        .as_ref()
+        .and_then(|sync| sync.dynamic_registration);
+    if synchronization != Some(true) {
+        return Vec::new();
+    }
+    let mut methods = vec![DID_OPEN_METHOD, DID_CHANGE_METHOD, DID_CLOSE_METHOD];
+    let features = [
+        (
+            COMPLETION_METHOD,
+            text_document
+                .completion
+                .as_ref()
+                .and_then(|completion| completion.dynamic_registration),
+        ),
+        (
+            DEFINITION_METHOD,
+            text_document
+                .definition
+                .as_ref()
+                .and_then(|definition| definition.dynamic_registration),
+        ),
+        (
+            DIAGNOSTIC_METHOD,
+            text_document
+                .diagnostic
+                .as_ref()
+                .and_then(|diagnostic| diagnostic.dynamic_registration),
+        ),
+    ];
+    for (method, dynamic_registration) in features {
+        if dynamic_registration == Some(true) {
+            methods.push(method);
+        }
+    }
+    methods
+}
+
+/// Register the methods accepted by the client for the presets files
+fn presets_registrations(methods: &[&'static str]) -> Vec<Registration> {
+    methods
+        .iter()
+        .map(|&method| {
+            let text_document_registration_options = presets_registration_options();
+            let options = match method {
+                DID_CHANGE_METHOD => serde_json::to_value(TextDocumentChangeRegistrationOptions {
+                    sync_kind: TextDocumentSyncKind::Incremental,
+                    text_document_registration_options,
+                }),
+                COMPLETION_METHOD => serde_json::to_value(CompletionRegistrationOptions {
+                    text_document_registration_options,
+                    completion_options: CompletionOptions {
+                        trigger_characters: Some(vec!["\"".to_owned()]),
+                        ..Default::default()
+                    },
+                }),
+                DEFINITION_METHOD => serde_json::to_value(DefinitionRegistrationOptions {
+                    text_document_registration_options,
+                    definition_options: DefinitionOptions::default(),
+                }),
+                DIAGNOSTIC_METHOD => serde_json::to_value(DiagnosticRegistrationOptions {
+                    static_registration_options: StaticRegistrationOptions::default(),
+                    text_document_registration_options,
+                    diagnostic_options: DiagnosticOptions {
+                        identifier: Some("neocmakelsp/presets".to_owned()),
+                        inter_file_dependencies: true,
+                        workspace_diagnostics: false,
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    },
+                }),
+                _ => serde_json::to_value(text_document_registration_options),
+            };
+            Registration {
+                id: format!("neocmakelsp/presets/{method}"),
+                method: method.to_string(),
+                register_options: Some(options.unwrap()),
+            }
+        })
+        .collect()
+}
+
+/// Read the settings sent by the client, which may be nested in the neocmake section
+fn parse_settings(value: LspAny) -> Option<Config> {
+    let value = match value {
+        LspAny::Object(mut map) if map.contains_key(SETTINGS_SECTION) => {
+            map.remove(SETTINGS_SECTION)?
+        }
+        value => value,
+    };
+    if !value.is_object() {
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+impl Backend {
+    pub(super) fn settings(&self) -> Config {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Ask the settings with `workspace/configuration`, or use the ones pushed by the client when
+    /// it cannot answer
+    pub(super) async fn fetch_settings(&self, pushed: LspAny) -> Option<Config> {
+        if !self.init_info().pull_settings {
+            return parse_settings(pushed);
+        }
+        let item = ConfigurationItem {
+            scope_uri: None,
+            section: Some(SETTINGS_SECTION.to_string()),
+        };
+        match self.client.configuration(vec![item]).await {
+            Ok(values) => values.into_iter().next().and_then(parse_settings),
+            Err(e) => {
+                tracing::warn!("Cannot get the settings: {e}");
+                parse_settings(pushed)
+            }
+        }
+    }
+
+    /// Register the capabilities that the client can register after the initialization
+    pub(super) async fn register_dynamic_capabilities(&self) {
+        let settings = self.settings();
+        let mut registrations = presets_registrations(&self.init_info().presets_methods);
+        if self.init_info().dynamic_formatting && settings.is_format_enabled() {
+            registrations.push(formatting_registration());
+        }
+        if self.init_info().dynamic_semantic_tokens && settings.enable_semantic_token() {
+            registrations.push(semantic_tokens_registration());
+        }
+        if registrations.is_empty() {
+            return;
+        }
+        if let Err(e) = self.client.register_capability(registrations).await {
+            tracing::warn!("Cannot register the capabilities: {e}");
+        }
+    }
+
+    /// Apply the new settings, register or unregister the toggled capabilities and lint again
+    pub(super) async fn apply_settings(&self, settings: Config) {
+        let previous = std::mem::replace(&mut *self.settings.write().unwrap(), settings.clone());
+        if previous == settings {
+            return;
+        }
+        init_snippet_setting(settings.use_snippets());
+
+        let mut registrations = vec![];
+        let mut unregistrations = vec![];
+        if self.init_info().dynamic_formatting
+            && previous.is_format_enabled() != settings.is_format_enabled()
+        {
+            if settings.is_format_enabled() {
+                registrations.push(formatting_registration());
+            } else {
+                unregistrations.push(Unregistration {
+                    id: FORMATTING_ID.to_string(),
+                    method: FORMATTING_METHOD.to_string(),
+                });
+            }
+        }
+        if self.init_info().dynamic_semantic_tokens
+            && previous.enable_semantic_token() != settings.enable_semantic_token()
+        {
+            if settings.enable_semantic_token() {
+                registrations.push(semantic_tokens_registration());
+            } else {
+                self.semantic_tokens.clear();
+                unregistrations.push(Unregistration {
+                    id: SEMANTIC_TOKENS_ID.to_string(),
+                    method: SEMANTIC_TOKENS_METHOD.to_string(),
+                });
+            }
+        }
+        if !unregistrations.is_empty()
+            && let Err(e) = self.client.unregister_capability(unregistrations).await
+        {
+            tracing::warn!("Cannot unregister the capabilities: {e}");
+        }
+        if !registrations.is_empty()
+            && let Err(e) = self.client.register_capability(registrations).await
+        {
+            tracing::warn!("Cannot register the capabilities: {e}");
+        }
+
+        let build_dir_changed = previous.build_directory() != settings.build_directory()
+            || previous.configure_preset() != settings.configure_preset();
+        if build_dir_changed {
+            self.reload_build_dirs(self.root_paths()).await;
+        }
+        if build_dir_changed || previous.is_lint_enabled() != settings.is_lint_enabled() {
+            self.update_diagnostics().await;
+            self.refresh_pull_diagnostics().await;
+        }
+        self.client
+            .log_message(MessageType::Info, "settings applied")
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_settings() {
+        let settings = parse_settings(json!({
+            "neocmake": {
+                "lint": { "enable": false },
+                "semantic_token": true,
+            }
+        }))
+        .unwrap();
+        assert!(!settings.is_lint_enabled());
+        assert!(settings.enable_semantic_token());
+        assert!(settings.is_format_enabled());
+
+        let settings = parse_settings(json!({ "use_snippets": true })).unwrap();
+        assert!(settings.use_snippets());
+
+        assert_eq!(parse_settings(LspAny::Null), None);
+        assert_eq!(parse_settings(json!({ "neocmake": null })), None);
+    }
+}
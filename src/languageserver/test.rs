@@ -7,15 +7,16 @@ use tower_lsp::jsonrpc::Request;
 use tower_lsp::lsp_types::WorkspaceFolders;
 use tower_lsp::lsp_types::{
     CompletionParams, CompletionResponse, DidOpenTextDocumentParams, InitializeParams,
-    InitializeResult, PartialResultParams, Position, SemanticTokensLegend, SemanticTokensOptions,
-    SemanticTokensProvider, TextDocumentIdentifier, TextDocumentItem, TextDocumentPositionParams,
-    Uri, WorkDoneProgressOptions, WorkDoneProgressParams, WorkspaceFolder,
+    InitializeResult, PartialResultParams, Position, SemanticTokensFullDelta, SemanticTokensLegend,
+    SemanticTokensOptions, SemanticTokensProvider, TextDocumentIdentifier, TextDocumentItem,
+    TextDocumentPositionParams, Uri, WorkDoneProgressOptions, WorkDoneProgressParams,
+    WorkspaceFolder,
 };
 use tower_lsp::{LanguageServer, LspService};
 
 use super::Backend;
 use crate::languageserver::Config;
-use crate::semantic_token::LEGEND_TYPE;
+use crate::semantic_token::{LEGEND_MODIFIERS, LEGEND_TYPE};
 
 fn create_request<T>(id: i64, init_param: T, method: &'static str) -> Request
 where
@@ -99,10 +100,13 @@ async fn test_init() {
                 },
                 legend: SemanticTokensLegend {
                     token_types: LEGEND_TYPE.iter().map(|tp| tp.to_string()).collect(),
-                    token_modifiers: [].to_vec()
+                    token_modifiers: LEGEND_MODIFIERS
+                        .iter()
+                        .map(|modifier| modifier.to_string())
+                        .collect()
                 },
-                range: None,
-                full: Some(true.into()),
+                range: Some(true.into()),
+                full: Some(SemanticTokensFullDelta { delta: Some(true) }.into()),
             }
         ))
     );
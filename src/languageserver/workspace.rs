@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::{LspAny, MessageActionItem, MessageType};
+
+use crate::Backend;
+use crate::build_dir::{BuildChoice, build_choices, latest_choice};
+use crate::fileapi::target::TARGET_REGEX;
+use crate::fileapi::{self, DEFAULT_QUERY};
+use crate::utils::{
+    did_vcpkg_project, owning_root, register_vcpkg_project, unregister_vcpkg_project,
+};
+use crate::{config, filewatcher, scansubs};
+
+/// A workspace folder, which is handled as its own CMake project
+#[derive(Debug, Clone)]
+pub struct ProjectRoot {
+    /// Where the project is built, and where CMake writes the cache and the fileapi replies
+    pub build: BuildChoice,
+    /// The build directories or the configure presets found in the project, the user can
+    /// choose one of them
+    pub choices: Vec<BuildChoice>,
+}
+
+impl ProjectRoot {
+    fn new(choices: Vec<BuildChoice>) -> Self {
+        let build = latest_choice(&choices)
+            .cloned()
+            .expect("there is always a build directory to choose");
+        Self { build, choices }
+    }
+}
+
+/// Read the CMakeCache.txt and the fileapi replies left in the build directory of a project
+fn load_build_dir(root: &Path, build: &BuildChoice) {
+    fileapi::set_preset_entries(root, build.cache_variables());
+    let build_dir = &build.build_dir;
+    let cache_file = build_dir.join("CMakeCache.txt");
+    if cache_file.exists() {
+        filewatcher::refresh_error_packages(root, cache_file);
+    }
+
+    let reply_dir = build_dir
+        .join(".cmake")
+        .join("api")
+        .join("v1")
+        .join("reply");
+    let Ok(entries) = std::fs::read_dir(reply_dir) else {
+        return;
+    };
+    tracing::info!("find cache-v2 json, start reading the data");
+    for entry in entries.flatten() {
+        let file_path = entry.path();
+        if !file_path.is_file() {
+            continue;
+        }
+        let Some(file_name) = file_path.file_name() else {
+            continue;
+        };
+        let file_name = file_name.to_string_lossy().to_string();
+        if file_name.starts_with("cache-v2") && file_name.ends_with(".json") {
+            fileapi::update_cache_data(root, &file_path);
+        }
+        if TARGET_REGEX.is_match(&file_name) {
+            fileapi::update_target_data(root, &file_path);
+        }
+    }
+    tracing::info!("Finish getting the data in cache-v2 json");
+}
+
+impl Backend {
+    pub(super) fn root_paths(&self) -> Vec<PathBuf> {
+        self.roots.iter().map(|root| root.key().clone()).collect()
+    }
+
+    /// The workspace folder of the file, the nested folders own their files
+    pub(super) fn root_of(&self, path: &Path) -> Option<PathBuf> {
+        owning_root(&self.root_paths(), path).cloned()
+    }
+
+    /// The workspace folder whose build directory contains the file
+    pub(super) fn root_of_build_file(&self, path: &Path) -> Option<PathBuf> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(&root.build.build_dir))
+            .max_by_key(|root| root.build.build_dir.components().count())
+            .map(|root| root.key().clone())
+    }
+
+    pub(super) fn build_of(&self, root: &Path) -> Option<BuildChoice> {
+        self.roots.get(root).map(|project| project.build.clone())
+    }
+
+    /// The project of a workspace folder, the build directory and the configure preset set in
+    /// `.neocmake.toml` are used before the ones in the settings of the client
+    async fn new_project(&self, root: &Path) -> ProjectRoot {
+        let config = config::get_config(root);
+        let settings = self.settings();
+        let configured_dir = config
+            .build_directory
+            .clone()
+            .or_else(|| settings.build_directory().map(Path::to_path_buf));
+        let configured_preset = config
+            .configure_preset
+            .clone()
+            .or_else(|| settings.configure_preset().map(str::to_string));
+        let root = root.to_path_buf();
+        // NOTE: the build directories are searched in the whole project, which blocks for a
+        // while in large trees
+        let choices = tokio::task::spawn_blocking(move || {
+            build_choices(
+                &root,
+                configured_dir.as_deref(),
+                configured_preset.as_deref(),
+            )
+        })
+        .await
+        .expect("the search of the build directories does not panic");
+        ProjectRoot::new(choices)
+    }
+
+    /// Add a workspace folder, with its own config and the data of its build directory
+    pub(super) async fn add_root(&self, root: PathBuf) {
+        config::load_project_config(&root);
+        let project = self.new_project(&root).await;
+        load_build_dir(&root, &project.build);
+        self.roots.insert(root, project);
+    }
+
+    /// Use another build directory or configure preset for the project, the data of the
+    /// previous build directory is dropped
+    fn set_build(&self, root: &Path, build: BuildChoice) {
+        let Some(mut project) = self.roots.get_mut(root) else {
+            return;
+        };
+        if project.build == build {
+            return;
+        }
+        tracing::info!("Use the build directory {}", build.build_dir.display());
+        fileapi::clear_root_data(root);
+        filewatcher::clear_error_packages(root);
+        load_build_dir(root, &build);
+        if build.build_dir.is_dir()
+            && let Some(query) = &*DEFAULT_QUERY
+        {
+            query.write_to_build_dir(&build.build_dir).ok();
+        }
+        project.build = build;
+    }
+
+    /// Find the build directories and the presets of the projects again, after the configured
+    /// ones or the presets files are changed. The choice of the user is kept when it is still
+    /// available
+    pub(super) async fn reload_build_dirs(&self, roots: Vec<PathBuf>) {
+        for root in roots {
+            let project = self.new_project(&root).await;
+            let Some(current) = self.build_of(&root) else {
+                continue;
+            };
+            let build = project
+                .choices
+                .iter()
+                .find(|choice| choice.title == current.title)
+                .cloned()
+                .unwrap_or(project.build);
+            self.set_build(&root, build);
+            if let Some(mut current) = self.roots.get_mut(&root) {
+                current.choices = project.choices;
+            }
+        }
+    }
+
+    /// Ask the user which build directory or configure preset to use, when there are several
+    pub(super) async fn choose_build_dir(&self, root: &Path) {
+        let Some(choices) = self.roots.get(root).map(|project| project.choices.clone()) else {
+            return;
+        };
+        if choices.len() < 2 {
+            return;
+        }
+        let actions = choices
+            .iter()
+            .map(|choice| MessageActionItem {
+                title: choice.title.clone(),
+                properties: HashMap::new(),
+            })
+            .collect();
+        let message = if choices.iter().any(|choice| choice.preset.is_some()) {
+            format!(
+                "Several configure presets are found in {}, choose the one to use",
+                root.display()
+            )
+        } else {
+            format!(
+                "Several build directories are found in {}, choose the one to use",
+                root.display()
+            )
+        };
+        let Ok(Some(action)) = self
+            .client
+            .show_message_request(MessageType::Info, message, Some(actions))
+            .await
+        else {
+            return;
+        };
+        if let Some(build) = choices
+            .into_iter()
+            .find(|choice| choice.title == action.title)
+        {
+            self.set_build(root, build);
+            self.refresh_pull_diagnostics().await;
+        }
+    }
+
+    /// Scan the CMake files of a workspace folder, and query the fileapi for the next build
+    pub(super) async fn scan_root(&self, root: &Path) {
+        scansubs::scan_all(root, true).await;
+        let build_dir = self.build_of(root).map(|build| build.build_dir);
+        if let Some(build_dir) = build_dir
+            && build_dir.is_dir()
+            && let Some(query) = &*DEFAULT_QUERY
+        {
+            query.write_to_build_dir(&build_dir).ok();
+        }
+        if did_vcpkg_project(root) {
+            tracing::info!("This project is vcpkg project, start init vcpkg data");
+            register_vcpkg_project(root);
+        }
+    }
+
+    /// The project to build the target of a code lens, found by the file of the lens
+    pub(super) fn command_root(&self, arguments: &[LspAny]) -> Option<PathBuf> {
+        let roots = self.root_paths();
+        if let [root] = roots.as_slice() {
+            return Some(root.clone());
+        }
+        let file = arguments.get(1).and_then(LspAny::as_str)?;
+        owning_root(&roots, Path::new(file)).cloned()
+    }
+
+    /// Remove a workspace folder, its scanned data is cached for the next time
+    pub(super) async fn remove_root(&self, root: &Path) {
+        if self.roots.remove(root).is_none() {
+            return;
+        }
+        scansubs::cache_project_data(root).await;
+        scansubs::forget_project(root).await;
+        fileapi::clear_root_data(root);
+        filewatcher::clear_error_packages(root);
+        config::remove_project_config(root);
+        unregister_vcpkg_project(root);
+    }
+}
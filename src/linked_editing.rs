@@ -0,0 +1,118 @@
+use tower_lsp::lsp_types::{LinkedEditingRanges, Position};
+use tree_sitter::{Node, Point};
+
+use crate::document::Document;
+use crate::utils::query::ToLspRange;
+use crate::utils::treehelper::{BLOCK_KINDS, ToPoint, location_range_contain, repeated_arguments};
+
+fn innermost_block(root: Node, point: Point) -> Option<Node> {
+    let mut node = root.descendant_for_point_range(point, point);
+    while let Some(current) = node {
+        if BLOCK_KINDS.contains(&current.kind()) {
+            return Some(current);
+        }
+        node = current.parent();
+    }
+    None
+}
+
+pub fn get_linked_editing_ranges(
+    document: &Document,
+    position: Position,
+) -> Option<LinkedEditingRanges> {
+    let point = position.to_point();
+    let block = innermost_block(document.tree().root_node(), point)?;
+    let arguments = repeated_arguments(block)?;
+    let repeated = arguments.repeated?;
+    if !location_range_contain(point, arguments.expected)
+        && !location_range_contain(point, repeated)
+    {
+        return None;
+    }
+    // NOTE: the linked ranges must have the same content
+    let source = document.text().as_bytes();
+    if arguments.expected.utf8_text(source).ok()? != repeated.utf8_text(source).ok()? {
+        return None;
+    }
+    Some(LinkedEditingRanges {
+        ranges: vec![
+            arguments.expected.range().lsp_range(),
+            repeated.range().lsp_range(),
+        ],
+        word_pattern: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::treehelper::all_repeated_arguments;
+
+    fn linked_at(source: &str, line: u32, character: u32) -> Option<Vec<(u32, u32, u32, u32)>> {
+        let document = Document::new(source);
+        let ranges = get_linked_editing_ranges(&document, Position { line, character })?;
+        Some(
+            ranges
+                .ranges
+                .iter()
+                .map(|range| {
+                    (
+                        range.start.line,
+                        range.start.character,
+                        range.end.line,
+                        range.end.character,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_linked_function_name() {
+        let source = r"function(hello a b)
+  message(${a})
+endfunction(hello)
+";
+        let expected = Some(vec![(0, 9, 0, 14), (2, 12, 2, 17)]);
+        assert_eq!(linked_at(source, 0, 10), expected);
+        assert_eq!(linked_at(source, 2, 14), expected);
+        // the other arguments are not repeated
+        assert_eq!(linked_at(source, 0, 16), None);
+    }
+
+    #[test]
+    fn test_linked_if_condition() {
+        let source = r"if(NOT A)
+  foreach(item IN LISTS items)
+  endforeach()
+endif(NOT A)
+";
+        assert_eq!(
+            linked_at(source, 0, 4),
+            Some(vec![(0, 3, 0, 8), (3, 6, 3, 11)])
+        );
+        // the closer does not repeat the loop variable
+        assert_eq!(linked_at(source, 1, 11), None);
+    }
+
+    #[test]
+    fn test_mismatched_arguments() {
+        let source = r"function(hello)
+endfunction(world)
+if(A  AND B)
+endif(A AND B)
+while(A)
+endwhile(B)
+macro(test)
+endmacro()
+foreach(item IN LISTS items)
+endforeach(item)
+";
+        let document = Document::new(source);
+        let mut blocks = vec![];
+        all_repeated_arguments(document.tree().root_node(), &mut blocks);
+        let matches: Vec<bool> = blocks.iter().map(|block| block.matches(source)).collect();
+        assert_eq!(matches, vec![false, true, false, true, true]);
+        assert_eq!(linked_at(source, 0, 10), None);
+    }
+}
@@ -11,45 +11,72 @@ mod treesitter_nodetypes;
 
 use tokio::net::TcpListener;
 use treesitter_nodetypes as CMakeNodeKinds;
+mod build_dir;
+mod call_hierarchy;
 mod cli;
 mod cmake_tool;
+mod code_lens;
 mod complete;
 mod config;
 mod consts;
+mod document;
+mod document_highlight;
 mod document_link;
 mod document_symbol;
+mod file_operations;
 mod fileapi;
 mod filewatcher;
+mod folding_range;
 mod formatting;
 mod grammar;
 mod hover;
+mod inlay_hint;
 mod jump;
 mod languageserver;
+mod linked_editing;
+mod presets;
 mod quick_fix;
 mod rename;
 mod scansubs;
 mod search;
+mod selection_range;
 mod semantic_token;
 mod signature_help;
 mod utils;
-use std::sync::OnceLock;
+mod workspace_symbol;
+use std::sync::atomic::AtomicU64;
+use std::sync::{OnceLock, RwLock};
 
-use tower_lsp::lsp_types::Uri;
+use tower_lsp::lsp_types::{SemanticTokens, Uri};
 
 use crate::cli::{Cli, Command};
+use crate::document::Document;
 use crate::formatting::format_file;
+use crate::languageserver::{Config, ProjectRoot};
 
 #[derive(Debug)]
 struct BackendInitInfo {
     pub scan_cmake_in_package: bool,
-    pub enable_lint: bool,
+    /// The client answers to `workspace/configuration`
+    pub pull_settings: bool,
+    /// The client accepts to register the formatting after the initialization
+    pub dynamic_formatting: bool,
+    /// The client accepts to register the semantic tokens after the initialization
+    pub dynamic_semantic_tokens: bool,
+    /// The methods which the client accepts to register for the presets files
+    pub presets_methods: Vec<&'static str>,
+    pub refresh_diagnostics: bool,
 }
 
 impl Default for BackendInitInfo {
     fn default() -> Self {
         Self {
             scan_cmake_in_package: true,
-            enable_lint: true,
+            pull_settings: false,
+            dynamic_formatting: false,
+            dynamic_semantic_tokens: false,
+            presets_methods: Vec::new(),
+            refresh_diagnostics: false,
         }
     }
 }
@@ -57,10 +84,22 @@ impl Default for BackendInitInfo {
 #[derive(Debug)]
 struct Backend {
     client: Client,
-    documents: DashMap<Uri, String>,
+    documents: DashMap<Uri, Document>,
+    /// The opened CMakePresets.json and CMakeUserPresets.json, which are not cmake documents
+    presets_documents: DashMap<Uri, Document>,
     /// Storage the message of buffers
     init_info: OnceLock<BackendInitInfo>,
-    root_path: OnceLock<Option<PathBuf>>,
+    /// The workspace folders, every one is a project with its own state
+    roots: DashMap<PathBuf, ProjectRoot>,
+    /// The settings of the client, which can change after the initialization
+    settings: RwLock<Config>,
+    /// The last semantic tokens sent for the documents, to send the delta next time
+    semantic_tokens: DashMap<Uri, SemanticTokens>,
+    /// The created source files which are not in a target yet, with the CMakeLists.txt owning them
+    created_sources: DashMap<PathBuf, PathBuf>,
+    /// Changed when the files or the build directories which the lints look at change, it is a
+    /// part of the result ids of the pulled diagnostics
+    diagnostic_generation: AtomicU64,
 }
 
 impl Backend {
@@ -68,8 +107,13 @@ impl Backend {
         Self {
             client,
             documents: DashMap::new(),
+            presets_documents: DashMap::new(),
             init_info: OnceLock::new(),
-            root_path: OnceLock::new(),
+            roots: DashMap::new(),
+            settings: RwLock::new(Config::default()),
+            semantic_tokens: DashMap::new(),
+            created_sources: DashMap::new(),
+            diagnostic_generation: AtomicU64::new(0),
         }
     }
 }
@@ -146,6 +190,8 @@ async fn main() -> Result<()> {
             let (stdin, stdout) = (tokio::io::stdin(), tokio::io::stdout());
             let (service, socket) = LspService::build(Backend::new)
                 .custom_method("neocmake/cmake_targets", Backend::cmake_targets)
+                .custom_method("neocmake/targetsForFile", Backend::targets_for_file)
+                .custom_method("neocmake/compileInfo", Backend::compile_info)
                 .finish();
             Server::new(stdin, stdout, socket).serve(service).await;
         }
@@ -155,6 +201,8 @@ async fn main() -> Result<()> {
             let (read, write) = tokio::io::split(stream);
             let (service, socket) = LspService::build(Backend::new)
                 .custom_method("neocmake/cmake_targets", Backend::cmake_targets)
+                .custom_method("neocmake/targetsForFile", Backend::targets_for_file)
+                .custom_method("neocmake/compileInfo", Backend::compile_info)
                 .finish();
             Server::new(read, write, socket).serve(service).await;
         }
@@ -232,11 +280,22 @@ async fn main() -> Result<()> {
                 None => println!("Nothing found"),
             }
         }
-        Command::Run { target, path, args } => {
-            cmake_tool::help_run(target, path, args)?;
+        Command::Run {
+            target,
+            path,
+            build_dir,
+            preset,
+            args,
+        } => {
+            cmake_tool::help_run(target, path, build_dir, preset, args)?;
         }
-        Command::Build { target, path } => {
-            cmake_tool::help_build(target, path)?;
+        Command::Build {
+            target,
+            path,
+            build_dir,
+            preset,
+        } => {
+            cmake_tool::help_build(target, path, build_dir, preset)?;
         }
     }
 
@@ -0,0 +1,385 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+mod complete;
+mod index;
+mod json;
+mod jump;
+mod lint;
+
+pub use self::complete::getcomplete;
+pub use self::index::is_presets_file;
+pub use self::jump::godef;
+pub use self::lint::check_presets_file;
+
+pub const PRESETS_FILE: &str = "CMakePresets.json";
+pub const USER_PRESETS_FILE: &str = "CMakeUserPresets.json";
+
+/// The macros of the presets, such as `${sourceDir}`, `$env{HOME}` and `$vendor{name}`
+static MACRO_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"\$(?P<namespace>env|penv|vendor)?\{(?P<name>[^}]*)\}").unwrap()
+});
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+enum Inherits {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Default for Inherits {
+    fn default() -> Self {
+        Self::Many(Vec::new())
+    }
+}
+
+impl Inherits {
+    fn names(&self) -> &[String] {
+        match self {
+            Self::One(name) => std::slice::from_ref(name),
+            Self::Many(names) => names,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct RawConfigurePreset {
+    name: String,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    inherits: Inherits,
+    generator: Option<String>,
+    binary_dir: Option<String>,
+    cmake_executable: Option<String>,
+    #[serde(default)]
+    cache_variables: HashMap<String, Value>,
+    #[serde(default)]
+    environment: HashMap<String, Option<String>>,
+}
+
+impl RawConfigurePreset {
+    /// Expand `${fileDir}`, which is the directory of the file containing the macro, so it is
+    /// expanded before the fields are inherited by the presets of other files
+    fn expand_file_dir(&mut self, file_dir: &Path) {
+        let file_dir = file_dir.to_string_lossy();
+        let expand = |value: &mut String| *value = value.replace("${fileDir}", &file_dir);
+        self.binary_dir.iter_mut().for_each(expand);
+        self.cmake_executable.iter_mut().for_each(expand);
+        self.environment.values_mut().flatten().for_each(expand);
+        for value in self.cache_variables.values_mut() {
+            let value = match value {
+                Value::Object(object) => object.get_mut("value"),
+                value => Some(value),
+            };
+            if let Some(Value::String(value)) = value {
+                expand(value);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct PresetsFile {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    configure_presets: Vec<RawConfigurePreset>,
+}
+
+/// A configure preset, with its inherited fields and its macros expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigurePreset {
+    pub name: String,
+    pub generator: Option<String>,
+    pub binary_dir: Option<PathBuf>,
+    pub cmake_executable: Option<PathBuf>,
+    pub cache_variables: HashMap<String, String>,
+    /// The variables set for CMake, `None` unsets the variable
+    pub environment: HashMap<String, Option<String>>,
+}
+
+/// Read a presets file and the files it includes, every file is only read once
+fn load_file(path: &Path, presets: &mut Vec<RawConfigurePreset>, visited: &mut HashSet<PathBuf>) {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(path.clone()) {
+        return;
+    }
+    let Some(file) = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<PresetsFile>(&content).ok())
+    else {
+        return;
+    };
+    let file_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    for include in &file.include {
+        load_file(&file_dir.join(include), presets, visited);
+    }
+    presets.extend(file.configure_presets.into_iter().map(|mut preset| {
+        preset.expand_file_dir(&file_dir);
+        preset
+    }));
+}
+
+/// Read the presets of the project, CMakeUserPresets.json includes CMakePresets.json implicitly
+fn load_presets(root: &Path) -> Vec<RawConfigurePreset> {
+    let mut presets = Vec::new();
+    let mut visited = HashSet::new();
+    load_file(&root.join(PRESETS_FILE), &mut presets, &mut visited);
+    load_file(&root.join(USER_PRESETS_FILE), &mut presets, &mut visited);
+    presets
+}
+
+/// Merge the fields of the parents into the preset, the first parent wins
+fn inherit(
+    name: &str,
+    presets: &HashMap<&str, &RawConfigurePreset>,
+    stack: &mut Vec<String>,
+) -> Option<RawConfigurePreset> {
+    if stack.iter().any(|visited| visited == name) {
+        tracing::warn!("The inheritance of the preset {name} is a cycle");
+        return None;
+    }
+    let mut preset = (*presets.get(name)?).clone();
+    stack.push(name.to_string());
+    for parent in preset.inherits.names().to_vec() {
+        // NOTE: the unknown parents are skipped, but a cycle makes the whole chain invalid
+        if !presets.contains_key(parent.as_str()) {
+            continue;
+        }
+        let parent = inherit(&parent, presets, stack)?;
+        preset.generator = preset.generator.or(parent.generator);
+        preset.binary_dir = preset.binary_dir.or(parent.binary_dir);
+        preset.cmake_executable = preset.cmake_executable.or(parent.cmake_executable);
+        for (key, value) in parent.cache_variables {
+            preset.cache_variables.entry(key).or_insert(value);
+        }
+        for (key, value) in parent.environment {
+            preset.environment.entry(key).or_insert(value);
+        }
+    }
+    stack.pop();
+    Some(preset)
+}
+
+const fn host_system_name() -> &'static str {
+    match std::env::consts::OS.as_bytes() {
+        b"linux" => "Linux",
+        b"macos" => "Darwin",
+        b"windows" => "Windows",
+        _ => std::env::consts::OS,
+    }
+}
+
+struct MacroContext<'a> {
+    source_dir: &'a Path,
+    preset: &'a RawConfigurePreset,
+}
+
+impl MacroContext<'_> {
+    fn expand(&self, value: &str) -> String {
+        MACRO_REGEX
+            .replace_all(value, |captures: &regex::Captures| {
+                let name = &captures["name"];
+                let expanded = match captures
+                    .name("namespace")
+                    .map(|namespace| namespace.as_str())
+                {
+                    None => self.expand_builtin(name),
+                    Some("env") => self
+                        .preset
+                        .environment
+                        .get(name)
+                        .cloned()
+                        .flatten()
+                        .or_else(|| std::env::var(name).ok()),
+                    Some("penv") => std::env::var(name).ok(),
+                    _ => None,
+                };
+                expanded.unwrap_or_else(|| captures[0].to_string())
+            })
+            .to_string()
+    }
+
+    fn expand_builtin(&self, name: &str) -> Option<String> {
+        let path = |path: &Path| path.to_string_lossy().to_string();
+        Some(match name {
+            "sourceDir" => path(self.source_dir),
+            "sourceParentDir" => path(self.source_dir.parent().unwrap_or(self.source_dir)),
+            "sourceDirName" => self.source_dir.file_name()?.to_string_lossy().to_string(),
+            "presetName" => self.preset.name.clone(),
+            "generator" => self.preset.generator.clone().unwrap_or_default(),
+            "hostSystemName" => host_system_name().to_string(),
+            "dollar" => "$".to_string(),
+            "pathListSep" => (if cfg!(windows) { ";" } else { ":" }).to_string(),
+            _ => return None,
+        })
+    }
+
+    fn cache_value(&self, value: &Value) -> Option<String> {
+        match value {
+            Value::Bool(true) => Some("TRUE".to_string()),
+            Value::Bool(false) => Some("FALSE".to_string()),
+            Value::String(value) => Some(self.expand(value)),
+            Value::Object(object) => self.cache_value(object.get("value")?),
+            _ => None,
+        }
+    }
+}
+
+/// Expand the macros of a preset, after its fields are inherited
+fn expand_preset(source_dir: &Path, preset: &RawConfigurePreset) -> ConfigurePreset {
+    let context = MacroContext { source_dir, preset };
+    let path = |value: &String| source_dir.join(context.expand(value));
+    ConfigurePreset {
+        name: preset.name.clone(),
+        generator: preset.generator.clone(),
+        binary_dir: preset.binary_dir.as_ref().map(path),
+        cmake_executable: preset
+            .cmake_executable
+            .as_ref()
+            .map(|value| PathBuf::from(context.expand(value))),
+        cache_variables: preset
+            .cache_variables
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), context.cache_value(value)?)))
+            .collect(),
+        environment: preset
+            .environment
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.clone(),
+                    value.as_ref().map(|value| context.expand(value)),
+                )
+            })
+            .collect(),
+    }
+}
+
+/// The configure presets of the project which can be selected, the hidden ones are skipped
+pub fn load_configure_presets(root: &Path) -> Vec<ConfigurePreset> {
+    let presets = load_presets(root);
+    let by_name: HashMap<&str, &RawConfigurePreset> = presets
+        .iter()
+        .map(|preset| (preset.name.as_str(), preset))
+        .collect();
+    presets
+        .iter()
+        .filter(|preset| !preset.hidden)
+        .filter_map(|preset| {
+            let preset = inherit(&preset.name, &by_name, &mut Vec::new())?;
+            Some(expand_preset(root, &preset))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_load_configure_presets() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("cmake")).unwrap();
+        fs::write(
+            root.join("cmake").join("base.json"),
+            r#"{
+  "version": 6,
+  "configurePresets": [
+    {
+      "name": "base",
+      "hidden": true,
+      "generator": "Ninja",
+      "binaryDir": "${sourceDir}/out/build/${presetName}",
+      "cacheVariables": {
+        "CMAKE_EXPORT_COMPILE_COMMANDS": true,
+        "CMAKE_BUILD_TYPE": "Debug",
+        "FROM_FILE": "${fileDir}"
+      },
+      "environment": { "CC": "gcc" }
+    }
+  ]
+}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join(PRESETS_FILE),
+            r#"{
+  "version": 6,
+  "include": ["cmake/base.json"],
+  "configurePresets": [
+    {
+      "name": "release",
+      "inherits": "base",
+      "cacheVariables": {
+        "CMAKE_BUILD_TYPE": { "type": "STRING", "value": "Release" },
+        "COMPILER": "$env{CC}-${generator}"
+      }
+    },
+    { "name": "loop-a", "inherits": ["loop-b"] },
+    { "name": "loop-b", "inherits": ["loop-a"], "hidden": true }
+  ]
+}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join(USER_PRESETS_FILE),
+            r#"{
+  "version": 6,
+  "configurePresets": [
+    {
+      "name": "mine",
+      "inherits": ["release"],
+      "binaryDir": "build-mine",
+      "cmakeExecutable": "/opt/cmake/bin/cmake",
+      "environment": { "CC": null }
+    }
+  ]
+}"#,
+        )
+        .unwrap();
+
+        let presets = load_configure_presets(root);
+        let names: Vec<&str> = presets.iter().map(|preset| preset.name.as_str()).collect();
+        assert_eq!(names, vec!["release", "mine"]);
+
+        let release = &presets[0];
+        assert_eq!(release.generator.as_deref(), Some("Ninja"));
+        assert_eq!(
+            release.binary_dir,
+            Some(root.join("out").join("build").join("release"))
+        );
+        assert_eq!(release.cache_variables["CMAKE_BUILD_TYPE"], "Release");
+        assert_eq!(
+            release.cache_variables["CMAKE_EXPORT_COMPILE_COMMANDS"],
+            "TRUE"
+        );
+        assert_eq!(release.cache_variables["COMPILER"], "gcc-Ninja");
+        assert_eq!(
+            Path::new(&release.cache_variables["FROM_FILE"]),
+            root.canonicalize().unwrap().join("cmake")
+        );
+        assert_eq!(release.environment["CC"].as_deref(), Some("gcc"));
+
+        let mine = &presets[1];
+        assert_eq!(mine.binary_dir, Some(root.join("build-mine")));
+        assert_eq!(
+            mine.cmake_executable,
+            Some(PathBuf::from("/opt/cmake/bin/cmake"))
+        );
+        assert_eq!(mine.environment["CC"], None);
+        assert_eq!(mine.cache_variables["CMAKE_BUILD_TYPE"], "Release");
+    }
+}
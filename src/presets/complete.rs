@@ -0,0 +1,258 @@
+use std::path::Path;
+
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Position};
+
+use super::index::{PRESET_KINDS, visible_definitions};
+use super::json::{JsonContext, PathItem, scan};
+use crate::document::position_to_byte;
+
+const TOP_LEVEL_KEYS: &[(&str, &str)] = &[
+    ("version", "The version of the presets schema"),
+    ("cmakeMinimumRequired", "The minimum version of CMake"),
+    ("include", "The files to include"),
+    ("vendor", "The data of the IDEs and tools"),
+    ("configurePresets", "The presets to configure the project"),
+    ("buildPresets", "The presets to build the project"),
+    ("testPresets", "The presets to run the tests"),
+    ("packagePresets", "The presets to package the project"),
+    ("workflowPresets", "The presets to run several steps"),
+];
+
+/// The keys shared by all kinds of presets
+const COMMON_KEYS: &[(&str, &str)] = &[
+    ("name", "The name of the preset"),
+    ("hidden", "Hide the preset, which can only be inherited"),
+    ("inherits", "The presets to inherit from"),
+    ("displayName", "The name shown to the user"),
+    ("description", "The description shown to the user"),
+    ("environment", "The environment variables"),
+    ("condition", "The condition to enable the preset"),
+    ("vendor", "The data of the IDEs and tools"),
+];
+
+const CONFIGURE_KEYS: &[(&str, &str)] = &[
+    ("generator", "The generator of the build system"),
+    ("architecture", "The platform of the generator"),
+    ("toolset", "The toolset of the generator"),
+    ("toolchainFile", "The toolchain file"),
+    ("binaryDir", "The build directory"),
+    ("installDir", "The install directory"),
+    ("cmakeExecutable", "The cmake program"),
+    ("cacheVariables", "The cache variables"),
+    ("warnings", "The warnings to enable"),
+    ("errors", "The warnings treated as errors"),
+    ("debug", "The debug options"),
+    ("trace", "The trace options"),
+];
+
+const BUILD_KEYS: &[(&str, &str)] = &[
+    ("configurePreset", "The configure preset of the build"),
+    (
+        "inheritConfigureEnvironment",
+        "Inherit the environment of the configure preset",
+    ),
+    ("jobs", "The number of parallel jobs"),
+    ("targets", "The targets to build"),
+    ("configuration", "The configuration to build"),
+    ("cleanFirst", "Clean before building"),
+    ("verbose", "Show the build commands"),
+    ("nativeToolOptions", "The options of the native build tool"),
+];
+
+const TEST_KEYS: &[(&str, &str)] = &[
+    ("configurePreset", "The configure preset of the tests"),
+    (
+        "inheritConfigureEnvironment",
+        "Inherit the environment of the configure preset",
+    ),
+    ("configuration", "The configuration to test"),
+    ("overwriteConfigurationFile", "The options of the dashboard"),
+    ("output", "The output options"),
+    ("filter", "The tests to run"),
+    ("execution", "The execution options"),
+];
+
+const PACKAGE_KEYS: &[(&str, &str)] = &[
+    ("configurePreset", "The configure preset of the package"),
+    (
+        "inheritConfigureEnvironment",
+        "Inherit the environment of the configure preset",
+    ),
+    ("generators", "The generators of CPack"),
+    ("configurations", "The configurations to package"),
+    ("variables", "The variables of CPack"),
+    ("packageName", "The name of the package"),
+    ("packageVersion", "The version of the package"),
+    ("packageDirectory", "The output directory"),
+];
+
+const WORKFLOW_KEYS: &[(&str, &str)] = &[("steps", "The steps of the workflow")];
+
+const GENERATORS: &[&str] = &[
+    "Ninja",
+    "Ninja Multi-Config",
+    "Unix Makefiles",
+    "MinGW Makefiles",
+    "MSYS Makefiles",
+    "NMake Makefiles",
+    "NMake Makefiles JOM",
+    "Borland Makefiles",
+    "Watcom WMake",
+    "Visual Studio 17 2022",
+    "Visual Studio 16 2019",
+    "Visual Studio 15 2017",
+    "Visual Studio 14 2015",
+    "Xcode",
+    "Green Hills MULTI",
+];
+
+fn key_items(keys: &[(&str, &str)], in_string: bool) -> Vec<CompletionItem> {
+    keys.iter()
+        .map(|(key, detail)| CompletionItem {
+            label: key.to_string(),
+            kind: Some(CompletionItemKind::Property),
+            detail: Some(detail.to_string()),
+            insert_text: (!in_string).then(|| format!("\"{key}\"")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn value_items<'a>(
+    values: impl Iterator<Item = &'a str>,
+    kind: CompletionItemKind,
+    detail: &str,
+    in_string: bool,
+) -> Vec<CompletionItem> {
+    values
+        .map(|value| CompletionItem {
+            label: value.to_string(),
+            kind: Some(kind),
+            detail: Some(detail.to_string()),
+            insert_text: (!in_string).then(|| format!("\"{value}\"")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn preset_keys(kind: &str) -> Vec<(&'static str, &'static str)> {
+    let keys = match kind {
+        "configurePresets" => CONFIGURE_KEYS,
+        "buildPresets" => BUILD_KEYS,
+        "testPresets" => TEST_KEYS,
+        "packagePresets" => PACKAGE_KEYS,
+        // NOTE: the workflow presets do not share the common keys
+        _ => {
+            return [("name", "The name of the preset")]
+                .into_iter()
+                .chain(WORKFLOW_KEYS.iter().copied())
+                .collect();
+        }
+    };
+    COMMON_KEYS.iter().chain(keys).copied().collect()
+}
+
+/// The completion of the keys, the generators and the names of the presets
+pub fn getcomplete(path: &Path, text: &str, position: Position) -> Option<Vec<CompletionItem>> {
+    let (items, context) = scan(text, Some(position_to_byte(text, position)));
+    let JsonContext {
+        path: json_path,
+        is_key,
+        in_string,
+    } = context?;
+
+    let preset_names = |kind: &str| -> Vec<CompletionItem> {
+        let defs = visible_definitions(path, text, &items);
+        let names = defs
+            .iter()
+            .filter(|def| def.kind == kind)
+            .map(|def| def.name.as_str());
+        value_items(names, CompletionItemKind::Reference, kind, in_string)
+    };
+
+    let completions = match json_path.as_slice() {
+        [] if is_key => key_items(TOP_LEVEL_KEYS, in_string),
+        [PathItem::Key(kind), PathItem::Index(_)]
+            if is_key && PRESET_KINDS.contains(&kind.as_str()) =>
+        {
+            key_items(&preset_keys(kind), in_string)
+        }
+        _ if is_key => return None,
+        [
+            PathItem::Key(kind),
+            PathItem::Index(_),
+            PathItem::Key(field),
+        ]
+        | [
+            PathItem::Key(kind),
+            PathItem::Index(_),
+            PathItem::Key(field),
+            PathItem::Index(_),
+        ] if PRESET_KINDS.contains(&kind.as_str()) => match field.as_str() {
+            "inherits" => preset_names(kind),
+            "configurePreset" => preset_names("configurePresets"),
+            "generator" if kind == "configurePresets" => value_items(
+                GENERATORS.iter().copied(),
+                CompletionItemKind::EnumMember,
+                "Generator",
+                in_string,
+            ),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(completions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(items: Option<Vec<CompletionItem>>) -> Vec<String> {
+        items
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    fn complete_at_end(text: &str) -> Option<Vec<CompletionItem>> {
+        let line = text.lines().count() as u32 - 1;
+        let character = text.lines().last().unwrap().len() as u32;
+        getcomplete(
+            Path::new("/tmp/CMakePresets.json"),
+            text,
+            Position { line, character },
+        )
+    }
+
+    #[test]
+    fn test_complete_keys() {
+        let items = labels(complete_at_end("{\n  \"conf"));
+        assert!(items.contains(&"configurePresets".to_string()));
+
+        let items = labels(complete_at_end(
+            "{\n  \"configurePresets\": [\n    {\n      \"bin",
+        ));
+        assert!(items.contains(&"binaryDir".to_string()));
+        assert!(items.contains(&"inherits".to_string()));
+        assert!(!items.contains(&"targets".to_string()));
+    }
+
+    #[test]
+    fn test_complete_values() {
+        let items = labels(complete_at_end(
+            "{\n  \"configurePresets\": [\n    {\"name\": \"base\", \"hidden\": true},\n    {\"name\": \"debug\", \"generator\": \"",
+        ));
+        assert!(items.contains(&"Ninja".to_string()));
+
+        let text = "{\n  \"configurePresets\": [\n    {\"name\": \"base\"},\n    {\"name\": \"debug\", \"inherits\": [\"";
+        let items = labels(complete_at_end(text));
+        assert_eq!(items, vec!["base", "debug"]);
+
+        let text = "{\n  \"configurePresets\": [{\"name\": \"base\"}],\n  \"buildPresets\": [{\"name\": \"b\", \"configurePreset\": ";
+        let items = complete_at_end(text).unwrap();
+        assert_eq!(items[0].label, "base");
+        assert_eq!(items[0].insert_text.as_deref(), Some("\"base\""));
+    }
+}
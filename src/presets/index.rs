@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::{Position, Range};
+
+use super::json::{JsonItem, PathItem, scan};
+use super::{PRESETS_FILE, USER_PRESETS_FILE};
+use crate::document::byte_to_point;
+use crate::utils::treehelper::ToPosition;
+
+/// The kinds of presets, every kind has its own names
+pub const PRESET_KINDS: [&str; 5] = [
+    "configurePresets",
+    "buildPresets",
+    "testPresets",
+    "packagePresets",
+    "workflowPresets",
+];
+
+/// A preset defined in a presets file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresetDef {
+    pub kind: String,
+    pub name: String,
+    pub file: PathBuf,
+    /// The range of the name
+    pub range: Range,
+    pub inherits: Vec<String>,
+}
+
+pub fn is_presets_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name == PRESETS_FILE || name == USER_PRESETS_FILE)
+}
+
+pub fn byte_to_position(text: &str, byte: usize) -> Position {
+    byte_to_point(text, byte).to_position()
+}
+
+pub fn item_range(text: &str, item: &JsonItem) -> Range {
+    Range {
+        start: byte_to_position(text, item.start),
+        end: byte_to_position(text, item.end),
+    }
+}
+
+/// The kind and the index of the preset which contains the item
+pub fn preset_of(item: &JsonItem) -> Option<(&str, usize)> {
+    match item.path.as_slice() {
+        [PathItem::Key(kind), PathItem::Index(index), ..]
+            if PRESET_KINDS.contains(&kind.as_str()) =>
+        {
+            Some((kind, *index))
+        }
+        _ => None,
+    }
+}
+
+/// The field of the preset, which the item is the value of
+pub fn preset_field(item: &JsonItem) -> Option<&str> {
+    match item.path.get(2) {
+        Some(PathItem::Key(field)) if !item.is_key => Some(field),
+        _ => None,
+    }
+}
+
+/// The presets defined in the scanned document
+pub fn definitions(file: &Path, text: &str, items: &[JsonItem]) -> Vec<PresetDef> {
+    let mut defs: Vec<(String, usize, PresetDef)> = Vec::new();
+    for item in items.iter().filter(|item| item.path.len() == 3) {
+        let (Some((kind, index)), Some("name")) = (preset_of(item), preset_field(item)) else {
+            continue;
+        };
+        defs.push((
+            kind.to_string(),
+            index,
+            PresetDef {
+                kind: kind.to_string(),
+                name: item.value.clone(),
+                file: file.to_path_buf(),
+                range: item_range(text, item),
+                inherits: Vec::new(),
+            },
+        ));
+    }
+    for item in items.iter().filter(|item| item.is_value_of("inherits")) {
+        let Some((kind, index)) = preset_of(item) else {
+            continue;
+        };
+        if let Some((_, _, def)) = defs
+            .iter_mut()
+            .find(|(def_kind, def_index, _)| def_kind == kind && *def_index == index)
+        {
+            def.inherits.push(item.value.clone());
+        }
+    }
+    defs.into_iter().map(|(_, _, def)| def).collect()
+}
+
+/// The files included by the scanned document
+pub fn includes(file: &Path, items: &[JsonItem]) -> Vec<PathBuf> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    items
+        .iter()
+        .filter(|item| matches!(item.path.as_slice(), [PathItem::Key(key), PathItem::Index(_)] if key == "include"))
+        .map(|item| dir.join(&item.value))
+        .collect()
+}
+
+fn collect_file(file: &Path, defs: &mut Vec<PresetDef>, visited: &mut HashSet<PathBuf>) {
+    if !visited.insert(file.to_path_buf()) {
+        return;
+    }
+    let Ok(text) = std::fs::read_to_string(file) else {
+        return;
+    };
+    let (items, _) = scan(&text, None);
+    for include in includes(file, &items) {
+        collect_file(&include, defs, visited);
+    }
+    defs.append(&mut definitions(file, &text, &items));
+}
+
+/// The presets which the document can use: its own ones, the ones of the included files, and the
+/// ones of CMakePresets.json for CMakeUserPresets.json
+pub fn visible_definitions(file: &Path, text: &str, items: &[JsonItem]) -> Vec<PresetDef> {
+    let mut visited = HashSet::from([file.to_path_buf()]);
+    let mut defs = definitions(file, text, items);
+    for include in includes(file, items) {
+        collect_file(&include, &mut defs, &mut visited);
+    }
+    if file
+        .file_name()
+        .is_some_and(|name| name == USER_PRESETS_FILE)
+    {
+        collect_file(&file.with_file_name(PRESETS_FILE), &mut defs, &mut visited);
+    }
+    defs
+}
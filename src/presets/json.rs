@@ -0,0 +1,274 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathItem {
+    Key(String),
+    Index(usize),
+}
+
+/// A key, a string or a literal of the document, with the path of its value from the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonItem {
+    /// For a key, the path of the object which contains it
+    pub path: Vec<PathItem>,
+    /// The string without the quotes, or the literal
+    pub value: String,
+    pub is_key: bool,
+    pub is_string: bool,
+    /// The byte offset of the start, the quote included
+    pub start: usize,
+    /// The byte offset of the end, the quote included
+    pub end: usize,
+}
+
+impl JsonItem {
+    /// The item is the value of the key, directly or in an array
+    pub fn is_value_of(&self, key: &str) -> bool {
+        if self.is_key {
+            return false;
+        }
+        match self.path.as_slice() {
+            [.., PathItem::Key(last)] => last == key,
+            [.., PathItem::Key(last), PathItem::Index(_)] => last == key,
+            _ => false,
+        }
+    }
+}
+
+/// Where the cursor is in the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonContext {
+    /// The path of the value at the cursor, or of the object whose key is at the cursor
+    pub path: Vec<PathItem>,
+    pub is_key: bool,
+    /// The cursor is inside a string, so the quotes should not be inserted
+    pub in_string: bool,
+}
+
+#[derive(Debug)]
+enum Frame {
+    Object { key: Option<String>, in_value: bool },
+    Array { index: usize },
+}
+
+#[derive(Debug, Default)]
+struct Scanner {
+    stack: Vec<Frame>,
+}
+
+impl Scanner {
+    fn path(&self) -> Vec<PathItem> {
+        self.stack
+            .iter()
+            .filter_map(|frame| match frame {
+                Frame::Object {
+                    key: Some(key),
+                    in_value: true,
+                } => Some(PathItem::Key(key.clone())),
+                Frame::Object { .. } => None,
+                Frame::Array { index } => Some(PathItem::Index(*index)),
+            })
+            .collect()
+    }
+
+    fn expects_key(&self) -> bool {
+        matches!(
+            self.stack.last(),
+            Some(Frame::Object {
+                in_value: false,
+                ..
+            })
+        )
+    }
+
+    fn context(&self, in_string: bool) -> JsonContext {
+        JsonContext {
+            path: self.path(),
+            is_key: self.expects_key(),
+            in_string,
+        }
+    }
+}
+
+/// The end of a string starting at the quote, and whether it is closed before the line ends
+fn string_end(bytes: &[u8], start: usize) -> (usize, bool) {
+    let mut index = start + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return (index + 1, true),
+            b'\n' => return (index, false),
+            _ => index += 1,
+        }
+    }
+    (bytes.len(), false)
+}
+
+fn unescape(raw: &str) -> String {
+    serde_json::from_str(&format!("\"{raw}\"")).unwrap_or_else(|_| raw.to_string())
+}
+
+/// Scan the strings and the literals of a JSON document, and find the context of the cursor when
+/// it is given. The scan does not stop at the syntax errors, so the documents being edited can be
+/// completed
+pub fn scan(text: &str, cursor: Option<usize>) -> (Vec<JsonItem>, Option<JsonContext>) {
+    let bytes = text.as_bytes();
+    let mut scanner = Scanner::default();
+    let mut items = Vec::new();
+    let mut context = None;
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        if byte.is_ascii_whitespace() {
+            index += 1;
+            continue;
+        }
+        if context.is_none() && cursor.is_some_and(|cursor| cursor <= index) {
+            context = Some(scanner.context(false));
+        }
+        match byte {
+            b'{' => scanner.stack.push(Frame::Object {
+                key: None,
+                in_value: false,
+            }),
+            b'[' => scanner.stack.push(Frame::Array { index: 0 }),
+            b'}' | b']' => {
+                scanner.stack.pop();
+            }
+            b':' => {
+                if let Some(Frame::Object { in_value, .. }) = scanner.stack.last_mut() {
+                    *in_value = true;
+                }
+            }
+            b',' => match scanner.stack.last_mut() {
+                Some(Frame::Object { key, in_value }) => {
+                    *key = None;
+                    *in_value = false;
+                }
+                Some(Frame::Array { index }) => *index += 1,
+                None => {}
+            },
+            b'"' => {
+                let (end, closed) = string_end(bytes, index);
+                let content_end = if closed { end - 1 } else { end };
+                let value = unescape(&text[index + 1..content_end]);
+                if context.is_none()
+                    && cursor.is_some_and(|cursor| index < cursor && (cursor < end || !closed))
+                {
+                    context = Some(scanner.context(true));
+                }
+                let is_key = scanner.expects_key();
+                items.push(JsonItem {
+                    path: scanner.path(),
+                    value: value.clone(),
+                    is_key,
+                    is_string: true,
+                    start: index,
+                    end,
+                });
+                if is_key && let Some(Frame::Object { key, .. }) = scanner.stack.last_mut() {
+                    *key = Some(value);
+                }
+                index = end;
+                continue;
+            }
+            _ => {
+                let length = bytes[index..]
+                    .iter()
+                    .position(|byte| !(byte.is_ascii_alphanumeric() || b"+-.".contains(byte)))
+                    .unwrap_or(bytes.len() - index);
+                // NOTE: skip the unexpected character, which can be longer than a byte
+                let length = if length == 0 {
+                    text[index..].chars().next().map_or(1, char::len_utf8)
+                } else {
+                    length
+                };
+                let end = index + length;
+                if context.is_none() && cursor.is_some_and(|cursor| cursor <= end) {
+                    context = Some(scanner.context(false));
+                }
+                items.push(JsonItem {
+                    path: scanner.path(),
+                    value: text[index..end].to_string(),
+                    is_key: false,
+                    is_string: false,
+                    start: index,
+                    end,
+                });
+                index = end;
+                continue;
+            }
+        }
+        index += 1;
+    }
+    if context.is_none() && cursor.is_some() {
+        context = Some(scanner.context(false));
+    }
+    (items, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> PathItem {
+        PathItem::Key(key.to_string())
+    }
+
+    #[test]
+    fn test_scan_items() {
+        let text =
+            r#"{"version": 6, "configurePresets": [{"name": "a\"b", "inherits": ["base"]}]}"#;
+        let (items, _) = scan(text, None);
+        let version = items.iter().find(|item| item.value == "6").unwrap();
+        assert_eq!(version.path, vec![key("version")]);
+        assert!(!version.is_string);
+
+        let name = items
+            .iter()
+            .find(|item| item.path.ends_with(&[key("name")]))
+            .unwrap();
+        assert_eq!(name.value, "a\"b");
+        assert_eq!(&text[name.start..name.end], r#""a\"b""#);
+
+        let inherits = items.iter().find(|item| item.value == "base").unwrap();
+        assert!(inherits.is_value_of("inherits"));
+        assert_eq!(
+            inherits.path,
+            vec![
+                key("configurePresets"),
+                PathItem::Index(0),
+                key("inherits"),
+                PathItem::Index(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_context() {
+        let text = "{\n  \"configurePresets\": [\n    {},\n    {\"name\": \"b\", \"gen\n";
+        let (_, context) = scan(text, Some(text.len() - 1));
+        assert_eq!(
+            context,
+            Some(JsonContext {
+                path: vec![key("configurePresets"), PathItem::Index(1)],
+                is_key: true,
+                in_string: true,
+            })
+        );
+
+        let text = r#"{"configurePresets": [{"inherits": ["#;
+        let (_, context) = scan(text, Some(text.len()));
+        assert_eq!(
+            context,
+            Some(JsonContext {
+                path: vec![
+                    key("configurePresets"),
+                    PathItem::Index(0),
+                    key("inherits"),
+                    PathItem::Index(0)
+                ],
+                is_key: false,
+                in_string: false,
+            })
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use tower_lsp::lsp_types::{Location, Position, Uri};
+
+use super::index::{preset_field, preset_of, visible_definitions};
+use super::json::scan;
+use crate::document::position_to_byte;
+
+/// Jump to the presets named by `inherits` or `configurePreset`
+pub fn godef(path: &Path, text: &str, position: Position) -> Option<Vec<Location>> {
+    let cursor = position_to_byte(text, position);
+    let (items, _) = scan(text, None);
+    let item = items
+        .iter()
+        .find(|item| item.is_string && item.start < cursor && cursor < item.end)?;
+    let (kind, _) = preset_of(item)?;
+    let kind = match preset_field(item)? {
+        "inherits" if item.is_value_of("inherits") => kind,
+        "configurePreset" if item.path.len() == 3 => "configurePresets",
+        _ => return None,
+    };
+    let locations: Vec<Location> = visible_definitions(path, text, &items)
+        .into_iter()
+        .filter(|def| def.kind == kind && def.name == item.value)
+        .filter_map(|def| {
+            Some(Location {
+                uri: Uri::from_file_path(&def.file).ok()?,
+                range: def.range,
+            })
+        })
+        .collect();
+    (!locations.is_empty()).then_some(locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_godef() {
+        let text = r#"{
+  "configurePresets": [
+    { "name": "base", "hidden": true },
+    { "name": "debug", "inherits": "base" }
+  ],
+  "buildPresets": [
+    { "name": "base", "configurePreset": "debug" }
+  ]
+}"#;
+        let path = Path::new("/tmp/CMakePresets.json");
+        let locations = godef(
+            path,
+            text,
+            Position {
+                line: 3,
+                character: 37,
+            },
+        )
+        .unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position::new(2, 14));
+
+        let locations = godef(
+            path,
+            text,
+            Position {
+                line: 6,
+                character: 45,
+            },
+        )
+        .unwrap();
+        assert_eq!(locations[0].range.start, Position::new(3, 14));
+
+        assert!(
+            godef(
+                path,
+                text,
+                Position {
+                    line: 2,
+                    character: 16,
+                },
+            )
+            .is_none()
+        );
+    }
+}
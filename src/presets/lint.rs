@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+use super::index::{PresetDef, item_range, preset_field, preset_of, visible_definitions};
+use super::json::{JsonItem, PathItem, scan};
+
+/// The versions of the presets schema which CMake supports
+const SUPPORTED_VERSIONS: std::ops::RangeInclusive<u32> = 1..=10;
+
+/// The keys of the root which need a newer schema
+const TOP_LEVEL_VERSIONS: &[(&str, u32)] = &[
+    ("testPresets", 2),
+    ("include", 4),
+    ("packagePresets", 6),
+    ("workflowPresets", 6),
+    ("$schema", 8),
+];
+
+/// The keys of the presets which need a newer schema
+const PRESET_VERSIONS: &[(&str, u32)] = &[
+    ("installDir", 3),
+    ("toolchainFile", 3),
+    ("condition", 3),
+    ("resolvePackageReferences", 4),
+    ("testOutputTruncation", 5),
+    ("trace", 7),
+];
+
+fn diagnostic(range: Range, message: String, severity: DiagnosticSeverity) -> Diagnostic {
+    Diagnostic {
+        range,
+        message: message.into(),
+        severity: Some(severity),
+        code: None,
+        code_description: None,
+        source: None,
+        related_information: None,
+        tags: None,
+        data: None,
+    }
+}
+
+fn check_version(text: &str, items: &[JsonItem], diagnostics: &mut Vec<Diagnostic>) {
+    let Some(version_item) = items
+        .iter()
+        .find(|item| item.path == [PathItem::Key("version".to_string())])
+    else {
+        diagnostics.push(diagnostic(
+            Range::default(),
+            "the version of the presets schema is required".to_string(),
+            DiagnosticSeverity::Error,
+        ));
+        return;
+    };
+    let range = item_range(text, version_item);
+    let Some(version) = version_item
+        .value
+        .parse::<u32>()
+        .ok()
+        .filter(|_| !version_item.is_string)
+    else {
+        diagnostics.push(diagnostic(
+            range,
+            "the version should be an integer".to_string(),
+            DiagnosticSeverity::Error,
+        ));
+        return;
+    };
+    if !SUPPORTED_VERSIONS.contains(&version) {
+        diagnostics.push(diagnostic(
+            range,
+            format!("the version {version} is not supported by CMake"),
+            DiagnosticSeverity::Error,
+        ));
+        return;
+    }
+    for item in items.iter().filter(|item| item.is_key) {
+        let required = match item.path.as_slice() {
+            [] => TOP_LEVEL_VERSIONS,
+            [_, PathItem::Index(_)] if preset_of(item).is_some() => PRESET_VERSIONS,
+            _ => continue,
+        };
+        if let Some((key, required)) = required
+            .iter()
+            .find(|(key, required)| *key == item.value && version < *required)
+        {
+            diagnostics.push(diagnostic(
+                item_range(text, item),
+                format!("{key} requires the version {required} of the presets schema"),
+                DiagnosticSeverity::Error,
+            ));
+        }
+    }
+}
+
+/// The presets reached by following the inheritance of the preset
+fn inherits_itself(def: &PresetDef, parents: &HashMap<(&str, &str), Vec<&str>>) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack: Vec<&str> = def.inherits.iter().map(String::as_str).collect();
+    while let Some(name) = stack.pop() {
+        if name == def.name {
+            return true;
+        }
+        if !visited.insert(name) {
+            continue;
+        }
+        if let Some(grandparents) = parents.get(&(def.kind.as_str(), name)) {
+            stack.extend(grandparents);
+        }
+    }
+    false
+}
+
+fn check_presets(path: &Path, text: &str, items: &[JsonItem], diagnostics: &mut Vec<Diagnostic>) {
+    let defs = visible_definitions(path, text, items);
+    let mut counts: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut parents: HashMap<(&str, &str), Vec<&str>> = HashMap::new();
+    for def in &defs {
+        *counts.entry((&def.kind, &def.name)).or_default() += 1;
+        parents
+            .entry((&def.kind, &def.name))
+            .or_default()
+            .extend(def.inherits.iter().map(String::as_str));
+    }
+
+    for def in defs.iter().filter(|def| def.file == path) {
+        if counts[&(def.kind.as_str(), def.name.as_str())] > 1 {
+            diagnostics.push(diagnostic(
+                def.range,
+                format!("the preset {} is defined more than once", def.name),
+                DiagnosticSeverity::Error,
+            ));
+        }
+        if inherits_itself(def, &parents) {
+            diagnostics.push(diagnostic(
+                def.range,
+                format!("the inheritance of the preset {} is a cycle", def.name),
+                DiagnosticSeverity::Error,
+            ));
+        }
+    }
+
+    for item in items.iter().filter(|item| item.is_string) {
+        let (Some((kind, _)), Some(field)) = (preset_of(item), preset_field(item)) else {
+            continue;
+        };
+        let kind = match field {
+            "inherits" if item.is_value_of("inherits") => kind,
+            "configurePreset" if item.path.len() == 3 => "configurePresets",
+            _ => continue,
+        };
+        if !counts.contains_key(&(kind, item.value.as_str())) {
+            diagnostics.push(diagnostic(
+                item_range(text, item),
+                format!("unknown preset {}", item.value),
+                DiagnosticSeverity::Error,
+            ));
+        }
+    }
+}
+
+/// Check the syntax, the schema version and the references of a presets file
+pub fn check_presets_file(path: &Path, text: &str) -> Vec<Diagnostic> {
+    if let Err(e) = serde_json::from_str::<serde_json::Value>(text) {
+        let position = Position {
+            line: e.line().saturating_sub(1) as u32,
+            character: e.column().saturating_sub(1) as u32,
+        };
+        return vec![diagnostic(
+            Range {
+                start: position,
+                end: position,
+            },
+            e.to_string(),
+            DiagnosticSeverity::Error,
+        )];
+    }
+    let (items, _) = scan(text, None);
+    let mut diagnostics = Vec::new();
+    check_version(text, &items, &mut diagnostics);
+    check_presets(path, text, &items, &mut diagnostics);
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Message;
+
+    use super::*;
+
+    fn messages(text: &str) -> Vec<String> {
+        check_presets_file(Path::new("/tmp/none/CMakePresets.json"), text)
+            .into_iter()
+            .filter_map(|diagnostic| match diagnostic.message {
+                Message::String(message) => Some(message),
+                Message::MarkupContent(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_check_presets() {
+        let text = r#"{
+  "version": 3,
+  "include": ["other.json"],
+  "configurePresets": [
+    { "name": "base", "hidden": true },
+    { "name": "a", "inherits": ["b", "base"] },
+    { "name": "b", "inherits": "a" },
+    { "name": "base" },
+    { "name": "c", "inherits": "missing" }
+  ],
+  "buildPresets": [
+    { "name": "a", "configurePreset": "nothing" }
+  ]
+}"#;
+        assert_eq!(
+            messages(text),
+            vec![
+                "include requires the version 4 of the presets schema",
+                "the preset base is defined more than once",
+                "the inheritance of the preset a is a cycle",
+                "the inheritance of the preset b is a cycle",
+                "the preset base is defined more than once",
+                "unknown preset missing",
+                "unknown preset nothing",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_version() {
+        assert_eq!(
+            messages(r#"{"configurePresets": []}"#),
+            vec!["the version of the presets schema is required"]
+        );
+        assert_eq!(
+            messages(r#"{"version": 42}"#),
+            vec!["the version 42 is not supported by CMake"]
+        );
+        assert_eq!(messages(r#"{"version": 10}"#), Vec::<String>::new());
+        assert_eq!(messages("{\"version\": 10,}").len(), 1);
+    }
+}
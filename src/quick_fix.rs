@@ -1,7 +1,7 @@
+use crate::config::CommandCase;
 use crate::grammar::ErrorType;
 use crate::utils::query::try_get_argument_list;
 use crate::utils::treehelper::ToPosition;
-use crate::{config::CommandCase, consts::TREESITTER_CMAKE_LANGUAGE};
 use tower_lsp::lsp_types::{
     CodeAction, CodeActionKind, CodeActionResponse, Diagnostic, DocumentChange, Edit,
     OptionalVersionedTextDocumentIdentifier, Range, TextDocumentEdit, TextEdit, WorkspaceEdit,
@@ -9,15 +9,12 @@ use tower_lsp::lsp_types::{
 
 pub fn lint_fix_action(
     context: &str,
+    root: tree_sitter::Node<'_>,
     diagnosticses: &[&Diagnostic],
     uri: tower_lsp::lsp_types::Uri,
 ) -> Option<Vec<CodeActionResponse>> {
     let mut responses = vec![];
 
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(context, None)?;
-    let root = tree.root_node();
     for diagnostic in diagnosticses {
         let error_type: ErrorType =
             serde_json::from_value(diagnostic.data.as_ref().unwrap().clone()).unwrap();
@@ -31,6 +28,9 @@ pub fn lint_fix_action(
             ErrorType::UpLowerCase { command_case, name } => {
                 responses.push(fix_uplowercase(diagnostic, command_case, &name, &uri));
             }
+            ErrorType::BlockMismatch { expected } => {
+                responses.push(fix_block_mismatch(diagnostic, expected, &uri));
+            }
             _ => {
                 continue;
             }
@@ -75,6 +75,40 @@ fn fix_uplowercase(
         tags: None,
     })
 }
+fn fix_block_mismatch(
+    diagnostic: &Diagnostic,
+    expected: String,
+    uri: &tower_lsp::lsp_types::Uri,
+) -> CodeActionResponse {
+    let range = diagnostic.range;
+    CodeActionResponse::CodeAction(CodeAction {
+        title: "block mismatch fix".to_string(),
+        kind: Some(CodeActionKind::QuickFix),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: None,
+            change_annotations: None,
+            document_changes: Some(vec![DocumentChange::TextDocumentEdit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    version: None,
+                    text_document_identifier: tower_lsp::lsp_types::TextDocumentIdentifier {
+                        uri: uri.clone(),
+                    },
+                },
+                edits: vec![Edit::TextEdit(TextEdit {
+                    range,
+                    new_text: expected,
+                })],
+            })]),
+        }),
+        command: None,
+        is_preferred: Some(true),
+        disabled: None,
+        data: None,
+        tags: None,
+    })
+}
+
 fn fix_too_long(
     input: tree_sitter::Node,
     source: &str,
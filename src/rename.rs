@@ -1,42 +1,326 @@
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::LazyLock;
 
-use tower_lsp::lsp_types::{Location, Position, TextEdit, WorkspaceEdit};
+use tower_lsp::jsonrpc::{Error as LspError, Result};
+use tower_lsp::lsp_types::{Location, Position, PrepareRenameResult, TextEdit, WorkspaceEdit};
 
+use crate::complete::builtin::{is_builtin_command, is_builtin_variable};
+use crate::document::Document;
+use crate::jump::{ProjectSymbol, ProjectSymbolKind, ReferenceInfo};
+use crate::utils::gen_module_pattern;
+use crate::utils::query::ToLspRange;
+use crate::utils::treehelper::{CurrentNodeInfo, PositionType, ToPoint};
 use crate::{Backend, jump};
 
+/// Commands are made of letters, digits and underscores, and do not start with a digit
+static COMMAND_NAME_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap());
+
+/// The chars a variable can have to be referenced as `${name}`
+static VARIABLE_NAME_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^[A-Za-z0-9/_.+\-]+$").unwrap());
+
+/// The names of the targets, with the namespaces of the aliases
+static TARGET_NAME_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^[A-Za-z0-9_.+\-]+(::[A-Za-z0-9_.+\-]+)*$").unwrap());
+
+/// The symbol under the cursor, which can be renamed
+struct RenameTarget {
+    range: tower_lsp::lsp_types::Range,
+    definition: ReferenceInfo,
+}
+
+/// The commands or the variables provided by CMake, a variable can be named like a command
+fn is_builtin(name: &str, is_function: bool) -> bool {
+    if is_function {
+        is_builtin_command(name)
+    } else {
+        is_builtin_variable(name)
+    }
+}
+
+fn is_system_module(path: &Path) -> bool {
+    gen_module_pattern("*")
+        .and_then(|pattern| glob::Pattern::new(&pattern).ok())
+        .is_some_and(|pattern| pattern.matches_path(path))
+}
+
+fn is_readonly(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly())
+}
+
+fn is_valid_name(name: &str, is_function: bool) -> bool {
+    if is_function {
+        COMMAND_NAME_REGEX.is_match(name)
+    } else {
+        VARIABLE_NAME_REGEX.is_match(name)
+    }
+}
+
+/// Refuse the edits in the files which cannot be written
+fn check_locations(locations: &[Location]) -> Result<()> {
+    for Location { uri, .. } in locations {
+        if let Ok(path) = uri.to_file_path()
+            && is_readonly(&path)
+        {
+            return Err(LspError::invalid_params(format!(
+                "Cannot rename in the read-only file {}",
+                path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn workspace_edit(locations: Vec<Location>, edited: &str) -> WorkspaceEdit {
+    let mut changes = HashMap::new();
+    for Location { uri, range } in locations {
+        let edit = TextEdit::new(range, edited.to_string());
+        changes.entry(uri).or_insert_with(Vec::new).push(edit);
+    }
+    WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    }
+}
+
+/// Refuse the definitions which are not owned by the project
+fn check_definition(name: &str, definition: &ReferenceInfo) -> Result<()> {
+    let Ok(path) = definition.loc.uri.to_file_path() else {
+        return Err(LspError::invalid_params(format!(
+            "{name} is not defined in a local file"
+        )));
+    };
+    if is_system_module(&path) {
+        return Err(LspError::invalid_params(format!(
+            "{name} is defined in the system module {}",
+            path.display()
+        )));
+    }
+    if is_readonly(&path) {
+        return Err(LspError::invalid_params(format!(
+            "{name} is defined in the read-only file {}",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
 impl Backend {
+    /// The target or the cache option under the cursor, with all its uses in the project
+    async fn rename_project_symbol(
+        &self,
+        location: Position,
+        originuri: &Path,
+        document: &Document,
+    ) -> Result<Option<ProjectSymbol>> {
+        let Some(symbol) = jump::project_symbol(
+            document,
+            originuri,
+            location,
+            true,
+            &[ProjectSymbolKind::Target, ProjectSymbolKind::CacheOption],
+            &self.documents,
+        )
+        .await
+        else {
+            return Ok(None);
+        };
+        if symbol.kind == ProjectSymbolKind::CacheOption && is_builtin_variable(&symbol.name) {
+            return Err(LspError::invalid_params(format!(
+                "{} is provided by CMake and cannot be renamed",
+                symbol.name
+            )));
+        }
+        check_locations(&symbol.locations)?;
+        Ok(Some(symbol))
+    }
+
+    async fn rename_target<P: AsRef<Path>>(
+        &self,
+        location: Position,
+        originuri: P,
+        document: &Document,
+    ) -> Result<RenameTarget> {
+        let node_info = CurrentNodeInfo::get(
+            document.text(),
+            document.tree().root_node(),
+            location.to_point(),
+        );
+        let (Some(name), Some(node)) = (node_info.content(), node_info.node()) else {
+            return Err(LspError::invalid_params("No symbol to rename here"));
+        };
+        if !matches!(
+            node_info.pos_type(),
+            PositionType::VarOrFun | PositionType::FunOrMacroIdentifier
+        ) || node.utf8_text(document.text().as_bytes()) != Ok(name)
+        {
+            return Err(LspError::invalid_params(format!(
+                "{name} cannot be renamed"
+            )));
+        }
+        // NOTE: the cache entries and the variables of the environment are not defined in the
+        // project
+        let Some(definition) = jump::get_cached_def(originuri, name, &self.documents).await else {
+            if is_builtin(name, true) || is_builtin(name, false) {
+                return Err(LspError::invalid_params(format!(
+                    "{name} is provided by CMake and cannot be renamed"
+                )));
+            }
+            return Err(LspError::invalid_params(format!(
+                "Cannot find the definition of {name} in the project"
+            )));
+        };
+        if is_builtin(name, definition.is_function) {
+            return Err(LspError::invalid_params(format!(
+                "{name} is provided by CMake and cannot be renamed"
+            )));
+        }
+        check_definition(name, &definition)?;
+        Ok(RenameTarget {
+            range: node.range().lsp_range(),
+            definition,
+        })
+    }
+
+    pub async fn prepare_rename_symbol<P: AsRef<Path>>(
+        &self,
+        location: Position,
+        originuri: P,
+        document: &Document,
+    ) -> Result<Option<PrepareRenameResult>> {
+        if let Some(symbol) = self
+            .rename_project_symbol(location, originuri.as_ref(), document)
+            .await?
+        {
+            return Ok(Some(PrepareRenameResult::Range(symbol.range)));
+        }
+        let target = self.rename_target(location, originuri, document).await?;
+        Ok(Some(PrepareRenameResult::Range(target.range)))
+    }
+
     pub async fn rename_symbol<P: AsRef<Path>>(
         &self,
         edited: &str,
         location: Position,
         originuri: P,
         client: &tower_lsp::Client,
-        source: &str,
-    ) -> Option<WorkspaceEdit> {
-        let definitions = jump::godef(
+        document: &Document,
+    ) -> Result<Option<WorkspaceEdit>> {
+        if let Some(symbol) = self
+            .rename_project_symbol(location, originuri.as_ref(), document)
+            .await?
+        {
+            let valid = match symbol.kind {
+                ProjectSymbolKind::Target => TARGET_NAME_REGEX.is_match(edited),
+                ProjectSymbolKind::CacheOption => {
+                    is_valid_name(edited, false) && !is_builtin_variable(edited)
+                }
+            };
+            if !valid {
+                return Err(LspError::invalid_params(format!(
+                    "{edited} is not a valid name for {}",
+                    symbol.name
+                )));
+            }
+            // NOTE: CMake only allows `::` in the names of the ALIAS and IMPORTED targets
+            if symbol.kind == ProjectSymbolKind::Target
+                && edited.contains("::")
+                && !jump::target_allows_namespace(&symbol.locations, &self.documents).await
+            {
+                return Err(LspError::invalid_params(format!(
+                    "{edited} has a namespace, which only ALIAS and IMPORTED targets can have"
+                )));
+            }
+            return Ok(Some(workspace_edit(symbol.locations, edited)));
+        }
+        let target = self
+            .rename_target(location, originuri.as_ref(), document)
+            .await?;
+        if !is_valid_name(edited, target.definition.is_function) {
+            return Err(LspError::invalid_params(format!(
+                "{edited} is not a valid CMake identifier"
+            )));
+        }
+        if is_builtin(edited, target.definition.is_function) {
+            return Err(LspError::invalid_params(format!(
+                "{edited} is reserved by CMake"
+            )));
+        }
+        let Some(definitions) = jump::godef(
             location,
-            source,
+            document,
             originuri,
             client,
             false,
             true,
             &self.documents,
         )
-        .await?;
-
-        let changes =
-            definitions
-                .into_iter()
-                .fold(HashMap::new(), |mut map, Location { uri, range }| {
-                    let edit = TextEdit::new(range, edited.to_string());
-                    map.entry(uri).or_insert_with(Vec::new).push(edit);
-                    map
-                });
-
-        Some(WorkspaceEdit {
-            changes: Some(changes),
-            ..Default::default()
-        })
+        .await
+        else {
+            return Ok(None);
+        };
+
+        check_locations(&definitions)?;
+        Ok(Some(workspace_edit(definitions, edited)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_name() {
+        assert!(is_valid_name("my_function2", true));
+        assert!(!is_valid_name("2function", true));
+        assert!(!is_valid_name("my-function", true));
+        assert!(is_valid_name("PROJECT_1.0/path+dir-name", false));
+        assert!(!is_valid_name("has space", false));
+        assert!(!is_valid_name("${OTHER}", false));
+        assert!(!is_valid_name("", false));
+    }
+
+    #[test]
+    fn test_target_name() {
+        assert!(TARGET_NAME_REGEX.is_match("my_lib-2.0"));
+        assert!(TARGET_NAME_REGEX.is_match("Project::core"));
+        assert!(!TARGET_NAME_REGEX.is_match("my lib"));
+        assert!(!TARGET_NAME_REGEX.is_match("::core"));
+        assert!(!TARGET_NAME_REGEX.is_match("${NAME}"));
+    }
+
+    #[test]
+    fn test_builtin() {
+        assert!(is_builtin("CMAKE_CXX_STANDARD", false));
+        assert!(!is_builtin("cmake_custom_var", false));
+        assert!(!is_builtin("MY_VARIABLE", false));
+        assert!(!is_builtin("file", false));
+        assert!(!is_builtin("list", false));
+    }
+
+    #[test]
+    fn test_readonly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CMakeLists.txt");
+        std::fs::write(&path, "set(A 1)\n").unwrap();
+        assert!(!is_readonly(&path));
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        assert!(is_readonly(&path));
+    }
+
+    #[cfg(unix)]
+    #[cfg(not(target_os = "android"))]
+    #[test]
+    fn test_system_module() {
+        assert!(is_system_module(Path::new(
+            "/usr/share/cmake-3.31/Modules/GNUInstallDirs.cmake"
+        )));
+        assert!(!is_system_module(Path::new(
+            "/home/user/project/cmake/Helper.cmake"
+        )));
     }
 }
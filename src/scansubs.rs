@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
@@ -8,7 +8,8 @@ use tokio::sync::Mutex;
 
 use crate::complete::{COMPLETE_CACHE, CompleteKV};
 use crate::consts::TREESITTER_CMAKE_LANGUAGE;
-use crate::jump::{JUMP_CACHE, JumpKV};
+use crate::document::Document;
+use crate::jump::{JUMP_CACHE, JumpCacheUnit, JumpKV};
 use crate::utils::query::get_normal_commands;
 use crate::utils::{
     CachedData, CachedPCompleteItems, CachedPJumpItems, CachedProjectCMakeMap, CachedProjectTree,
@@ -32,6 +33,11 @@ pub static TREE_MAP: LazyLock<Arc<Mutex<TreeKey>>> =
 pub static TREE_CMAKE_MAP: LazyLock<Arc<Mutex<TreeCMakeKey>>> =
     LazyLock::new(|| Arc::new(Mutex::new(HashMap::new())));
 
+// NOTE: record the top CMakeLists of the scanned projects
+// They are not in the maps when they have no subdirectory or included file
+pub static ROOT_CMAKES: LazyLock<Arc<Mutex<HashSet<PathBuf>>>> =
+    LazyLock::new(|| Arc::new(Mutex::new(HashSet::new())));
+
 struct CacheLoader {
     tree_map: TreeKey,
     tree_cmake_map: TreeCMakeKey,
@@ -66,38 +72,88 @@ const GITIGNORE_CONTENT: &str = r"# This file is autogenerated by neocmakelsp.
 *";
 
 pub async fn cache_project_data<P: AsRef<Path>>(project_root: P) -> Option<()> {
-    let cache_dir = project_root.as_ref().join(".cache").join("neocmakelsp");
+    let project_root = project_root.as_ref();
+    let cache_dir = project_root.join(".cache").join("neocmakelsp");
     std::fs::create_dir_all(&cache_dir).ok()?;
     // NOTE: add a .gitignore to auto ignore this folder
     let gitignore_file = cache_dir.join(".gitignore");
     std::fs::write(gitignore_file, GITIGNORE_CONTENT).ok()?;
+    // NOTE: the other workspace folders keep their data in their own cache
     let tree = TREE_MAP.lock().await;
-    cache_data_write(&cache_dir, tree.clone(), cache::project::TREE_MAP_CACHE);
+    let project_tree: TreeKey = tree
+        .iter()
+        .filter(|(child, _)| child.starts_with(project_root))
+        .map(|(child, parent)| (child.clone(), parent.clone()))
+        .collect();
     drop(tree);
+    cache_data_write(&cache_dir, project_tree, cache::project::TREE_MAP_CACHE);
     let tree_cmake = TREE_CMAKE_MAP.lock().await;
+    let project_tree_cmake: TreeCMakeKey = tree_cmake
+        .iter()
+        .filter(|(cmake, _)| cmake.starts_with(project_root))
+        .map(|(cmake, users)| (cmake.clone(), users.clone()))
+        .collect();
+    drop(tree_cmake);
     cache_data_write(
         &cache_dir,
-        tree_cmake.clone(),
+        project_tree_cmake,
         cache::project::TREE_CMAKE_MAP_CACHE,
     );
-    drop(tree_cmake);
     let complete_cache = COMPLETE_CACHE.lock().await;
+    let project_complete: CompleteKV = complete_cache
+        .iter()
+        .filter(|(path, _)| path.starts_with(project_root))
+        .map(|(path, items)| (path.clone(), items.clone()))
+        .collect();
+    drop(complete_cache);
     cache_data_write(
         &cache_dir,
-        complete_cache.clone(),
+        project_complete,
         cache::project::COMPLETIONS_CACHE,
     );
-    drop(complete_cache);
     let jump_cache = JUMP_CACHE.lock().await;
-    cache_data_write(
-        &cache_dir,
-        jump_cache.clone(),
-        cache::project::JUMPITEMS_CACHE,
-    );
+    let project_jump: JumpKV = jump_cache
+        .iter()
+        .filter(|(_, unit)| in_project(unit, project_root))
+        .map(|(key, unit)| (key.clone(), unit.clone()))
+        .collect();
     drop(jump_cache);
+    cache_data_write(&cache_dir, project_jump, cache::project::JUMPITEMS_CACHE);
     Some(())
 }
 
+fn in_project(unit: &JumpCacheUnit, project_root: &Path) -> bool {
+    unit.location
+        .uri
+        .to_file_path()
+        .is_ok_and(|path| path.starts_with(project_root))
+}
+
+/// Forget the files of a project, when its folder is removed from the workspace
+pub async fn forget_project<P: AsRef<Path>>(project_root: P) {
+    let project_root = project_root.as_ref();
+    ROOT_CMAKES
+        .lock()
+        .await
+        .retain(|cmake| !cmake.starts_with(project_root));
+    TREE_MAP
+        .lock()
+        .await
+        .retain(|child, _| !child.starts_with(project_root));
+    TREE_CMAKE_MAP
+        .lock()
+        .await
+        .retain(|cmake, _| !cmake.starts_with(project_root));
+    COMPLETE_CACHE
+        .lock()
+        .await
+        .retain(|path, _| !path.starts_with(project_root));
+    JUMP_CACHE
+        .lock()
+        .await
+        .retain(|_, unit| !in_project(unit, project_root));
+}
+
 /// This function is to load cache data from json files
 fn load_cache_all<P: AsRef<Path>>(project_root: P) -> Option<CacheLoader> {
     let cache_dir = project_root.as_ref().join(".cache").join("neocmakelsp");
@@ -122,6 +178,10 @@ fn load_cache_all<P: AsRef<Path>>(project_root: P) -> Option<CacheLoader> {
 
 /// Scan all files. If there is a cache file or it is the first time to scan dir
 pub async fn scan_all<P: AsRef<Path>>(project_root: P, is_first: bool) {
+    let root_cmake = project_root.as_ref().join("CMakeLists.txt");
+    if root_cmake.is_file() {
+        ROOT_CMAKES.lock().await.insert(root_cmake.clone());
+    }
     if is_first
         && let Some(CacheLoader {
             tree_map,
@@ -130,21 +190,21 @@ pub async fn scan_all<P: AsRef<Path>>(project_root: P, is_first: bool) {
             jump_cache,
         }) = load_cache_all(project_root.as_ref())
     {
+        // NOTE: extend the data, the other workspace folders may have been loaded before
         let mut tree = TREE_MAP.lock().await;
-        *tree = tree_map;
+        tree.extend(tree_map);
         drop(tree);
         let mut tree_cmake = TREE_CMAKE_MAP.lock().await;
-        *tree_cmake = tree_cmake_map;
+        tree_cmake.extend(tree_cmake_map);
         drop(tree_cmake);
         let mut toload_complete_cache = COMPLETE_CACHE.lock().await;
-        *toload_complete_cache = complete_cache;
+        toload_complete_cache.extend(complete_cache);
         drop(toload_complete_cache);
         let mut toload_jump_cache = JUMP_CACHE.lock().await;
-        *toload_jump_cache = jump_cache;
+        toload_jump_cache.extend(jump_cache);
         drop(toload_jump_cache);
         return;
     }
-    let root_cmake = project_root.as_ref().join("CMakeLists.txt");
     let mut to_scan: Vec<PathBuf> = vec![root_cmake];
     while !to_scan.is_empty() {
         let mut next_to_scan = Vec::new();
@@ -156,6 +216,26 @@ pub async fn scan_all<P: AsRef<Path>>(project_root: P, is_first: bool) {
     }
 }
 
+/// All the files found by the scan, the CMakeLists and the included *.cmake files
+pub async fn scanned_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = ROOT_CMAKES.lock().await.iter().cloned().collect();
+    let tree = TREE_MAP.lock().await;
+    for (child, parent) in tree.iter() {
+        files.push(child.clone());
+        files.push(parent.clone());
+    }
+    drop(tree);
+    let tree_cmake = TREE_CMAKE_MAP.lock().await;
+    for (cmake, parents) in tree_cmake.iter() {
+        files.push(cmake.clone());
+        files.extend(parents.iter().cloned());
+    }
+    drop(tree_cmake);
+    files.sort();
+    files.dedup();
+    files
+}
+
 pub async fn scan_dir<P: AsRef<Path>>(path: P, is_first: bool) -> Vec<PathBuf> {
     let (bufs, cmakebufs) = scan_dir_inner(path.as_ref(), is_first).await;
     let mut tree = TREE_MAP.lock().await;
@@ -182,19 +262,18 @@ async fn scan_dir_inner<P: AsRef<Path>>(path: P, is_first: bool) -> (Vec<PathBuf
         return (Vec::new(), Vec::new());
     };
 
+    let document = Document::new(source);
     if is_first {
-        complete::update_cache(path.as_ref(), &source).await;
-        jump::update_cache(path.as_ref(), &source).await;
+        complete::update_cache(path.as_ref(), &document).await;
+        jump::update_cache(path.as_ref(), &document).await;
     }
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let tree = parse.parse(&source, None).unwrap();
-    let tree = tree.root_node();
+    let source = document.text();
+    let tree = document.tree().root_node();
     if tree.is_error() {
         return (Vec::new(), Vec::new());
     }
 
-    scan_node(&source, tree, path)
+    scan_node(source, tree, path)
 }
 
 /// first is [CMakeLists.txt], the second one is [*.cmake]
@@ -212,7 +291,7 @@ fn scan_node<P: AsRef<Path>>(
             let Some(first_arg) = command.first_arg else {
                 continue;
             };
-            let Some(file_name) = first_arg.try_replace_placeholders() else {
+            let Some(file_name) = first_arg.try_replace_placeholders(path.as_ref()) else {
                 continue;
             };
 
@@ -227,7 +306,7 @@ fn scan_node<P: AsRef<Path>>(
             let Some(first_arg) = command.first_arg else {
                 continue;
             };
-            let Some(file_name) = first_arg.try_replace_placeholders() else {
+            let Some(file_name) = first_arg.try_replace_placeholders(path.as_ref()) else {
                 continue;
             };
 
@@ -307,7 +386,7 @@ fn get_subdir_from_tree(source: &str, tree: tree_sitter::Node, parent: &Path) ->
             let Some(first_arg) = command.first_arg else {
                 continue;
             };
-            let Some(file_name) = first_arg.try_replace_placeholders() else {
+            let Some(file_name) = first_arg.try_replace_placeholders(parent) else {
                 continue;
             };
 
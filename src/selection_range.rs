@@ -0,0 +1,157 @@
+use tower_lsp::lsp_types::{Position, Range as LspRange, SelectionRange};
+use tree_sitter::Node;
+
+use crate::CMakeNodeKinds;
+use crate::document::Document;
+use crate::utils::query::{ToLspRange, ToQueryRange};
+use crate::utils::treehelper::BLOCK_KINDS;
+
+/// The nodes which are a step of the selection, from the inner to the outer
+fn is_selection_step(node: Node) -> bool {
+    let kind = node.kind();
+    kind.ends_with("_command")
+        || BLOCK_KINDS.contains(&kind)
+        || [
+            CMakeNodeKinds::IDENTIFIER,
+            CMakeNodeKinds::VARIABLE_REF,
+            CMakeNodeKinds::ARGUMENT,
+            CMakeNodeKinds::ARGUMENT_LIST,
+            CMakeNodeKinds::BODY,
+            CMakeNodeKinds::SOURCE_FILE,
+        ]
+        .contains(&kind)
+}
+
+pub fn get_selection_ranges(document: &Document, positions: &[Position]) -> Vec<SelectionRange> {
+    let root = document.tree().root_node();
+    positions
+        .iter()
+        .map(|position| get_selection_range(root, *position))
+        .collect()
+}
+
+fn get_selection_range(root: Node, position: Position) -> SelectionRange {
+    let query_range = LspRange {
+        start: position,
+        end: position,
+    }
+    .to_query_range();
+    let mut ranges: Vec<LspRange> = vec![];
+    let mut node = root.descendant_for_point_range(query_range.start(), query_range.end());
+    while let Some(current) = node {
+        let range = current.range().lsp_range();
+        // NOTE: the parent must contain the child, so the same range is only reported once
+        if is_selection_step(current) && ranges.last() != Some(&range) {
+            ranges.push(range);
+        }
+        node = current.parent();
+    }
+
+    let mut selection: Option<SelectionRange> = None;
+    for range in ranges.into_iter().rev() {
+        selection = Some(SelectionRange {
+            range,
+            parent: selection.map(Box::new),
+        });
+    }
+    // NOTE: the result must have one item for each position
+    selection.unwrap_or(SelectionRange {
+        range: LspRange {
+            start: position,
+            end: position,
+        },
+        parent: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(selection: &SelectionRange) -> Vec<(u32, u32, u32, u32)> {
+        let mut output = vec![];
+        let mut selection = Some(selection);
+        while let Some(current) = selection {
+            output.push((
+                current.range.start.line,
+                current.range.start.character,
+                current.range.end.line,
+                current.range.end.character,
+            ));
+            selection = current.parent.as_deref();
+        }
+        output
+    }
+
+    #[test]
+    fn test_selection_in_block() {
+        let source = r"function(hello)
+  if(A)
+    set(B 1 2)
+  endif()
+endfunction()
+";
+        let document = Document::new(source);
+        let selections = get_selection_ranges(
+            &document,
+            &[Position {
+                line: 2,
+                character: 10,
+            }],
+        );
+        assert_eq!(selections.len(), 1);
+        assert_eq!(
+            lines(&selections[0]),
+            vec![
+                // argument
+                (2, 10, 2, 11),
+                // argument list
+                (2, 8, 2, 13),
+                // command
+                (2, 4, 2, 14),
+                // body of if
+                (1, 7, 3, 2),
+                // if block
+                (1, 2, 3, 9),
+                // body of function
+                (0, 15, 4, 0),
+                // function
+                (0, 0, 4, 13),
+                // file
+                (0, 0, 5, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_selection_of_command_name() {
+        let document = Document::new("message(STATUS \"${A}\")\n");
+        let selections = get_selection_ranges(
+            &document,
+            &[
+                Position {
+                    line: 0,
+                    character: 2,
+                },
+                Position {
+                    line: 0,
+                    character: 18,
+                },
+            ],
+        );
+        assert_eq!(
+            lines(&selections[0]),
+            vec![(0, 0, 0, 7), (0, 0, 0, 22), (0, 0, 1, 0)]
+        );
+        assert_eq!(
+            lines(&selections[1]),
+            vec![
+                (0, 16, 0, 20),
+                (0, 15, 0, 21),
+                (0, 8, 0, 21),
+                (0, 0, 0, 22),
+                (0, 0, 1, 0)
+            ]
+        );
+    }
+}
@@ -1,13 +1,25 @@
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
 use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use tower_lsp::Client;
-use tower_lsp::lsp_types::{SemanticToken, SemanticTokenTypes, SemanticTokens};
+use tower_lsp::lsp_types::{
+    SemanticToken, SemanticTokenModifiers, SemanticTokenTypes, SemanticTokens, SemanticTokensDelta,
+    SemanticTokensEdit,
+};
 
+use crate::CMakeNodeKinds;
+use crate::complete::builtin::{is_builtin_command, is_builtin_variable};
 use crate::consts::TREESITTER_CMAKE_LANGUAGE;
-use crate::utils::query::{AstNode, AstNodeContainer};
+use crate::document::{Analysis, Document};
+use crate::fileapi;
+use crate::utils::query::{AstNode, ToQueryRange};
+use crate::utils::{LINK_KEYWORDS, TARGET_DEFINITION_COMMANDS};
 
-use tree_sitter::{Point, Query, QueryCursor, StreamingIterator};
+use tree_sitter::{Node, Point, Query, QueryCursor, StreamingIterator};
 
 const NONE_TYPE: &str = "none";
 
@@ -28,6 +40,46 @@ pub const LEGEND_TYPE: &[SemanticTokenTypes] = &[
     SemanticTokenTypes::Parameter,  // index 9
     SemanticTokenTypes::EnumMember, // index 10
     NONE_SEMANTIC_TOKEN,            // index 11
+    SemanticTokenTypes::Macro,      // index 12, generator expressions
+    SemanticTokenTypes::Property,   // index 13
+    SemanticTokenTypes::Class,      // index 14, targets
+];
+
+pub const LEGEND_MODIFIERS: &[SemanticTokenModifiers] = &[
+    SemanticTokenModifiers::Definition,     // bit 0
+    SemanticTokenModifiers::Readonly,       // bit 1
+    SemanticTokenModifiers::Deprecated,     // bit 2
+    SemanticTokenModifiers::DefaultLibrary, // bit 3
+];
+
+/// The commands in the deprecated section of `cmake-commands(7)`
+const DEPRECATED_COMMANDS: &[&str] = &[
+    "build_name",
+    "exec_program",
+    "export_library_dependencies",
+    "install_files",
+    "install_programs",
+    "install_targets",
+    "load_command",
+    "make_directory",
+    "output_required_files",
+    "qt_wrap_cpp",
+    "qt_wrap_ui",
+    "remove",
+    "subdir_depends",
+    "subdirs",
+    "use_mangled_mesa",
+    "utility_source",
+    "variable_requires",
+    "write_file",
+];
+
+/// The commands which set properties as `PROPERTIES <name> <value> ...`
+const PROPERTIES_COMMANDS: &[&str] = &[
+    "set_target_properties",
+    "set_source_files_properties",
+    "set_tests_properties",
+    "set_directory_properties",
 ];
 
 fn get_token_position(tokentype: SemanticTokenTypes) -> u32 {
@@ -37,6 +89,13 @@ fn get_token_position(tokentype: SemanticTokenTypes) -> u32 {
         .unwrap() as u32
 }
 
+fn get_modifier_bit(modifier: SemanticTokenModifiers) -> u32 {
+    1 << LEGEND_MODIFIERS
+        .iter()
+        .position(|data| *data == modifier)
+        .unwrap()
+}
+
 static NUMBERREGEX: LazyLock<regex::Regex> =
     LazyLock::new(|| regex::Regex::new(r"^\d+(?:\.+\d*)?$").unwrap());
 
@@ -48,11 +107,6 @@ trait NodeGetToken {
     fn hl_token_index(&self, source: &str) -> u32 {
         get_token_position(self.hl_token(source))
     }
-    fn get_semantic_token(&self, cursor: &mut Point, source: &str) -> SemanticToken;
-}
-
-trait ContainerGetTokens {
-    fn get_semantic_tokens(&self, source: &str) -> Vec<SemanticToken>;
 }
 
 impl<'a> NodeGetToken for AstNode<'a> {
@@ -106,12 +160,32 @@ impl<'a> NodeGetToken for AstNode<'a> {
         }
         NONE_SEMANTIC_TOKEN
     }
+}
+
+/// A token with its range in the document, before it is made relative to the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HlToken {
+    range: tree_sitter::Range,
+    token_type: u32,
+    modifiers: u32,
+}
+
+impl HlToken {
+    fn new(range: tree_sitter::Range, token_type: SemanticTokenTypes) -> Self {
+        Self {
+            range,
+            token_type: get_token_position(token_type),
+            modifiers: 0,
+        }
+    }
 
-    fn get_semantic_token(&self, cursor: &mut Point, source: &str) -> SemanticToken {
-        let otoken = self.hl_token_index(source);
-        let range = self.range();
-        let start_byte = range.start_byte;
-        let end_byte = range.end_byte;
+    fn with_modifier(mut self, modifier: SemanticTokenModifiers) -> Self {
+        self.modifiers |= get_modifier_bit(modifier);
+        self
+    }
+
+    const fn get_semantic_token(&self, cursor: &mut Point) -> SemanticToken {
+        let range = self.range;
         let start_point = range.start_point;
         if start_point.row > cursor.row {
             cursor.column = 0;
@@ -120,79 +194,597 @@ impl<'a> NodeGetToken for AstNode<'a> {
         let token = SemanticToken {
             delta_line: (start_point.row - cursor.row) as u32,
             delta_start: (start_point.column - cursor.column) as u32,
-            length: (end_byte - start_byte) as u32,
-            token_type: otoken,
-            token_modifiers_bitset: 0,
+            length: (range.end_byte - range.start_byte) as u32,
+            token_type: self.token_type,
+            token_modifiers_bitset: self.modifiers,
         };
         *cursor = start_point;
         token
     }
 }
 
-impl<'a> ContainerGetTokens for AstNodeContainer<'a> {
-    fn get_semantic_tokens(&self, source: &str) -> Vec<SemanticToken> {
-        assert!(self.nodes.is_sorted());
-        let mut cursor = Point::new(0, 0);
-        let mut tokens = vec![];
-        for node in &self.nodes {
-            tokens.push(node.get_semantic_token(&mut cursor, source));
+/// What is known about the document when choosing the modifiers and the targets
+struct TokenContext<'a> {
+    source: &'a str,
+    /// The cache variables set in the document or found in the CMake cache
+    cache_variables: HashSet<String>,
+    /// The targets defined in the document or found by the file api
+    targets: HashSet<String>,
+}
+
+/// A command, with the lowercase identifier and the arguments
+struct CommandParts<'a> {
+    identifier: String,
+    args: Vec<Node<'a>>,
+}
+
+impl<'a> CommandParts<'a> {
+    fn new(command: Node<'a>, source: &str) -> Option<Self> {
+        let keyword = command.child(0)?;
+        let identifier = keyword.utf8_text(source.as_bytes()).ok()?.to_lowercase();
+        let mut walk = command.walk();
+        let args = command
+            .named_children(&mut walk)
+            .find(|child| child.kind() == CMakeNodeKinds::ARGUMENT_LIST)
+            .map(|list| {
+                let mut walk = list.walk();
+                list.named_children(&mut walk).collect()
+            })
+            .unwrap_or_default();
+        Some(Self { identifier, args })
+    }
+
+    fn text(&self, index: usize, source: &'a str) -> Option<&'a str> {
+        self.args.get(index)?.utf8_text(source.as_bytes()).ok()
+    }
+}
+
+/// The commands which overlap the range, the nodes outside of it are not walked
+fn all_commands<'a>(node: Node<'a>, range: Option<&Range<Point>>, output: &mut Vec<Node<'a>>) {
+    if let Some(range) = range
+        && (node.end_position() <= range.start || node.start_position() >= range.end)
+    {
+        return;
+    }
+    if node.kind().ends_with("_command") {
+        output.push(node);
+    }
+    let mut walk = node.walk();
+    for child in node.children(&mut walk) {
+        all_commands(child, range, output);
+    }
+}
+
+impl<'a> TokenContext<'a> {
+    /// NOTE: the definitions are taken from the whole document, even for the tokens of a range
+    fn new(analysis: &Analysis, source: &'a str, path: &Path) -> Self {
+        let mut cache_variables = HashSet::new();
+        let mut targets = HashSet::new();
+        if let Some(entries) = fileapi::get_entries_data(path) {
+            cache_variables.extend(entries.into_keys());
+        }
+        if let Some(project_targets) = fileapi::get_targets(path) {
+            targets.extend(project_targets.into_keys());
+        }
+        for command in &analysis.commands {
+            let Some(first) = command.first_arg() else {
+                continue;
+            };
+            let identifier = command.identifier.to_lowercase();
+            let is_cache = match identifier.as_str() {
+                "option" => true,
+                "set" => command
+                    .args
+                    .iter()
+                    .skip(1)
+                    .any(|arg| arg.content == "CACHE"),
+                id if TARGET_DEFINITION_COMMANDS.contains(&id) => {
+                    targets.insert(first.to_string());
+                    false
+                }
+                _ => false,
+            };
+            if is_cache {
+                cache_variables.insert(first.to_string());
+            }
+        }
+        Self {
+            source,
+            cache_variables,
+            targets,
         }
-        tokens
+    }
+
+    fn is_target(&self, name: &str) -> bool {
+        self.targets.contains(name) || name.contains("::")
+    }
+
+    /// Choose the modifiers of a token found by the highlight query
+    fn capture_modifiers(&self, ast_node: &AstNode) -> u32 {
+        let node = ast_node.node;
+        let Ok(text) = node.utf8_text(self.source.as_bytes()) else {
+            return 0;
+        };
+        let mut modifiers = 0;
+        match node.kind() {
+            CMakeNodeKinds::IDENTIFIER => {
+                if DEPRECATED_COMMANDS.contains(&text.to_lowercase().as_str()) {
+                    modifiers |= get_modifier_bit(SemanticTokenModifiers::Deprecated)
+                        | get_modifier_bit(SemanticTokenModifiers::DefaultLibrary);
+                }
+                if ast_node.names.contains(&"function.builtin") || is_builtin_command(text) {
+                    modifiers |= get_modifier_bit(SemanticTokenModifiers::DefaultLibrary);
+                }
+            }
+            CMakeNodeKinds::VARIABLE => {
+                if is_builtin_variable(text) {
+                    modifiers |= get_modifier_bit(SemanticTokenModifiers::DefaultLibrary);
+                }
+                let is_cache_ref = node
+                    .parent()
+                    .is_some_and(|parent| parent.kind() == CMakeNodeKinds::CACHE_VAR);
+                if is_cache_ref || self.cache_variables.contains(text) {
+                    modifiers |= get_modifier_bit(SemanticTokenModifiers::Readonly);
+                }
+            }
+            CMakeNodeKinds::ARGUMENT => {
+                let Some(command) = node
+                    .parent()
+                    .and_then(|list| (list.named_child(0) == Some(node)).then(|| list.parent())?)
+                else {
+                    return 0;
+                };
+                let identifier = CommandParts::new(command, self.source)
+                    .map(|command| command.identifier)
+                    .unwrap_or_default();
+                match (command.kind(), identifier.as_str()) {
+                    (CMakeNodeKinds::FUNCTION_COMMAND | CMakeNodeKinds::MACRO_COMMAND, _) => {
+                        modifiers |= get_modifier_bit(SemanticTokenModifiers::Definition);
+                    }
+                    (CMakeNodeKinds::NORMAL_COMMAND, "set" | "option") => {
+                        modifiers |= get_modifier_bit(SemanticTokenModifiers::Definition)
+                            | self.cache_modifier(text);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        modifiers
+    }
+
+    fn cache_modifier(&self, name: &str) -> u32 {
+        if self.cache_variables.contains(name) {
+            get_modifier_bit(SemanticTokenModifiers::Readonly)
+        } else {
+            0
+        }
+    }
+
+    /// The target and property names in the arguments of the commands
+    fn command_tokens(&self, command: &CommandParts, output: &mut Vec<HlToken>) {
+        let target = |arg: Node| HlToken::new(arg.range(), SemanticTokenTypes::Class);
+        let property = |arg: Node| HlToken::new(arg.range(), SemanticTokenTypes::Property);
+        let text = |index: usize| command.text(index, self.source).unwrap_or_default();
+        let identifier = command.identifier.as_str();
+        let start = output.len();
+        match identifier {
+            id if TARGET_DEFINITION_COMMANDS.contains(&id) => {
+                if let Some(arg) = command.args.first() {
+                    output.push(target(*arg).with_modifier(SemanticTokenModifiers::Definition));
+                }
+            }
+            "add_dependencies" => output.extend(command.args.iter().map(|arg| target(*arg))),
+            "target_link_libraries" => {
+                for (index, arg) in command.args.iter().enumerate() {
+                    let name = text(index);
+                    if index == 0 || (!LINK_KEYWORDS.contains(&name) && self.is_target(name)) {
+                        output.push(target(*arg));
+                    }
+                }
+            }
+            id if id.starts_with("target_") => {
+                if let Some(arg) = command.args.first() {
+                    output.push(target(*arg));
+                }
+            }
+            "if" | "elseif" | "while" | "add_custom_command" => {
+                for index in 1..command.args.len() {
+                    if text(index - 1) == "TARGET" {
+                        output.push(target(command.args[index]));
+                    }
+                }
+            }
+            "set_property" | "get_property" | "define_property" => {
+                for index in 1..command.args.len() {
+                    if text(index - 1) == "PROPERTY" {
+                        output.push(property(command.args[index]));
+                    }
+                }
+            }
+            "get_target_property" | "get_source_file_property" => {
+                if identifier == "get_target_property"
+                    && let Some(arg) = command.args.get(1)
+                {
+                    output.push(target(*arg));
+                }
+                if let Some(arg) = command.args.get(2) {
+                    output.push(property(*arg));
+                }
+            }
+            "get_cmake_property" | "get_test_property" => {
+                if let Some(arg) = command.args.get(1) {
+                    output.push(property(*arg));
+                }
+            }
+            id if PROPERTIES_COMMANDS.contains(&id) => {
+                let Some(start) =
+                    (0..command.args.len()).find(|index| text(*index) == "PROPERTIES")
+                else {
+                    return;
+                };
+                if id == "set_target_properties" {
+                    output.extend(command.args[..start].iter().map(|arg| target(*arg)));
+                }
+                // NOTE: PROPERTIES <name> <value> <name> <value> ...
+                output.extend(
+                    command.args[start + 1..]
+                        .iter()
+                        .step_by(2)
+                        .map(|arg| property(*arg)),
+                );
+            }
+            _ => {}
+        }
+        // NOTE: the names made of variables or generator expressions keep their own tokens
+        let added = output.split_off(start);
+        output.extend(added.into_iter().filter(|token| {
+            !self.source[token.range.start_byte..token.range.end_byte].contains('$')
+        }));
     }
 }
 
-trait HlContainerInsert<'a> {
-    fn hl_insert_node(&mut self, ast_node: AstNode<'a>);
+/// Get the point of a byte offset in the text of the node
+fn offset_point(node: Node, text: &str, offset: usize) -> Point {
+    let before = &text[..offset];
+    let start = node.start_position();
+    match before.rfind('\n') {
+        Some(newline) => Point::new(
+            start.row + before.matches('\n').count(),
+            offset - newline - 1,
+        ),
+        None => Point::new(start.row, start.column + offset),
+    }
+}
 
-    fn sort_hlnode(&mut self);
+fn text_range(node: Node, text: &str, start: usize, end: usize) -> tree_sitter::Range {
+    tree_sitter::Range {
+        start_byte: node.start_byte() + start,
+        end_byte: node.start_byte() + end,
+        start_point: offset_point(node, text, start),
+        end_point: offset_point(node, text, end),
+    }
 }
 
-impl<'a> HlContainerInsert<'a> for AstNodeContainer<'a> {
-    fn sort_hlnode(&mut self) {
-        self.nodes.sort();
+/// Find the generator expressions in an argument, like `$<TARGET_FILE:app>`
+///
+/// The `$<NAME` part and the closing `>` are tokens, so the nested generator expressions and the
+/// variables inside keep their own tokens. The targets and properties given to the `TARGET_`
+/// expressions are tokens too.
+fn genex_tokens(arg: Node, source: &str, output: &mut Vec<HlToken>) {
+    let Ok(text) = arg.utf8_text(source.as_bytes()) else {
+        return;
+    };
+    let bytes = text.as_bytes();
+    let mut starts = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'$' && bytes.get(index + 1) == Some(&b'<') {
+            starts.push(index);
+            index += 2;
+            continue;
+        }
+        if bytes[index] == b'>'
+            && let Some(start) = starts.pop()
+        {
+            let content = &text[start + 2..index];
+            let name_end = content.find([':', '$']).unwrap_or(content.len());
+            let name = &content[..name_end];
+            output.push(HlToken::new(
+                text_range(arg, text, start, start + 2 + name_end),
+                SemanticTokenTypes::Macro,
+            ));
+            output.push(HlToken::new(
+                text_range(arg, text, index, index + 1),
+                SemanticTokenTypes::Macro,
+            ));
+            if name.starts_with("TARGET_")
+                && name != "TARGET_POLICY"
+                && let Some(parameters) = content[name_end..].strip_prefix(':')
+            {
+                let offset = start + 2 + name_end + 1;
+                let mut parts = vec![];
+                let mut part_start = 0;
+                for part in parameters.split(',') {
+                    parts.push((offset + part_start, part));
+                    part_start += part.len() + 1;
+                }
+                let types = match (name, parts.len()) {
+                    ("TARGET_PROPERTY", 1) => vec![SemanticTokenTypes::Property],
+                    ("TARGET_PROPERTY", _) => {
+                        vec![SemanticTokenTypes::Class, SemanticTokenTypes::Property]
+                    }
+                    _ => vec![SemanticTokenTypes::Class],
+                };
+                for ((part_start, part), token_type) in parts.into_iter().zip(types) {
+                    // NOTE: the nested expressions and variables are not names
+                    if part.is_empty() || part.contains(['$', '>']) {
+                        continue;
+                    }
+                    output.push(HlToken::new(
+                        text_range(arg, text, part_start, part_start + part.len()),
+                        token_type,
+                    ));
+                }
+            }
+        }
+        index += 1;
     }
-    fn hl_insert_node(&mut self, ast_node: AstNode<'a>) {
-        self.nodes.push(ast_node);
+}
+
+/// Split the tokens which contain other tokens, so the tokens do not overlap
+///
+/// A quoted argument like `"lib ${NAME}"` is cut around the variable reference.
+fn split_tokens(mut tokens: Vec<HlToken>) -> Vec<HlToken> {
+    tokens.sort_by(|a, b| {
+        a.range
+            .start_byte
+            .cmp(&b.range.start_byte)
+            .then(b.range.end_byte.cmp(&a.range.end_byte))
+    });
+    // NOTE: the tokens added later take the place of the ones with the same range
+    tokens.dedup_by(|later, earlier| {
+        if later.range == earlier.range {
+            *earlier = *later;
+            true
+        } else {
+            false
+        }
+    });
+    let mut result = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        let inner = tokens[index + 1..]
+            .iter()
+            .take_while(|inner| inner.range.start_byte < token.range.end_byte)
+            .filter(|inner| inner.range.end_byte <= token.range.end_byte);
+        let mut start_byte = token.range.start_byte;
+        let mut start_point = token.range.start_point;
+        for inner in inner {
+            if inner.range.start_byte > start_byte {
+                result.push(HlToken {
+                    range: tree_sitter::Range {
+                        start_byte,
+                        end_byte: inner.range.start_byte,
+                        start_point,
+                        end_point: inner.range.start_point,
+                    },
+                    ..*token
+                });
+            }
+            if inner.range.end_byte > start_byte {
+                start_byte = inner.range.end_byte;
+                start_point = inner.range.end_point;
+            }
+        }
+        if start_byte < token.range.end_byte {
+            result.push(HlToken {
+                range: tree_sitter::Range {
+                    start_byte,
+                    start_point,
+                    ..token.range
+                },
+                ..*token
+            });
+        }
     }
+    result.sort_by_key(|token| token.range.start_byte);
+    result
+}
+
+/// Every full result gets a new id, so the client can ask for the delta since it
+static RESULT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn next_result_id() -> String {
+    RESULT_ID.fetch_add(1, Ordering::Relaxed).to_string()
 }
 
-pub async fn semantic_token(_client: &Client, context: &str) -> Option<SemanticTokens> {
-    let mut parse = tree_sitter::Parser::new();
-    parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-    let thetree = parse.parse(context, None);
-    let tree = thetree?;
+pub async fn semantic_token(
+    _client: &Client,
+    document: &Document,
+    path: &Path,
+) -> Option<SemanticTokens> {
     Some(SemanticTokens {
-        result_id: None,
-        data: get_tokens(tree.root_node(), context),
+        result_id: Some(next_result_id()),
+        data: get_tokens(document, path, None),
     })
 }
 
-fn get_tokens(node: tree_sitter::Node, source: &str) -> Vec<SemanticToken> {
+pub fn semantic_token_range(
+    document: &Document,
+    path: &Path,
+    range: tower_lsp::lsp_types::Range,
+) -> SemanticTokens {
+    SemanticTokens {
+        result_id: None,
+        data: get_tokens(document, path, Some(range)),
+    }
+}
+
+/// Get the edits from the previous tokens to the current ones
+///
+/// The tokens are relative to each other, so the unchanged tokens at the start and at the end
+/// are kept and the middle is replaced in one edit.
+pub fn semantic_token_delta(
+    previous: &SemanticTokens,
+    current: &SemanticTokens,
+) -> SemanticTokensDelta {
+    let (old, new) = (&previous.data, &current.data);
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let edits = if prefix == old.len() && prefix == new.len() {
+        vec![]
+    } else {
+        let data = new[prefix..new.len() - suffix]
+            .iter()
+            .flat_map(|token| {
+                [
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                ]
+            })
+            .collect();
+        // NOTE: the edits count the integers, a token is made of five of them
+        vec![SemanticTokensEdit {
+            start: (prefix * 5) as u32,
+            delete_count: ((old.len() - prefix - suffix) * 5) as u32,
+            data: Some(data),
+        }]
+    };
+    SemanticTokensDelta {
+        result_id: current.result_id.clone(),
+        edits,
+    }
+}
+
+/// Remove the tokens inside the added ones, like the `$` of `$<`, the tokens with the same range
+/// are replaced later
+fn remove_covered_tokens(tokens: &mut Vec<HlToken>, extra_tokens: &[HlToken]) {
+    let mut covers: Vec<(usize, usize)> = extra_tokens
+        .iter()
+        .map(|token| (token.range.start_byte, token.range.end_byte))
+        .collect();
+    covers.sort_unstable();
+    tokens.sort_by_key(|token| token.range.start_byte);
+    // NOTE: the tokens and the covers are swept in order, `reach` is the end of the covers which
+    // start before the token
+    let mut next = 0;
+    let mut reach = 0;
+    tokens.retain(|token| {
+        let (start, end) = (token.range.start_byte, token.range.end_byte);
+        while next < covers.len() && covers[next].0 < start {
+            reach = reach.max(covers[next].1);
+            next += 1;
+        }
+        let same_start_reach = covers[next..]
+            .iter()
+            .take_while(|cover| cover.0 == start)
+            .last()
+            .map(|cover| cover.1);
+        reach < end && same_start_reach.is_none_or(|cover_end| cover_end <= end)
+    });
+}
+
+fn get_tokens(
+    document: &Document,
+    path: &Path,
+    range: Option<tower_lsp::lsp_types::Range>,
+) -> Vec<SemanticToken> {
+    let node = document.tree().root_node();
+    let source = document.text();
     let query_source = tree_sitter_cmake::HIGHLIGHTS_QUERY;
     let query = Query::new(&TREESITTER_CMAKE_LANGUAGE, query_source).unwrap();
 
     let mut cursor = QueryCursor::new();
+    let point_range = range.map(|range| {
+        let range = range.to_query_range();
+        range.start()..range.end()
+    });
+    if let Some(point_range) = point_range.clone() {
+        cursor.set_point_range(point_range);
+    }
     let mut matches = cursor.matches(&query, node, source.as_bytes());
 
-    let mut container = AstNodeContainer::new();
+    // NOTE: a node can be captured by many patterns, keep all the names
+    let mut captured: Vec<AstNode> = vec![];
+    let mut captured_index: HashMap<usize, usize> = HashMap::new();
+    let mut ignored = vec![];
     let names = query.capture_names();
-    let mut ranges = vec![];
     while let Some(m) = matches.next() {
         for e in m.captures {
-            if ranges.contains(&e.node.range()) {
-                continue;
-            }
-            ranges.push(e.node.range());
             let highlight = names[e.index as usize];
             if highlight == "none" {
+                ignored.push(e.node.range());
                 continue;
             }
-            let ast_node = AstNode::new(e.node, names[e.index as usize]);
-            container.hl_insert_node(ast_node);
+            if ignored.contains(&e.node.range()) {
+                continue;
+            }
+            match captured_index.get(&e.node.id()) {
+                Some(index) => captured[*index].names.push(highlight),
+                None => {
+                    captured_index.insert(e.node.id(), captured.len());
+                    captured.push(AstNode::new(e.node, highlight));
+                }
+            }
         }
     }
-    container.sort_hlnode();
-    container.get_semantic_tokens(source)
+
+    let mut commands = vec![];
+    all_commands(node, point_range.as_ref(), &mut commands);
+    let commands: Vec<CommandParts> = commands
+        .into_iter()
+        .filter_map(|command| CommandParts::new(command, source))
+        .collect();
+    let context = TokenContext::new(document.analysis(), source, path);
+
+    let mut tokens: Vec<HlToken> = captured
+        .iter()
+        .map(|ast_node| HlToken {
+            range: ast_node.node.range(),
+            token_type: ast_node.hl_token_index(source),
+            modifiers: context.capture_modifiers(ast_node),
+        })
+        .collect();
+    let mut extra_tokens = vec![];
+    for command in &commands {
+        context.command_tokens(command, &mut extra_tokens);
+        for arg in &command.args {
+            genex_tokens(*arg, source, &mut extra_tokens);
+        }
+    }
+    if let Some(point_range) = point_range {
+        extra_tokens.retain(|token| {
+            token.range.end_point > point_range.start && token.range.start_point < point_range.end
+        });
+    }
+    // NOTE: the modifiers found by the query are kept for the tokens which are replaced
+    let mut captured_modifiers: HashMap<tree_sitter::Range, u32> = HashMap::new();
+    for captured in &tokens {
+        captured_modifiers
+            .entry(captured.range)
+            .or_insert(captured.modifiers);
+    }
+    for token in &mut extra_tokens {
+        if let Some(modifiers) = captured_modifiers.get(&token.range) {
+            token.modifiers |= modifiers;
+        }
+    }
+    remove_covered_tokens(&mut tokens, &extra_tokens);
+    tokens.append(&mut extra_tokens);
+
+    let mut cursor = Point::new(0, 0);
+    split_tokens(tokens)
+        .iter()
+        .map(|token| token.get_semantic_token(&mut cursor))
+        .collect()
 }
 
 #[cfg(test)]
@@ -213,15 +805,11 @@ mod tests {
         assert!(!KEYWORDREGEX.is_match("Qt6::WaylandClient"));
     }
 
-    fn semantic_token_test(context: &str) -> Option<SemanticTokens> {
-        let mut parse = tree_sitter::Parser::new();
-        parse.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
-        let thetree = parse.parse(context, None);
-        let tree = thetree?;
-        Some(SemanticTokens {
+    fn semantic_token_test(context: &str) -> SemanticTokens {
+        SemanticTokens {
             result_id: None,
-            data: get_tokens(tree.root_node(), context),
-        })
+            data: get_tokens(&Document::new(context), Path::new("CMakeLists.txt"), None),
+        }
     }
 
     #[test]
@@ -229,7 +817,6 @@ mod tests {
         let tokens = semantic_token_test(include_str!(
             "../assets_for_test/highlight/bracket_argument.cmake"
         ))
-        .unwrap()
         .data;
         assert_eq!(
             tokens,
@@ -239,7 +826,9 @@ mod tests {
                     delta_start: 0,
                     length: 3,
                     token_type: get_token_position(SemanticTokenTypes::Function),
-                    token_modifiers_bitset: 0
+                    token_modifiers_bitset: get_modifier_bit(
+                        SemanticTokenModifiers::DefaultLibrary
+                    )
                 },
                 SemanticToken {
                     delta_line: 0,
@@ -253,12 +842,12 @@ mod tests {
                     delta_start: 1,
                     length: 1,
                     token_type: get_token_position(SemanticTokenTypes::Variable),
-                    token_modifiers_bitset: 0
+                    token_modifiers_bitset: get_modifier_bit(SemanticTokenModifiers::Definition)
                 },
                 SemanticToken {
                     delta_line: 0,
                     delta_start: 2,
-                    length: 17,
+                    length: 5,
                     token_type: get_token_position(SemanticTokenTypes::String),
                     token_modifiers_bitset: 0
                 },
@@ -292,7 +881,14 @@ mod tests {
                 },
                 SemanticToken {
                     delta_line: 0,
-                    delta_start: 6,
+                    delta_start: 1,
+                    length: 5,
+                    token_type: get_token_position(SemanticTokenTypes::String),
+                    token_modifiers_bitset: 0
+                },
+                SemanticToken {
+                    delta_line: 0,
+                    delta_start: 5,
                     length: 1,
                     token_type: get_token_position(SemanticTokenTypes::Operator),
                     token_modifiers_bitset: 0
@@ -300,4 +896,173 @@ mod tests {
             ]
         );
     }
+
+    fn tokens(source: &str) -> SemanticTokens {
+        semantic_token_test(source)
+    }
+
+    #[test]
+    fn test_range() {
+        let source = "set(A 1)\nmessage(STATUS ${A})\nset(B 2)\n";
+        let document = Document::new(source);
+        let range = tower_lsp::lsp_types::Range {
+            start: tower_lsp::lsp_types::Position {
+                line: 1,
+                character: 0,
+            },
+            end: tower_lsp::lsp_types::Position {
+                line: 1,
+                character: 20,
+            },
+        };
+        let data = semantic_token_range(&document, Path::new("CMakeLists.txt"), range).data;
+        // NOTE: the tokens are still relative to the start of the document
+        assert_eq!(data.first().unwrap().delta_line, 1);
+        assert!(data.iter().skip(1).all(|token| token.delta_line == 0));
+        assert_eq!(data.len(), 8);
+    }
+
+    #[test]
+    fn test_range_targets() {
+        let source =
+            "add_library(mylib a.cpp)\nset(A 1)\ntarget_link_libraries(app PRIVATE mylib)\n";
+        let document = Document::new(source);
+        let range = tower_lsp::lsp_types::Range {
+            start: tower_lsp::lsp_types::Position {
+                line: 2,
+                character: 0,
+            },
+            end: tower_lsp::lsp_types::Position {
+                line: 2,
+                character: 40,
+            },
+        };
+        let data = semantic_token_range(&document, Path::new("CMakeLists.txt"), range).data;
+        // NOTE: mylib is defined outside of the range, it is still a target
+        let targets: Vec<(u32, u32)> = data
+            .iter()
+            .filter(|token| token.token_type == get_token_position(SemanticTokenTypes::Class))
+            .map(|token| (token.delta_start, token.length))
+            .collect();
+        assert_eq!(targets, vec![(1, 3), (8, 5)]);
+    }
+
+    #[test]
+    fn test_delta() {
+        let previous = tokens("set(A 1)\nset(B 2)\nset(C 3)\n");
+        let current = tokens("set(A 1)\nmessage(B)\nset(C 3)\n");
+        let delta = semantic_token_delta(&previous, &current);
+        assert_eq!(delta.edits.len(), 1);
+        let edit = &delta.edits[0];
+        let mut data: Vec<u32> = previous
+            .data
+            .iter()
+            .flat_map(|token| {
+                [
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                ]
+            })
+            .collect();
+        let start = edit.start as usize;
+        data.splice(
+            start..start + edit.delete_count as usize,
+            edit.data.clone().unwrap(),
+        );
+        let expected: Vec<u32> = current
+            .data
+            .iter()
+            .flat_map(|token| {
+                [
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                ]
+            })
+            .collect();
+        assert_eq!(data, expected);
+
+        assert!(semantic_token_delta(&current, &current).edits.is_empty());
+    }
+
+    /// Get the text, the type and the modifiers of the tokens
+    fn token_texts(source: &str) -> Vec<(String, SemanticTokenTypes, u32)> {
+        let lines: Vec<&str> = source.lines().collect();
+        let (mut line, mut column) = (0, 0);
+        let mut result = vec![];
+        for token in tokens(source).data {
+            if token.delta_line > 0 {
+                column = 0;
+            }
+            line += token.delta_line as usize;
+            column += token.delta_start as usize;
+            let text = lines[line][column..column + token.length as usize].to_string();
+            result.push((
+                text,
+                LEGEND_TYPE[token.token_type as usize].clone(),
+                token.token_modifiers_bitset,
+            ));
+        }
+        result
+    }
+
+    #[test]
+    fn test_modifiers_and_types() {
+        let source = r#"option(ENABLE "enable" ON)
+add_library(lib STATIC lib.cpp)
+target_link_libraries(app PRIVATE lib pthread Qt6::Core)
+set_target_properties(app PROPERTIES OUTPUT_NAME main)
+add_custom_command(TARGET app COMMAND echo $<TARGET_FILE:app> $<$<CONFIG:Debug>:${DEBUG}>)
+message("${ENABLE}" ${CMAKE_CXX_STANDARD})
+exec_program(ls)
+"#;
+        let texts = token_texts(source);
+        let find = |text: &str, token_type: SemanticTokenTypes| {
+            texts
+                .iter()
+                .find(|(found, found_type, _)| found == text && *found_type == token_type)
+                .map(|(_, _, modifiers)| *modifiers)
+        };
+        let definition = get_modifier_bit(SemanticTokenModifiers::Definition);
+        let readonly = get_modifier_bit(SemanticTokenModifiers::Readonly);
+        let deprecated = get_modifier_bit(SemanticTokenModifiers::Deprecated);
+        let default_library = get_modifier_bit(SemanticTokenModifiers::DefaultLibrary);
+
+        assert_eq!(
+            find("option", SemanticTokenTypes::Function),
+            Some(default_library)
+        );
+        assert_eq!(
+            find("ENABLE", SemanticTokenTypes::EnumMember),
+            Some(definition | readonly)
+        );
+        assert_eq!(find("ENABLE", SemanticTokenTypes::Variable), Some(readonly));
+        assert_eq!(
+            find("CMAKE_CXX_STANDARD", SemanticTokenTypes::Variable),
+            Some(default_library)
+        );
+        assert_eq!(
+            find("exec_program", SemanticTokenTypes::Function),
+            Some(deprecated | default_library)
+        );
+
+        assert_eq!(find("lib", SemanticTokenTypes::Class), Some(definition));
+        assert_eq!(find("app", SemanticTokenTypes::Class), Some(0));
+        assert_eq!(find("Qt6::Core", SemanticTokenTypes::Class), Some(0));
+        assert_eq!(find("pthread", SemanticTokenTypes::Class), None);
+        assert_eq!(find("OUTPUT_NAME", SemanticTokenTypes::Property), Some(0));
+        assert_eq!(find("main", SemanticTokenTypes::Property), None);
+
+        assert_eq!(find("$<TARGET_FILE", SemanticTokenTypes::Macro), Some(0));
+        assert_eq!(find("$<CONFIG", SemanticTokenTypes::Macro), Some(0));
+        assert_eq!(find("$<", SemanticTokenTypes::Macro), Some(0));
+        assert_eq!(find("DEBUG", SemanticTokenTypes::Variable), Some(0));
+        // NOTE: the quoted argument is cut around the variable reference
+        assert_eq!(find("\"", SemanticTokenTypes::String), Some(0));
+    }
 }
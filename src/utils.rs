@@ -55,6 +55,23 @@ pub static BUILTIN_MODULE_CACHED_DIR: LazyLock<Option<PathBuf>> = LazyLock::new(
 
 pub static SERVER_VERSION: LazyLock<version::Version> = LazyLock::new(load_version_cache);
 
+/// The commands which create a target, the name of the target is their first argument
+pub const TARGET_DEFINITION_COMMANDS: &[&str] =
+    &["add_executable", "add_library", "add_custom_target"];
+
+/// The keywords of `target_link_libraries`, the other arguments can be targets
+pub const LINK_KEYWORDS: &[&str] = &[
+    "PUBLIC",
+    "PRIVATE",
+    "INTERFACE",
+    "LINK_PUBLIC",
+    "LINK_PRIVATE",
+    "LINK_INTERFACE_LIBRARIES",
+    "debug",
+    "optimized",
+    "general",
+];
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ServerVersion {
     version: String,
@@ -171,6 +188,17 @@ pub struct CMakePackage {
     pub from: CMakePackageFrom,
 }
 
+/// Get the deepest root which contains the path, so the nested projects own their files
+pub fn owning_root<'a, I>(roots: I, path: &Path) -> Option<&'a PathBuf>
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    roots
+        .into_iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+}
+
 pub fn include_is_module(file_name: &str) -> bool {
     !file_name.ends_with(".cmake")
 }
@@ -181,7 +209,9 @@ pub trait NeoStrExt {
 
     /// [NeoStrExt::try_replace_placeholders] should run [NeoStrExt::remove_quotation] first, and
     /// try to replace the placeholder, if cannot find the key, it should give up and return None
-    fn try_replace_placeholders(&self) -> Option<String>;
+    ///
+    /// The cache values are the ones of the project which owns the file at `path`
+    fn try_replace_placeholders(&self, path: &Path) -> Option<String>;
 }
 
 /// Some extension used in neocmakelsp for str and String
@@ -190,8 +220,8 @@ impl NeoStrExt for str {
         self.trim_matches('"')
     }
 
-    fn try_replace_placeholders(&self) -> Option<String> {
-        replace_placeholders(self.remove_quotation())
+    fn try_replace_placeholders(&self, path: &Path) -> Option<String> {
+        replace_placeholders(self.remove_quotation(), path)
     }
 }
 
@@ -200,19 +230,19 @@ impl NeoStrExt for String {
         self.trim_matches('"')
     }
 
-    fn try_replace_placeholders(&self) -> Option<String> {
-        replace_placeholders(self.remove_quotation())
+    fn try_replace_placeholders(&self, path: &Path) -> Option<String> {
+        replace_placeholders(self.remove_quotation(), path)
     }
 }
 
-pub fn replace_placeholders(template: &str) -> Option<String> {
+pub fn replace_placeholders(template: &str, path: &Path) -> Option<String> {
     if template.contains("$ENV{") {
         return replace_placeholders_with_env_map(template);
     }
     if !template.contains("${") {
         return Some(template.to_string());
     }
-    let values = fileapi::get_entries_data()?;
+    let values = fileapi::get_entries_data(path).unwrap_or_default();
     replace_placeholders_with_hashmap(template, &values)
 }
 
@@ -313,6 +343,23 @@ mod tests {
         assert!(!include_is_module("test.cmake"));
     }
 
+    #[test]
+    fn owning_root_test() {
+        let roots = [PathBuf::from("/work"), PathBuf::from("/work/nested")];
+        assert_eq!(
+            owning_root(&roots, Path::new("/work/nested/CMakeLists.txt")),
+            Some(&roots[1])
+        );
+        assert_eq!(
+            owning_root(&roots, Path::new("/work/nested2/CMakeLists.txt")),
+            Some(&roots[0])
+        );
+        assert_eq!(
+            owning_root(&roots, Path::new("/other/CMakeLists.txt")),
+            None
+        );
+    }
+
     #[test]
     fn env_arg_test() {
         unsafe {
@@ -320,7 +367,9 @@ mod tests {
         }
         assert_eq!(
             "/tmp/wezterm",
-            "$ENV{TempDir}/wezterm".try_replace_placeholders().unwrap()
+            "$ENV{TempDir}/wezterm"
+                .try_replace_placeholders(Path::new("CMakeLists.txt"))
+                .unwrap()
         );
     }
 
@@ -329,7 +378,7 @@ mod tests {
         assert_eq!(
             "./cli.rs",
             r"${CMAKE_CURRENT_LIST_DIR}/cli.rs"
-                .try_replace_placeholders()
+                .try_replace_placeholders(Path::new("CMakeLists.txt"))
                 .unwrap()
         );
     }
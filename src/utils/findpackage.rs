@@ -158,14 +158,10 @@ static CMAKECONFIGVERSION: LazyLock<regex::Regex> =
 
 pub trait FindPackageFunsTrait {
     fn get_cmake_packages(&self) -> Vec<CMakePackage> {
-        let mut cmake_packages = CMAKE_PACKAGES.clone();
-        cmake_packages.extend(VCPKG_CMAKE_PACKAGES.clone());
-        cmake_packages
+        CMAKE_PACKAGES.clone()
     }
     fn get_cmake_packages_withkeys(&self) -> HashMap<String, CMakePackage> {
-        let mut cmake_packages_keys = CMAKE_PACKAGES_WITHKEY.clone();
-        cmake_packages_keys.extend(VCPKG_CMAKE_PACKAGES_WITHKEY.clone());
-        cmake_packages_keys
+        CMAKE_PACKAGES_WITHKEY.clone()
     }
 
     #[cfg(unix)]
@@ -249,6 +245,18 @@ pub static CACHE_CMAKE_PACKAGES: LazyLock<Vec<CMakePackage>> = LazyLock::new(get
 pub static CACHE_CMAKE_PACKAGES_WITHKEYS: LazyLock<HashMap<String, CMakePackage>> =
     LazyLock::new(get_cmake_packages_withkeys);
 
+/// All the packages, with the ones installed by vcpkg in the workspace projects
+pub fn all_cmake_packages() -> Vec<CMakePackage> {
+    let mut packages = CACHE_CMAKE_PACKAGES.clone();
+    packages.extend(vcpkg_cmake_packages());
+    packages
+}
+
+/// Find a package by name, the ones installed by vcpkg in the workspace projects come first
+pub fn find_cmake_package(name: &str) -> Option<CMakePackage> {
+    find_vcpkg_cmake_package(name).or_else(|| CACHE_CMAKE_PACKAGES_WITHKEYS.get(name).cloned())
+}
+
 fn get_version(source: &[u8], parser: &mut Parser) -> Option<String> {
     let tree = parser.parse(source, None)?;
     let commands = query::get_normal_commands(source, tree.root_node(), None);
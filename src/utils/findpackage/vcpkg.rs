@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, LazyLock, Mutex};
+use std::sync::{LazyLock, Mutex};
 
 use super::{
     CMAKECONFIG, CMAKECONFIGVERSION, CMAKEREGEX, SPECIAL_PACKAGE_PATTERN, get_version,
@@ -16,11 +16,9 @@ pub fn did_vcpkg_project(path: &Path) -> bool {
     path.is_dir() && path.join("vcpkg.json").is_file()
 }
 
-pub static VCPKG_PREFIX: LazyLock<Arc<Mutex<Vec<&str>>>> =
-    LazyLock::new(|| Arc::new(Mutex::new([].to_vec())));
-
-pub static VCPKG_LIBS: LazyLock<Arc<Mutex<Vec<&str>>>> =
-    LazyLock::new(|| Arc::new(Mutex::new([].to_vec())));
+/// The packages installed by vcpkg, keyed by the root of each project
+static VCPKG_PACKAGES: LazyLock<Mutex<HashMap<PathBuf, HashMap<String, CMakePackage>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 #[cfg(not(windows))]
 fn safe_canonicalize(path: &Path) -> std::io::Result<PathBuf> {
@@ -33,31 +31,19 @@ fn safe_canonicalize(path: &Path) -> std::io::Result<PathBuf> {
     Ok(path.absolutize()?.into_owned())
 }
 
-fn get_available_libs() -> Vec<PathBuf> {
-    let mut ava: Vec<PathBuf> = vec![];
-    let vcpkg_prefix = VCPKG_PREFIX.lock().unwrap();
-    let vcpkg_libs = VCPKG_LIBS.lock().unwrap();
-    for prefix in vcpkg_prefix.iter() {
-        for lib in vcpkg_libs.iter() {
-            let p = Path::new(prefix).join(lib);
-            if p.exists() {
-                ava.push(p);
-            }
-        }
-    }
-    ava
+fn get_available_libs(prefix: &Path, libs: &[String]) -> Vec<PathBuf> {
+    libs.iter()
+        .map(|lib| prefix.join(lib))
+        .filter(|path| path.exists())
+        .collect()
 }
 
-fn get_cmake_message() -> HashMap<String, CMakePackage> {
+fn get_cmake_message(prefix: &Path, libs: &[String]) -> HashMap<String, CMakePackage> {
     let mut packages: HashMap<String, CMakePackage> = HashMap::new();
-    let vcpkg_prefix = VCPKG_PREFIX.lock().unwrap();
     let mut parser = tree_sitter::Parser::new();
     parser.set_language(&TREESITTER_CMAKE_LANGUAGE).unwrap();
 
-    for lib in vcpkg_prefix.iter() {
-        let Ok(paths) = glob::glob(&format!("{lib}/share/*/cmake/")) else {
-            continue;
-        };
+    if let Ok(paths) = glob::glob(&format!("{}/share/*/cmake/", prefix.to_string_lossy())) {
         for path in paths.flatten() {
             let Ok(files) = glob::glob(&format!("{}/*.cmake", path.to_string_lossy())) else {
                 continue;
@@ -123,8 +109,7 @@ fn get_cmake_message() -> HashMap<String, CMakePackage> {
             );
         }
     }
-    drop(vcpkg_prefix);
-    for lib in get_available_libs() {
+    for lib in get_available_libs(prefix, libs) {
         let Ok(paths) = std::fs::read_dir(lib) else {
             continue;
         };
@@ -230,10 +215,68 @@ pub fn make_vcpkg_package_search_path(search_path: &Path) -> Vec<String> {
     paths
 }
 
-pub static VCPKG_CMAKE_PACKAGES: LazyLock<Vec<CMakePackage>> =
-    LazyLock::new(|| get_cmake_message().into_values().collect());
-pub static VCPKG_CMAKE_PACKAGES_WITHKEY: LazyLock<HashMap<String, CMakePackage>> =
-    LazyLock::new(get_cmake_message);
+/// Search the packages installed by vcpkg for a project, its pkg-config files are searched first
+pub fn register_vcpkg_project(root: &Path) {
+    let installed_path = root.join("vcpkg_installed");
+    let Some(installed) = installed_path.to_str() else {
+        return;
+    };
+    if VCPKG_PACKAGES.lock().unwrap().contains_key(root) {
+        return;
+    }
+
+    #[cfg(unix)]
+    {
+        use crate::utils::packagepkgconfig::QUERYSRULES;
+        // NOTE: the rules are leaked, the projects are not added often
+        QUERYSRULES
+            .lock()
+            .unwrap()
+            .insert(0, Box::leak(format!("{installed}/*.pc").into_boxed_str()));
+    }
+
+    let libs = make_vcpkg_package_search_path(&installed_path);
+    let packages = get_cmake_message(&installed_path, &libs);
+    VCPKG_PACKAGES
+        .lock()
+        .unwrap()
+        .insert(root.to_path_buf(), packages);
+}
+
+/// Stop searching the packages of a project, when it is removed from the workspace
+pub fn unregister_vcpkg_project(root: &Path) {
+    let installed_path = root.join("vcpkg_installed");
+    let Some(installed) = installed_path.to_str() else {
+        return;
+    };
+    VCPKG_PACKAGES.lock().unwrap().remove(root);
+
+    #[cfg(unix)]
+    {
+        use crate::utils::packagepkgconfig::QUERYSRULES;
+        let rule = format!("{installed}/*.pc");
+        QUERYSRULES.lock().unwrap().retain(|query| *query != rule);
+    }
+}
+
+/// The packages installed by vcpkg in all the registered projects
+pub fn vcpkg_cmake_packages() -> Vec<CMakePackage> {
+    VCPKG_PACKAGES
+        .lock()
+        .unwrap()
+        .values()
+        .flat_map(|packages| packages.values().cloned())
+        .collect()
+}
+
+/// Find a package installed by vcpkg in one of the registered projects
+pub fn find_vcpkg_cmake_package(name: &str) -> Option<CMakePackage> {
+    VCPKG_PACKAGES
+        .lock()
+        .unwrap()
+        .values()
+        .find_map(|packages| packages.get(name).cloned())
+}
 
 // FIXME: I can not fix the unit test on macOS.
 // It always starts with `/private`.
@@ -255,20 +298,8 @@ mod tests {
 
         assert!(did_vcpkg_project(dir.path()));
 
-        let prefix_dir = safe_canonicalize(dir.path())
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        let mut prefix = VCPKG_PREFIX.lock().unwrap();
-
-        prefix.push(Box::leak(prefix_dir.into_boxed_str()));
-        drop(prefix);
-
-        let mut libs = VCPKG_LIBS.lock().unwrap();
-        libs.push("x64-linux");
-        libs.push("share/cmake");
-        drop(libs);
+        let prefix_dir = safe_canonicalize(dir.path()).unwrap();
+        let libs = vec!["x64-linux".to_string(), "share/cmake".to_string()];
 
         let share_path = dir.path().join("share");
 
@@ -324,6 +355,6 @@ mod tests {
                 },
             ),
         ]);
-        assert_eq!(get_cmake_message(), target);
+        assert_eq!(get_cmake_message(&prefix_dir, &libs), target);
     }
 }
@@ -42,6 +42,17 @@ pub struct QueryRange {
     start: Point,
     end: Point,
 }
+
+impl QueryRange {
+    pub const fn start(&self) -> Point {
+        self.start
+    }
+
+    pub const fn end(&self) -> Point {
+        self.end
+    }
+}
+
 pub trait ToQueryRange {
     fn to_query_range(self) -> QueryRange;
 }
@@ -277,18 +288,6 @@ pub struct MacroNode<'a> {
     pub arguments: Vec<Node<'a>>,
 }
 
-impl<'a> MacroNode<'a> {
-    pub fn args(&'a self, source: &'a [u8]) -> Vec<FunMarcoArg<'a>> {
-        let mut arg_strs = vec![];
-        for arg in self.arguments[1..].iter() {
-            arg_strs.push(FunMarcoArg {
-                node: *arg,
-                content: arg.utf8_text(source).unwrap(),
-            });
-        }
-        arg_strs
-    }
-}
 pub struct FuncNode<'a> {
     pub name: &'a str,
     pub name_node: Node<'a>,
@@ -296,26 +295,6 @@ pub struct FuncNode<'a> {
     pub arguments: Vec<Node<'a>>,
 }
 
-pub struct FunMarcoArg<'a> {
-    #[allow(unused)]
-    // I want to use it in jump
-    pub node: Node<'a>,
-    pub content: &'a str,
-}
-
-impl<'a> FuncNode<'a> {
-    pub fn args(&'a self, source: &'a [u8]) -> Vec<FunMarcoArg<'a>> {
-        let mut arg_strs = vec![];
-        for arg in self.arguments[1..].iter() {
-            arg_strs.push(FunMarcoArg {
-                node: *arg,
-                content: arg.utf8_text(source).unwrap(),
-            });
-        }
-        arg_strs
-    }
-}
-
 pub struct NormalCommandNode<'a> {
     pub node: Node<'a>,
     pub identifier: &'a str,
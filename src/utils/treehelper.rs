@@ -17,6 +17,96 @@ use crate::utils::{BUILTIN_MODULE_CACHED_DIR, CachedMessages, NeoStrExt, cache};
 
 const BLACK_POS_STRING: [&str; 5] = ["(", ")", "{", "}", "$"];
 
+/// The nodes of the blocks, which start with a command like `if()` and end with a command like
+/// `endif()`
+pub const BLOCK_KINDS: &[&str] = &[
+    CMakeNodeKinds::IF_CONDITION,
+    CMakeNodeKinds::FOREACH_LOOP,
+    CMakeNodeKinds::WHILE_LOOP,
+    CMakeNodeKinds::FUNCTION_DEF,
+    CMakeNodeKinds::MACRO_DEF,
+    CMakeNodeKinds::BLOCK_DEF,
+];
+
+/// The arguments that a closer, like `endif()`, may repeat from the opener of its block
+#[derive(Debug, Clone, Copy)]
+pub struct RepeatedArguments<'a> {
+    pub opener: Node<'a>,
+    pub closer: Node<'a>,
+    /// What the closer should repeat, the first argument or the whole argument list
+    pub expected: Node<'a>,
+    /// The arguments given to the closer
+    pub repeated: Option<Node<'a>>,
+}
+
+impl RepeatedArguments<'_> {
+    /// CMake compares the arguments, so the spaces between them do not matter
+    pub fn matches(&self, source: &str) -> bool {
+        let Some(repeated) = self.repeated else {
+            return true;
+        };
+        let text = |node: Node| {
+            node.utf8_text(source.as_bytes())
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+        };
+        text(self.expected) == text(repeated)
+    }
+}
+
+/// Get the arguments of a command, `endforeach()` and `endwhile()` take an argument and not an
+/// argument list
+fn argument_list(command: Node) -> Option<Node> {
+    let mut walk = command.walk();
+    command.children(&mut walk).find(|child| {
+        [CMakeNodeKinds::ARGUMENT_LIST, CMakeNodeKinds::ARGUMENT].contains(&child.kind())
+    })
+}
+
+/// Get the arguments of the opener that the closer of a block may repeat
+///
+/// `endfunction()`, `endmacro()` and `endforeach()` repeat the first argument of the opener, while
+/// `endif()` and `endwhile()` repeat the whole condition.
+pub fn repeated_arguments(block: Node) -> Option<RepeatedArguments> {
+    let mut walk = block.walk();
+    let commands: Vec<Node> = block
+        .children(&mut walk)
+        .filter(|child| child.kind().ends_with("_command"))
+        .collect();
+    let (opener, closer) = (*commands.first()?, *commands.last()?);
+    if opener == closer {
+        return None;
+    }
+    let opener_arguments = argument_list(opener)?;
+    let expected = match block.kind() {
+        CMakeNodeKinds::FUNCTION_DEF | CMakeNodeKinds::MACRO_DEF | CMakeNodeKinds::FOREACH_LOOP => {
+            opener_arguments.named_child(0)?
+        }
+        CMakeNodeKinds::IF_CONDITION | CMakeNodeKinds::WHILE_LOOP => opener_arguments,
+        _ => return None,
+    };
+    Some(RepeatedArguments {
+        opener,
+        closer,
+        expected,
+        repeated: argument_list(closer),
+    })
+}
+
+/// Find all the blocks under the node, the outer ones first
+pub fn all_repeated_arguments<'a>(node: Node<'a>, output: &mut Vec<RepeatedArguments<'a>>) {
+    if BLOCK_KINDS.contains(&node.kind())
+        && let Some(arguments) = repeated_arguments(node)
+    {
+        output.push(arguments);
+    }
+    let mut walk = node.walk();
+    for child in node.children(&mut walk) {
+        all_repeated_arguments(child, output);
+    }
+}
+
 /// treesitter to lsp_types
 #[inline]
 pub const fn point_to_position(input: Point) -> Position {
@@ -191,8 +281,12 @@ pub enum PositionType<'a> {
 }
 
 pub fn location_range_contain(location: Point, range_node: Node) -> bool {
-    let range_start_position = range_node.start_position();
-    let range_end_position = range_node.end_position();
+    location_in_range(location, range_node.range())
+}
+
+pub const fn location_in_range(location: Point, range: tree_sitter::Range) -> bool {
+    let range_start_position = range.start_point;
+    let range_end_position = range.end_point;
     if range_end_position.row < location.row || range_start_position.row > location.row {
         return false;
     }
@@ -233,7 +327,6 @@ pub fn contain_comment(location: Point, root: Node) -> bool {
 
 #[derive(Debug, Default)]
 pub struct CurrentNodeInfo<'a> {
-    #[allow(unused)]
     node: Option<Node<'a>>,
     typ: PositionType<'a>,
     content: Option<&'a str>,
@@ -249,6 +342,10 @@ impl<'a> CurrentNodeInfo<'a> {
         self.content.map(|content| content.remove_quotation())
     }
 
+    pub const fn node(&self) -> Option<Node<'a>> {
+        self.node
+    }
+
     pub fn is_first_argument(&'a self) -> bool {
         self.argument_index.is_some_and(|index| index == 0)
     }
@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+
+use dashmap::DashMap;
+use tower_lsp::lsp_types::{
+    BaseSymbolInformation, Location, SymbolKind, Uri, WorkspaceSymbol, WorkspaceSymbolResponse,
+};
+
+use crate::document::{Analysis, ArgumentIndex, Document};
+use crate::languageserver::read_document;
+use crate::scansubs::scanned_files;
+use crate::utils::TARGET_DEFINITION_COMMANDS;
+use crate::utils::query::ToLspRange;
+
+pub async fn workspace_symbols(
+    query: &str,
+    documents: &DashMap<Uri, Document>,
+) -> Option<WorkspaceSymbolResponse> {
+    let symbols = symbols_in(query, scanned_files().await, documents).await;
+    if symbols.is_empty() {
+        return None;
+    }
+    Some(WorkspaceSymbolResponse::WorkspaceSymbolList(symbols))
+}
+
+async fn symbols_in(
+    query: &str,
+    paths: Vec<PathBuf>,
+    documents: &DashMap<Uri, Document>,
+) -> Vec<WorkspaceSymbol> {
+    let mut symbols: Vec<(usize, WorkspaceSymbol)> = vec![];
+    for path in paths {
+        let Ok(document) = read_document(&path, documents).await else {
+            continue;
+        };
+        for symbol in file_symbols(document.analysis(), &path) {
+            if let Some(score) = fuzzy_score(query, &symbol.base_symbol_information.name) {
+                symbols.push((score, symbol));
+            }
+        }
+    }
+    symbols.sort_by(|(score_a, symbol_a), (score_b, symbol_b)| {
+        score_a.cmp(score_b).then_with(|| {
+            symbol_a
+                .base_symbol_information
+                .name
+                .cmp(&symbol_b.base_symbol_information.name)
+        })
+    });
+    symbols.into_iter().map(|(_, symbol)| symbol).collect()
+}
+
+/// The functions, macros, targets, options and cache variables defined in a file
+fn file_symbols(analysis: &Analysis, path: &Path) -> Vec<WorkspaceSymbol> {
+    let uri = Uri::from_file_path(path).unwrap();
+    let container_name = path.display().to_string();
+    let symbol = |name: &str, range: tree_sitter::Range, kind: SymbolKind| WorkspaceSymbol {
+        location: Location {
+            uri: uri.clone(),
+            range: range.lsp_range(),
+        }
+        .into(),
+        data: None,
+        base_symbol_information: BaseSymbolInformation {
+            name: name.to_string(),
+            kind,
+            tags: None,
+            container_name: Some(container_name.clone()),
+        },
+    };
+
+    let mut symbols = vec![];
+    for def in analysis.functions.iter().chain(analysis.macros.iter()) {
+        symbols.push(symbol(&def.name, def.name_range, SymbolKind::Function));
+    }
+    for command in &analysis.commands {
+        let Some(ArgumentIndex { content, range }) = command.args.first() else {
+            continue;
+        };
+        let identifier = command.identifier.to_lowercase();
+        let kind = if TARGET_DEFINITION_COMMANDS.contains(&identifier.as_str()) {
+            SymbolKind::Object
+        } else if identifier == "option" {
+            SymbolKind::Boolean
+        } else if identifier == "set" && command.args.iter().any(|arg| arg.content == "CACHE") {
+            SymbolKind::Variable
+        } else {
+            continue;
+        };
+        symbols.push(symbol(content, *range, kind));
+    }
+    symbols
+}
+
+/// Match the query as a case insensitive subsequence of the name
+///
+/// The lower the score, the better the match, the score is the count of the skipped chars
+/// before and between the matched ones.
+fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut name_chars = name.chars().map(|c| c.to_ascii_lowercase());
+    for query_char in query.chars().map(|c| c.to_ascii_lowercase()) {
+        loop {
+            if name_chars.next()? == query_char {
+                break;
+            }
+            score += 1;
+        }
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(fuzzy_score("abc", "ABC"), Some(0));
+        assert_eq!(fuzzy_score("abc", "a_b_c"), Some(2));
+        assert_eq!(fuzzy_score("abc", "xabc"), Some(1));
+        assert_eq!(fuzzy_score("abc", "acb"), None);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_symbols() {
+        let dir = tempdir().unwrap();
+        let top_path = dir.path().join("CMakeLists.txt");
+        let sub_path = dir.path().join("sub").join("CMakeLists.txt");
+        let documents = DashMap::new();
+        documents.insert(
+            Uri::from_file_path(&top_path).unwrap(),
+            Document::new(
+                r#"option(ENABLE_TESTS "enable tests" ON)
+set(INSTALL_DIR "/usr" CACHE PATH "install dir")
+set(LOCAL_VAR 1)
+function(add_test_helper)
+endfunction()
+add_subdirectory(sub)
+"#,
+            )
+            .with_version(1),
+        );
+        documents.insert(
+            Uri::from_file_path(&sub_path).unwrap(),
+            Document::new(
+                r"add_library(test_lib STATIC lib.cpp)
+add_executable(main main.cpp)
+macro(test_macro)
+endmacro()
+",
+            )
+            .with_version(1),
+        );
+
+        let symbols = symbols_in("tst", vec![top_path.clone(), sub_path.clone()], &documents).await;
+        let names: Vec<(&str, SymbolKind)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.base_symbol_information.name.as_str(),
+                    symbol.base_symbol_information.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("test_lib", SymbolKind::Object),
+                ("test_macro", SymbolKind::Function),
+                ("add_test_helper", SymbolKind::Function),
+                ("ENABLE_TESTS", SymbolKind::Boolean),
+            ]
+        );
+
+        let symbols = symbols_in("", vec![top_path, sub_path], &documents).await;
+        assert!(symbols.iter().any(|symbol| {
+            symbol.base_symbol_information.name == "INSTALL_DIR"
+                && symbol.base_symbol_information.kind == SymbolKind::Variable
+        }));
+        assert!(
+            !symbols
+                .iter()
+                .any(|symbol| symbol.base_symbol_information.name == "LOCAL_VAR")
+        );
+    }
+}